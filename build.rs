@@ -0,0 +1,24 @@
+//! Generates `rerun_query.h`, the C header of the `capi` feature, in `OUT_DIR`.
+//!
+//! The committed `include/rerun_query.h` is checked against it by `tests/capi.rs`, so that builds
+//! never write into the source tree.
+
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_header();
+}
+
+#[cfg(feature = "capi")]
+fn generate_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("set by cargo");
+    let out_dir = std::env::var("OUT_DIR").expect("set by cargo");
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::Builder::new()
+        .with_src(format!("{}/src/capi.rs", crate_dir))
+        .with_config(cbindgen::Config::from_root_or_default(&crate_dir))
+        .generate()
+        .expect("Unable to generate the C header")
+        .write_to_file(format!("{}/rerun_query.h", out_dir));
+}
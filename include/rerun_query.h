@@ -0,0 +1,131 @@
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#ifndef RERUN_QUERY_H
+#define RERUN_QUERY_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of a call.
+typedef enum RqStatus {
+  // The call succeeded.
+  RQ_STATUS_OK = 0,
+  // The file could not be opened or read.
+  RQ_STATUS_OPEN = 1,
+  // The file could not be decoded.
+  RQ_STATUS_DECODE = 2,
+  // The file holds no recording with data entities.
+  RQ_STATUS_STORE_NOT_FOUND = 3,
+  // No entity or timeline matches the arguments.
+  RQ_STATUS_NO_DATA = 4,
+  // An argument is invalid, e.g. a null pointer or a string that is not UTF-8.
+  RQ_STATUS_INVALID = 5,
+  // A buffer of the caller is too small.
+  RQ_STATUS_BUFFER_TOO_SMALL = 6,
+  // An output could not be written or encoded.
+  RQ_STATUS_WRITE = 7,
+  // The call panicked, which is a bug.
+  RQ_STATUS_PANIC = 8,
+  // The values of the entity have a type that is not supported.
+  RQ_STATUS_UNSUPPORTED_COMPONENT = 9,
+} RqStatus;
+
+// The type of the values of an entity.
+typedef enum RqDataType {
+  // One value per row.
+  RQ_DATA_TYPE_SCALAR = 0,
+  // `width` values per row, the flattened elements of a tensor.
+  RQ_DATA_TYPE_TENSOR = 1,
+} RqDataType;
+
+// An opened recording, with the values of its data entities.
+typedef struct RqRecording RqRecording;
+
+// The shape of the values of an entity.
+typedef struct RqEntityInfo {
+  // Whether the entity holds scalars or tensors.
+  enum RqDataType data_type;
+  // The number of rows.
+  size_t num_rows;
+  // The number of values per row: 1 for scalars, the number of tensor elements for tensors.
+  size_t width;
+} RqEntityInfo;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Open an RRD file and decode the scalar and tensor entities of its recording.
+//
+// On success, `*recording` is a handle to release with [`rq_close`].
+//
+// # Safety
+//
+// `file_path` must be a NUL-terminated string and `recording` a valid pointer.
+enum RqStatus rq_open(const char *file_path, struct RqRecording **recording);
+
+// Release a recording opened with [`rq_open`]. Does nothing if `recording` is null.
+//
+// # Safety
+//
+// `recording` must be null or a handle returned by [`rq_open`], not yet closed.
+void rq_close(struct RqRecording *recording);
+
+// The number of data entities of a recording, 0 if `recording` is null.
+//
+// # Safety
+//
+// `recording` must be null or a valid handle.
+size_t rq_entity_count(const struct RqRecording *recording);
+
+// The path of the data entity at `index`, sorted by path, or null if out of range.
+//
+// The string is owned by the recording and valid until [`rq_close`].
+//
+// # Safety
+//
+// `recording` must be null or a valid handle.
+const char *rq_entity_path(const struct RqRecording *recording, size_t index);
+
+// Get the type and the shape of the values of an entity, to size the buffers of
+// [`rq_read_column`].
+//
+// # Safety
+//
+// `recording` must be a valid handle, `entity_path` a NUL-terminated string and `info` a valid
+// pointer.
+enum RqStatus rq_entity_info(const struct RqRecording *recording,
+                             const char *entity_path,
+                             struct RqEntityInfo *info);
+
+// Copy the rows of an entity into buffers of the caller, in the order they were logged.
+//
+// `times` receives a time per row on `timeline`, in nanoseconds for temporal timelines and
+// `INT64_MIN` for rows without a time on the timeline. `values` receives `width` values per row,
+// NaN for missing values. Either buffer may be null to skip it. `capacity` is the number of rows
+// the buffers can hold, which must be at least the `num_rows` of [`rq_entity_info`].
+//
+// # Safety
+//
+// `recording` must be a valid handle, `entity_path` and `timeline` NUL-terminated strings, and
+// the buffers null or valid for `capacity` rows.
+enum RqStatus rq_read_column(const struct RqRecording *recording,
+                             const char *entity_path,
+                             const char *timeline,
+                             int64_t *times,
+                             double *values,
+                             size_t capacity);
+
+// The message of the last error of the calling thread, or an empty string.
+//
+// The string is valid until the next failing call on the thread.
+const char *rq_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RERUN_QUERY_H */
@@ -1,17 +1,103 @@
+from . import requery as _native
 from .requery import (
     query_data_entities,
+    query_data_entities_batch,
     query_meta_entities,
     list_entity_paths,
+    build_index,
+    clear_cache,
+    set_cache_budget,
+    export_parquet,
+    export_csv,
+    export_lerobot,
+    export_mcap,
+    import_mcap,
+    export_npz,
+    export_zarr,
+    export_tfrecord,
+    export_webdataset,
+    write_filtered_rrd,
+    merge_rrds,
+    split_rrd,
+    query_data_entities_async,
+    query_data_entities_batch_async,
+    query_meta_entities_async,
+    list_entity_paths_async,
+    iter_data_entities_async,
     DataChunk,
     MetaChunk,
     Data,
+    DataChunkStream,
+    RequeryError,
+    RrdOpenError,
+    RrdWriteError,
+    RrdDecodeError,
+    RrdEncodeError,
+    StoreNotFoundError,
+    NoMatchingDataError,
+    UnsupportedComponentError,
+    InvalidArgumentError,
 )
 
+
+
+def sql(file_path, query):
+    """
+    Run a SQL query over the entities of an RRD file.
+
+    Every entity is a table named by its entity path, with a column per timeline and component,
+    e.g. `SELECT avg("Scalar") FROM "/action/gripper" WHERE log_time > '2024-08-20T02:30:14'`.
+    Requires `pyarrow`, and a build with the `sql` feature.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        query (str): The SQL query.
+
+    Returns:
+        pyarrow.Table: The result of the query. Use `to_pandas()` for a DataFrame.
+    """
+    import pyarrow
+
+    return pyarrow.ipc.open_stream(_native.sql_ipc(file_path, query)).read_all()
+
+
 __all__ = [
     "query_data_entities",
+    "query_data_entities_batch",
     "query_meta_entities",
     "list_entity_paths",
+    "build_index",
+    "clear_cache",
+    "set_cache_budget",
+    "export_parquet",
+    "export_csv",
+    "export_lerobot",
+    "export_mcap",
+    "import_mcap",
+    "export_npz",
+    "export_zarr",
+    "export_tfrecord",
+    "export_webdataset",
+    "write_filtered_rrd",
+    "merge_rrds",
+    "split_rrd",
+    "sql",
+    "query_data_entities_async",
+    "query_data_entities_batch_async",
+    "query_meta_entities_async",
+    "list_entity_paths_async",
+    "iter_data_entities_async",
     "DataChunk",
     "MetaChunk",
     "Data",
+    "DataChunkStream",
+    "RequeryError",
+    "RrdOpenError",
+    "RrdWriteError",
+    "RrdDecodeError",
+    "RrdEncodeError",
+    "StoreNotFoundError",
+    "NoMatchingDataError",
+    "UnsupportedComponentError",
+    "InvalidArgumentError",
 ]
@@ -1,6 +1,46 @@
-from typing import Dict, List, Union
+from typing import AsyncIterator, Awaitable, Dict, List, Optional, Tuple, Union
 import numpy as np
 
+class RequeryError(Exception):
+    """
+    The base class of the errors of requery.
+
+    Attributes:
+        path (Optional[str]): The path of the RRD file being read, if known.
+        entity (Optional[str]): The path of the entity, or the entity filter of the query, if known.
+        component (Optional[str]): The full name of the component, e.g. `rerun.components.Scalar`, if known.
+        message_index (Optional[int]): The index of the message of the file that could not be decoded, if known.
+    """
+
+    path: Optional[str]
+    entity: Optional[str]
+    component: Optional[str]
+    message_index: Optional[int]
+
+class RrdOpenError(RequeryError, OSError):
+    """An input file could not be opened or read."""
+
+class RrdWriteError(RequeryError, OSError):
+    """An output file could not be written."""
+
+class RrdDecodeError(RequeryError, ValueError):
+    """An input file could not be decoded."""
+
+class RrdEncodeError(RequeryError, ValueError):
+    """The data could not be encoded in the output format."""
+
+class StoreNotFoundError(RequeryError, ValueError):
+    """The recording store to query was not found."""
+
+class NoMatchingDataError(RequeryError, ValueError):
+    """No data matches the filters."""
+
+class UnsupportedComponentError(RequeryError, ValueError):
+    """A component has a type that the operation doesn't support."""
+
+class InvalidArgumentError(RequeryError, ValueError):
+    """An argument is invalid, or the data doesn't allow the operation."""
+
 class DataChunk:
     """
     Represents a chunk of data associated with an entity path and timelines.
@@ -102,14 +142,20 @@ def query_data_entities(
     file_path: str,
     data_type_filter: str = "",
     entity_path_filter: str = "",
+    time_range: Optional[Tuple[int, int]] = None,
+    timeline: str = "log_time",
 ) -> List[DataChunk]:
     """
     Retrieve specific data (scalar or tensor) for entities in a specific RRD file.
 
+    If the file is indexed with `build_index`, only the chunks matching the filters are decoded.
+
     Args:
         file_path (str): The path to the RRD file.
         data_type_filter (str, optional): The data type to filter. Use "scalar" or "tensor", or "" for both. Defaults to "".
         entity_path_filter (str, optional): The specific entity path to filter. Use "" for all entities. Defaults to "".
+        time_range (Optional[Tuple[int, int]]): The `(start, end)` times to keep on `timeline`, in nanoseconds for temporal timelines, bounds included. Static chunks are always kept. `None` keeps all times.
+        timeline (str): The timeline of `time_range`.
 
     Returns:
         List[DataChunk]: A list of DataChunk objects.
@@ -117,6 +163,7 @@ def query_data_entities(
     Raises:
         IOError: If there's an issue reading the file.
         ValueError: If the file format is invalid or if an invalid data_type_filter is provided.
+        UnsupportedComponentError: If a matching chunk has a tensor type that cannot be converted.
 
     Example:
         >>> chunks = query_data_entities("/path/to/data.rrd", data_type_filter="tensor", entity_path_filter="entity1")
@@ -125,6 +172,88 @@ def query_data_entities(
     """
     ...
 
+def query_data_entities_batch(
+    file_paths: List[str],
+    data_type_filter: str = "",
+    entity_path_filter: str = "",
+    time_range: Optional[Tuple[int, int]] = None,
+    timeline: str = "log_time",
+    num_threads: int = 0,
+) -> List[Union[List[DataChunk], Exception]]:
+    """
+    Retrieve specific data (scalar or tensor) from many RRD files concurrently.
+
+    The files are processed by a pool of `num_threads` threads, each reading one file at a time,
+    without holding the GIL. A file that fails doesn't stop the others: its exception is returned
+    in place of its chunks.
+
+    Args:
+        file_paths (List[str]): The paths to the RRD files.
+        data_type_filter (str, optional): The data type to filter, see `query_data_entities`.
+        entity_path_filter (str, optional): The specific entity path to filter, see `query_data_entities`.
+        time_range (Optional[Tuple[int, int]]): The `(start, end)` times to keep on `timeline`, see `query_data_entities`.
+        timeline (str): The timeline of `time_range`.
+        num_threads (int): The number of files processed at once. Defaults to 0, one per core.
+
+    Returns:
+        List[Union[List[DataChunk], Exception]]: For every file, in order, its DataChunk objects
+        or the exception `query_data_entities` would raise for it.
+
+    Example:
+        >>> results = query_data_entities_batch(paths, data_type_filter="scalar", num_threads=8)
+        >>> for path, result in zip(paths, results):
+        ...     if isinstance(result, Exception):
+        ...         print(f"Skipping {path}: {result}")
+    """
+    ...
+
+def build_index(file_path: str) -> str:
+    """
+    Index the chunks of an RRD file, and write the index next to the file as `<file>.idx`.
+
+    Once a file is indexed, `query_data_entities` and `query_meta_entities` only decode the
+    chunks matching their filters instead of the whole file. The index is rebuilt by the next
+    query when the file is modified, and removing it restores full decoding.
+
+    Args:
+        file_path (str): The path to the RRD file.
+
+    Returns:
+        str: The path of the written index.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the index.
+        ValueError: If the RRD file can't be decoded.
+
+    Example:
+        >>> build_index("/path/to/file.rrd")
+        '/path/to/file.rrd.idx'
+    """
+    ...
+
+def clear_cache() -> None:
+    """
+    Drop every decoded recording of the cache.
+
+    Queries keep the recordings they decode in memory, keyed by file path, so repeated queries on
+    an unchanged file skip decoding. A file is decoded again when its size or modification time
+    changes.
+    """
+    ...
+
+def set_cache_budget(max_bytes: int) -> None:
+    """
+    Set the memory budget of the cache of decoded recordings, and evict the least recently used
+    recordings beyond it.
+
+    Args:
+        max_bytes (int): The estimated memory of the cached recordings, in bytes. Defaults to 1 GiB, and 0 disables the cache.
+
+    Example:
+        >>> set_cache_budget(4 << 30)  # 4 GiB
+    """
+    ...
+
 def query_meta_entities(file_path: str, entity_path: str = "") -> List[MetaChunk]:
     """
     Retrieve specific metadata for entities in a specific RRD file.
@@ -146,3 +275,538 @@ def query_meta_entities(file_path: str, entity_path: str = "") -> List[MetaChunk
         ...     print(f"Entity: {chunk.entity_path}, Media Type: {chunk.media_type}, Text: {chunk.text}")
     """
     ...
+
+class DataChunkStream(AsyncIterator[DataChunk]):
+    """
+    An async iterator over the DataChunk objects of a query, returned by `iter_data_entities_async`.
+
+    The chunks are converted on the thread pool of the awaitable functions and yielded as soon as
+    they are ready, with a few chunks converted ahead. The conversion stops when the iterator is
+    dropped or an awaited `__anext__` is cancelled, which ends the stream. An idle stream holds no
+    thread.
+    """
+
+    def __aiter__(self) -> "DataChunkStream": ...
+    def __anext__(self) -> Awaitable[DataChunk]: ...
+
+def query_data_entities_async(
+    file_path: str,
+    data_type_filter: str = "",
+    entity_path_filter: str = "",
+    time_range: Optional[Tuple[int, int]] = None,
+    timeline: str = "log_time",
+) -> Awaitable[List[DataChunk]]:
+    """
+    Awaitable version of `query_data_entities`, for asyncio.
+
+    Must be called from a running event loop. The file is read without the GIL on a pool of Rust
+    threads, one per core, and the returned future is resolved on the event loop when the query is
+    done, so neither the event loop nor its default executor are blocked meanwhile.
+
+    Example:
+        >>> chunks = await query_data_entities_async("/path/to/data.rrd", "scalar", "/action")
+    """
+    ...
+
+def query_data_entities_batch_async(
+    file_paths: List[str],
+    data_type_filter: str = "",
+    entity_path_filter: str = "",
+    time_range: Optional[Tuple[int, int]] = None,
+    timeline: str = "log_time",
+    num_threads: int = 0,
+) -> Awaitable[List[Union[List[DataChunk], Exception]]]:
+    """
+    Awaitable version of `query_data_entities_batch`, for asyncio.
+    """
+    ...
+
+def query_meta_entities_async(
+    file_path: str, entity_path: str = ""
+) -> Awaitable[List[MetaChunk]]:
+    """
+    Awaitable version of `query_meta_entities`, for asyncio.
+    """
+    ...
+
+def list_entity_paths_async(file_path: str) -> Awaitable[List[str]]:
+    """
+    Awaitable version of `list_entity_paths`, for asyncio.
+    """
+    ...
+
+def iter_data_entities_async(
+    file_path: str,
+    data_type_filter: str = "",
+    entity_path_filter: str = "",
+    time_range: Optional[Tuple[int, int]] = None,
+    timeline: str = "log_time",
+) -> DataChunkStream:
+    """
+    Stream the DataChunk objects of `query_data_entities` with `async for`, as they are converted.
+
+    The errors of the query, e.g. if the file can't be read or no chunk matches, are raised by
+    the first iteration, and the error of a chunk that can't be converted ends the stream.
+
+    Example:
+        >>> async for chunk in iter_data_entities_async("/path/to/data.rrd", "tensor"):
+        ...     print(chunk.entity_path)
+    """
+    ...
+
+def export_parquet(
+    file_path: str,
+    out_dir: str,
+    entity_path_filter: str = "",
+    compression: str = "zstd",
+) -> List[str]:
+    """
+    Export the data entities (scalar or tensor) of a specific RRD file to Parquet, one file per entity.
+
+    The files mirror the entity paths inside `out_dir`, e.g. `/action/joints/position` is written to
+    `<out_dir>/action/joints/position.parquet`. Each file has one column per timeline and one typed
+    column per component (`Scalar` as float64, `TensorData` as a list of the tensor's native type).
+
+    Args:
+        file_path (str): The path to the RRD file.
+        out_dir (str): The directory to write the Parquet files to. Created if missing.
+        entity_path_filter (str, optional): The specific entity path to filter. Use "" for all entities. Defaults to "".
+        compression (str, optional): The compression codec, "zstd", "snappy" or "none". Defaults to "zstd".
+
+    Returns:
+        List[str]: The paths of the written Parquet files.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the Parquet files.
+        ValueError: If the file format is invalid, no data matches the filter, or the compression is unknown.
+
+    Example:
+        >>> files = export_parquet("/path/to/data.rrd", "/path/to/out", entity_path_filter="/action")
+        >>> print(files)
+        ['/path/to/out/action/gripper/left/position.parquet', ...]
+    """
+    ...
+
+def export_csv(
+    file_path: str,
+    out_dir: str,
+    data_type_filter: str = "",
+    entity_path_filter: str = "",
+    delimiter: str = ",",
+    time_format: str = "raw",
+) -> List[str]:
+    """
+    Export the data entities (scalar or tensor) of a specific RRD file to CSV, one file per entity.
+
+    The header holds the timeline names followed by the value columns: `Scalar` for scalars, and one
+    column per tensor element for tensors, named after the tensor dimensions (e.g. `joint_0`) or
+    `TensorData_<i>` if they are unnamed. Every logged row becomes one CSV row.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        out_dir (str): The directory to write the CSV files to. Created if missing.
+        data_type_filter (str, optional): The data type to filter. Use "scalar" or "tensor", or "" for both. Defaults to "".
+        entity_path_filter (str, optional): The specific entity path to filter. Use "" for all entities. Defaults to "".
+        delimiter (str, optional): The field delimiter, a single ASCII character. Defaults to ",".
+        time_format (str, optional): How temporal timelines are written, "raw" (nanoseconds), "seconds" or "iso8601". Defaults to "raw".
+
+    Returns:
+        List[str]: The paths of the written CSV files.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the CSV files.
+        ValueError: If the file format is invalid, no data matches the filters, or an option is invalid.
+
+    Example:
+        >>> files = export_csv("/path/to/data.rrd", "/path/to/out", data_type_filter="scalar", time_format="seconds")
+    """
+    ...
+
+def export_lerobot(file_paths: List[str], out_dir: str, config: str) -> List[str]:
+    """
+    Export RRD recordings to a LeRobot dataset, one episode per file.
+
+    The configured entities are resampled to `fps` on a temporal timeline and written as
+    `data/chunk-XXX/episode_XXXXXX.parquet` with `timestamp`, `frame_index`, `episode_index`,
+    `index` and `task_index` columns, together with `meta/info.json`, `meta/episodes.jsonl`,
+    `meta/episodes_stats.jsonl` and `meta/tasks.jsonl`.
+
+    Scalar and tensor entities become float32 features. Encoded images (JPEG or PNG) become
+    `image` features stored inline in the parquet files.
+
+    Args:
+        file_paths (List[str]): The RRD files to export, in episode order.
+        out_dir (str): The root directory of the dataset. Created if missing.
+        config (str): The JSON mapping config, with the keys `fps`, `features` (LeRobot feature
+            key to entity path), and optionally `robot_type`, `timeline` (defaults to "log_time"),
+            `task`, `task_entity` (a meta entity holding the task text) and `chunks_size`.
+
+    Returns:
+        List[str]: The paths of the written episode parquet files.
+
+    Raises:
+        IOError: If there's an issue reading the RRD files or writing the dataset.
+        ValueError: If the config is invalid, or the configured entities are missing or inconsistent.
+
+    Example:
+        >>> config = {
+        ...     "fps": 30,
+        ...     "task": "Pick up the cube",
+        ...     "features": {
+        ...         "action": "/action/joints/position",
+        ...         "observation.state": "/observation/joints/position",
+        ...     },
+        ... }
+        >>> export_lerobot(["/path/to/episode_0.rrd", "/path/to/episode_1.rrd"], "/path/to/dataset", json.dumps(config))
+    """
+    ...
+
+def export_mcap(
+    file_path: str,
+    out_path: str,
+    entity_path_filter: str = "",
+    timeline: str = "log_time",
+    compression: str = "zstd",
+) -> int:
+    """
+    Export the entities of an RRD file to an MCAP file, for Foxglove and ROS 2 tooling.
+
+    Every entity becomes one channel with the entity path as topic and JSON encoded messages:
+    encoded images as `foxglove.CompressedImage`, raw images as `foxglove.RawImage`, text and
+    text logs as `foxglove.Log`, scalars as `requery.Scalar` and tensors as `requery.Tensor`.
+    Message timestamps come from the given temporal timeline, static data is written at time zero.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        out_path (str): The path of the MCAP file to write. Its directory is created if missing.
+        entity_path_filter (str): The specific entity path to filter. Set to empty string to export all entities.
+        timeline (str): The temporal timeline used for the message timestamps.
+        compression (str): The chunk compression to use: "zstd", "lz4" or "none".
+
+    Returns:
+        int: The number of messages written.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the MCAP file.
+        ValueError: If the timeline is not temporal, the compression is unsupported, or no data is found.
+
+    Example:
+        >>> export_mcap("/path/to/file.rrd", "/path/to/file.mcap", timeline="log_time")
+    """
+    ...
+
+def import_mcap(
+    file_path: str,
+    out_path: str,
+    topic_filter: str = "",
+    application_id: str = "",
+) -> int:
+    """
+    Import an MCAP file, e.g. a ROS 2 bag recorded with the MCAP storage plugin, into an RRD file.
+
+    CDR encoded ROS 2 messages are logged at the entity path of their topic, on the `log_time`
+    timeline: `sensor_msgs/msg/JointState` as tensors at `<topic>/position`, `<topic>/velocity`
+    and `<topic>/effort` (joint names as static text at `<topic>/names`), `sensor_msgs/msg/Image`
+    as `Image`, `sensor_msgs/msg/CompressedImage` as `EncodedImage`, `std_msgs/msg/Float64` as
+    `Scalar` and `rcl_interfaces/msg/Log` as `TextLog`. Other messages are skipped.
+
+    Args:
+        file_path (str): The path to the MCAP file.
+        out_path (str): The path of the RRD file to write. Its directory is created if missing.
+        topic_filter (str): Only import the topics containing this string. Set to empty string to import all topics.
+        application_id (str): The application id of the recording. Defaults to the MCAP file name.
+
+    Returns:
+        int: The number of imported messages.
+
+    Raises:
+        IOError: If there's an issue reading the MCAP file or writing the RRD file.
+        ValueError: If a message cannot be decoded, or no supported messages are found.
+
+    Example:
+        >>> import_mcap("/path/to/bag.mcap", "/path/to/bag.rrd", topic_filter="/joint_states")
+    """
+    ...
+
+def export_npz(
+    file_path: str,
+    out_path: str,
+    entity_path_filter: str = "",
+    compressed: bool = False,
+) -> List[str]:
+    """
+    Export the data entities (scalar or tensor) of an RRD file to a NumPy `.npz` archive.
+
+    Every entity contributes one array per component and one per timeline, named after the entity
+    path without its leading slash: `<entity>/Scalar` (float64, shape `(rows,)`),
+    `<entity>/TensorData` (native dtype, shape `(rows, *tensor_shape)`) and `<entity>/<timeline>`
+    (int64, shape `(rows,)`). Missing floating point values are written as NaN.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        out_path (str): The path of the `.npz` file to write. Its directory is created if missing.
+        entity_path_filter (str): The specific entity path to filter. Set to empty string to export all data.
+        compressed (bool): Deflate the arrays, like `numpy.savez_compressed`.
+
+    Returns:
+        List[str]: The names of the arrays in the archive.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the archive.
+        ValueError: If no data is found, or a tensor changes shape or type between rows.
+
+    Example:
+        >>> export_npz("/path/to/file.rrd", "/path/to/file.npz")
+        >>> arrays = numpy.load("/path/to/file.npz")
+        >>> arrays["action/joints/position/TensorData"].shape
+        (1844, 32)
+    """
+    ...
+
+def export_zarr(
+    file_path: str,
+    out_dir: str,
+    entity_path_filter: str = "",
+    zarr_format: int = 2,
+    chunk_rows: int = 1024,
+    compression: str = "zstd",
+) -> List[str]:
+    """
+    Export the data entities (scalar or tensor) of an RRD file to a Zarr directory store.
+
+    Groups mirror the entity hierarchy, and every entity group holds the same arrays as
+    `export_npz`: `Scalar` (float64), `TensorData` (native dtype, shape `(rows, *tensor_shape)`)
+    and one int64 array per timeline. Arrays are chunked along the row axis only.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        out_dir (str): The root directory of the store. Created if missing.
+        entity_path_filter (str): The specific entity path to filter. Set to empty string to export all data.
+        zarr_format (int): The Zarr format version, 2 or 3.
+        chunk_rows (int): The number of rows per chunk.
+        compression (str): The compression codec to use: "zstd", "gzip" or "none".
+
+    Returns:
+        List[str]: The paths of the written arrays, relative to `out_dir`.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the store.
+        ValueError: If an argument is invalid, no data is found, or a tensor changes shape or type between rows.
+
+    Example:
+        >>> export_zarr("/path/to/file.rrd", "/path/to/store.zarr", zarr_format=3)
+        >>> zarr.open("/path/to/store.zarr")["action/joints/position/TensorData"].shape
+        (1844, 32)
+    """
+    ...
+
+def export_tfrecord(
+    file_path: str,
+    out_dir: str,
+    data_type_filter: str = "",
+    entity_path_filter: str = "",
+    fps: int = 30,
+    timeline: str = "log_time",
+    samples_per_shard: int = 1000,
+) -> List[str]:
+    """
+    Export the data entities of an RRD file to sharded TFRecord files of `tf.train.Example` records.
+
+    Entities are selected like `query_data_entities`, plus the `EncodedImage` entities matching
+    `entity_path_filter` when `data_type_filter` is empty. They are resampled to `fps` on the
+    temporal `timeline`, using the latest sample at or before each frame. Every example holds one
+    feature per entity (a float list, or the encoded image bytes), keyed by the entity path with
+    dots as separators (e.g. `action.joints.position`), plus `timestamp` (seconds since the first
+    sample), `frame_index` and the time of the sample in nanoseconds under the timeline name.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        out_dir (str): The directory of the shards. Created if missing.
+        data_type_filter (str): The type of data to filter. Can be either "scalar", "tensor", or empty string for all data.
+        entity_path_filter (str): The specific entity path to filter. Set to empty string to export all data.
+        fps (int): The number of samples per second.
+        timeline (str): The temporal timeline used to align the entities.
+        samples_per_shard (int): The maximum number of samples in one shard.
+
+    Returns:
+        List[str]: The paths of the written shards, named `<rrd_stem>-00000-of-00003.tfrecord`.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the shards.
+        ValueError: If an argument is invalid, no data is found, or the entities don't overlap in time.
+
+    Example:
+        >>> shards = export_tfrecord("/path/to/file.rrd", "/path/to/tfrecord", entity_path_filter="/action")
+        >>> dataset = tf.data.TFRecordDataset(shards)
+    """
+    ...
+
+def export_webdataset(
+    file_path: str,
+    out_dir: str,
+    data_type_filter: str = "",
+    entity_path_filter: str = "",
+    fps: int = 30,
+    timeline: str = "log_time",
+    samples_per_shard: int = 1000,
+) -> List[str]:
+    """
+    Export the data entities of an RRD file to WebDataset `.tar` shards.
+
+    Entities are selected and resampled like `export_tfrecord`. Every sample holds a
+    `<key>.json` file with `frame_index`, `timestamp` and the time on the timeline, a
+    `<key>.<feature>.npy` float32 vector per scalar or tensor entity, and a
+    `<key>.<feature>.jpg` or `.png` file per image entity.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        out_dir (str): The directory of the shards. Created if missing.
+        data_type_filter (str): The type of data to filter. Can be either "scalar", "tensor", or empty string for all data.
+        entity_path_filter (str): The specific entity path to filter. Set to empty string to export all data.
+        fps (int): The number of samples per second.
+        timeline (str): The temporal timeline used to align the entities.
+        samples_per_shard (int): The maximum number of samples in one shard.
+
+    Returns:
+        List[str]: The paths of the written shards, named `<rrd_stem>-000000.tar`.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the shards.
+        ValueError: If an argument is invalid, no data is found, or the entities don't overlap in time.
+
+    Example:
+        >>> shards = export_webdataset("/path/to/file.rrd", "/path/to/wds")
+        >>> dataset = webdataset.WebDataset(shards).decode()
+    """
+    ...
+
+def write_filtered_rrd(
+    src: str,
+    dst: str,
+    entity_filter: str = "",
+    time_range: Optional[Tuple[int, int]] = None,
+    store_selector: str = "",
+    timeline: str = "log_time",
+    include_blueprint: bool = True,
+) -> int:
+    """
+    Write a copy of an RRD file restricted to some stores, entities and time range.
+
+    The store infos of the kept stores are copied as is, so the result opens directly in the
+    Rerun viewer. Temporal chunks are trimmed to the rows whose time on `timeline` is within
+    `time_range`, bounds included, and static chunks are always kept. Blueprints of the kept
+    recordings are copied unfiltered if `include_blueprint` is set.
+
+    Args:
+        src (str): The path to the RRD file to read.
+        dst (str): The path of the RRD file to write. Its directory is created if missing.
+        entity_filter (str): Only keep the entities whose path contains this string. Set to empty string to keep all entities.
+        time_range (Optional[Tuple[int, int]]): The `(start, end)` times to keep on `timeline`, in nanoseconds for temporal timelines. `None` keeps all times.
+        store_selector (str): The store id or application id of the recordings to keep. Set to empty string to keep all recordings.
+        timeline (str): The timeline of `time_range`.
+        include_blueprint (bool): Also copy the blueprints of the kept recordings.
+
+    Returns:
+        int: The number of written messages.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the new one.
+        ValueError: If the RRD file can't be decoded, or no recording or data matches the filters.
+
+    Example:
+        >>> start = 1724121014346522000
+        >>> write_filtered_rrd("/path/to/file.rrd", "/path/to/small.rrd", "/action", (start, start + 30_000_000_000))
+        260
+    """
+    ...
+
+def merge_rrds(
+    file_paths: List[str],
+    out_path: str,
+    store_id: str = "",
+    offset_timelines: List[str] = [],
+) -> int:
+    """
+    Merge RRD files, e.g. the rotated log files of one session, into a single recording.
+
+    Every recording of every file is written under one store id: `store_id` if set, or the id
+    of the first recording of the first file otherwise. The store info of that first recording
+    and the blueprints of the first file holding any are kept.
+
+    The timelines listed in `offset_timelines` are shifted so that every file starts right after
+    the previous one ends, which turns per-file counters such as `log_tick` into one sequence.
+
+    Args:
+        file_paths (List[str]): The RRD files to merge, in session order.
+        out_path (str): The path of the RRD file to write. Its directory is created if missing.
+        store_id (str): The store id of the merged recording. Set to empty string to keep the id of the first recording.
+        offset_timelines (List[str]): The timelines to offset so that the files follow each other.
+
+    Returns:
+        int: The number of written messages.
+
+    Raises:
+        IOError: If there's an issue reading an RRD file or writing the merged one.
+        ValueError: If an RRD file can't be decoded or no recording is found.
+
+    Example:
+        >>> merge_rrds(["/path/to/part_0.rrd", "/path/to/part_1.rrd"], "/path/to/session.rrd", offset_timelines=["log_tick"])
+        2842
+    """
+    ...
+
+def split_rrd(
+    file_path: str,
+    out_dir: str,
+    marker_entity: str = "",
+    gap_threshold: Optional[int] = None,
+    timeline: str = "log_time",
+) -> List[str]:
+    """
+    Split an RRD file into one RRD file per episode.
+
+    Episodes are delimited on `timeline` either by the rows of `marker_entity` (e.g. the
+    `/meta/episode` text rows logged at the start of every episode), or by gaps between
+    consecutive data times larger than `gap_threshold`. Data before the first marker belongs to
+    the first episode. Every episode file holds the temporal rows of its time range, all the
+    static data and the blueprints, under a new recording store id.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        out_dir (str): The directory of the episode files, named `episode_000000.rrd`, ... Created if missing.
+        marker_entity (str): The entity whose rows start the episodes. Set to empty string to split on gaps.
+        gap_threshold (Optional[int]): The minimal gap between two episodes, in nanoseconds for temporal timelines. Used if `marker_entity` is empty.
+        timeline (str): The timeline used to delimit the episodes.
+
+    Returns:
+        List[str]: The paths of the written episode files.
+
+    Raises:
+        IOError: If there's an issue reading the RRD file or writing the episodes.
+        ValueError: If the RRD file can't be decoded, neither `marker_entity` nor `gap_threshold` is set, or no episode is found.
+
+    Example:
+        >>> split_rrd("/path/to/session.rrd", "/path/to/episodes", marker_entity="/meta/episode")
+        ['/path/to/episodes/episode_000000.rrd', '/path/to/episodes/episode_000001.rrd']
+    """
+    ...
+
+def sql_ipc(file_path: str, query: str) -> bytes:
+    """
+    Run a SQL query over the entities of an RRD file, see `requery.sql`.
+
+    Args:
+        file_path (str): The path to the RRD file.
+        query (str): The SQL query. Every entity is a table named by its entity path.
+
+    Returns:
+        bytes: The result of the query as an Arrow IPC stream.
+
+    Raises:
+        OSError: If the file cannot be opened.
+        ValueError: If the file cannot be decoded or the query is invalid.
+
+    Example:
+        >>> stream = sql_ipc("/path/to/file.rrd", 'SELECT count(*) FROM "/action/gripper"')
+    """
+    ...
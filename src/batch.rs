@@ -0,0 +1,94 @@
+//! Queries over many RRD files at once, e.g. to compute statistics over the episodes of a dataset.
+//!
+//! The files are processed concurrently by a pool of threads, each processing one file at a time,
+//! so that at most as many recordings as threads are decoded at once. The results are returned in
+//! the order of the files, with an error per file that fails.
+
+use log::debug;
+use rayon::prelude::*;
+
+use crate::{query_data_entities_in_range, DataChunk, Error, Result};
+
+/// A query of the data entities of a recording, see [`query_data_entities_in_range`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataQuery {
+    /// The data type to keep, "scalar" or "tensor", or "" for both.
+    pub data_type_filter: String,
+    /// A string contained in the paths of the entities to keep, or "" for all entities.
+    pub entity_path_filter: String,
+    /// The `(start, end)` times to keep on `timeline`, bounds included. `None` keeps all times.
+    pub time_range: Option<(i64, i64)>,
+    /// The timeline of `time_range`.
+    pub timeline: String,
+}
+
+impl Default for DataQuery {
+    fn default() -> Self {
+        Self {
+            data_type_filter: String::new(),
+            entity_path_filter: String::new(),
+            time_range: None,
+            timeline: "log_time".to_string(),
+        }
+    }
+}
+
+/// Call `f` with every file path on a pool of `num_threads` threads, or one per core if 0.
+///
+/// Each thread processes one file at a time and only the values returned by `f` are kept, so `f`
+/// bounds the memory of the scan by reducing the recording it reads, e.g. to statistics.
+///
+/// # Returns
+///
+/// * `Result<Vec<T>>` - The values returned by `f`, in the order of `file_paths`, or an error if
+///   the thread pool cannot be created.
+pub fn scan_files<T, F>(file_paths: &[String], num_threads: usize, f: F) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(&str) -> T + Sync,
+{
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map_err(|e| Error::Invalid(format!("Thread pool error: {}", e)))?;
+    debug!(
+        "Scanning {} files with {} threads",
+        file_paths.len(),
+        pool.current_num_threads()
+    );
+    Ok(pool.install(|| {
+        file_paths
+            .par_iter()
+            .with_max_len(1)
+            .map(|file_path| f(file_path))
+            .collect()
+    }))
+}
+
+/// Run a query of the data entities on many RRD files concurrently, see [`scan_files`].
+///
+/// # Arguments
+///
+/// * `file_paths` - The paths to the RRD files.
+/// * `query` - The query to run on every file.
+/// * `num_threads` - The number of files processed at once, 0 for one per core.
+///
+/// # Returns
+///
+/// * `Result<Vec<Result<Vec<DataChunk>>>>` - The data chunks or the error of every file, in the
+///   order of `file_paths`.
+pub fn query_data_entities_batch(
+    file_paths: &[String],
+    query: &DataQuery,
+    num_threads: usize,
+) -> Result<Vec<Result<Vec<DataChunk>>>> {
+    scan_files(file_paths, num_threads, |file_path| {
+        query_data_entities_in_range(
+            file_path,
+            &query.data_type_filter,
+            &query.entity_path_filter,
+            query.time_range,
+            &query.timeline,
+        )
+    })
+}
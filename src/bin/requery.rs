@@ -0,0 +1,687 @@
+//! `requery`: inspect and export Rerun `.rrd` recordings from the command line.
+//!
+//! ```text
+//! requery ls recording.rrd
+//! requery stats recording.rrd --entity /action
+//! requery cat recording.rrd /action/joints/position --limit 10
+//! requery merge part_0.rrd part_1.rrd -o session.rrd --offset-timeline log_tick
+//! requery export parquet recording.rrd -o ./out
+//! ```
+
+use std::process::ExitCode;
+
+use chrono::{DateTime, SecondsFormat};
+use clap::{Args, Parser, Subcommand};
+use re_arrow2::array::get_display;
+use re_chunk::Chunk;
+use re_log_types::{StoreKind, TimeType};
+use rerun_query::{
+    build_index, chunk_columns, load_bundle, query_entity_chunks, query_meta_entities,
+    store_kind_name, summarize_stores, time_type_name, type_name, EntitySummary, Result,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "requery",
+    version,
+    about = "Inspect and export Rerun .rrd recordings"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the stores of a recording and their entities.
+    Ls {
+        /// The RRD file.
+        file: String,
+    },
+    /// Print the timelines and the component types of every entity.
+    Schema {
+        /// The RRD file.
+        file: String,
+        #[command(flatten)]
+        filter: EntityFilter,
+    },
+    /// Print the number of chunks and rows and the time range of every entity.
+    Stats {
+        /// The RRD file.
+        file: String,
+        #[command(flatten)]
+        filter: EntityFilter,
+    },
+    /// Print the rows of an entity, sorted by time.
+    Cat {
+        /// The RRD file.
+        file: String,
+        /// The path of the entity, e.g. /action/joints/position.
+        entity: String,
+        /// The timeline to sort and filter the rows by.
+        #[arg(long, default_value = "log_time")]
+        timeline: String,
+        /// The first time to print, in nanoseconds for temporal timelines.
+        #[arg(long)]
+        start: Option<i64>,
+        /// The last time to print, in nanoseconds for temporal timelines.
+        #[arg(long)]
+        end: Option<i64>,
+        /// The maximum number of rows to print.
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Print the text entities, e.g. the robot description.
+    Meta {
+        /// The RRD file.
+        file: String,
+        /// The exact path of the entity. Prints all text entities if omitted.
+        #[arg(default_value = "")]
+        entity: String,
+    },
+    /// Index the chunks of a recording into <file>.idx, so that queries only decode the chunks they
+    /// need.
+    Index {
+        /// The RRD file.
+        file: String,
+    },
+    /// Write a copy of a recording restricted to some entities, recordings and time range.
+    Filter {
+        /// The RRD file.
+        file: String,
+        /// The RRD file to write.
+        #[arg(short, long)]
+        output: String,
+        /// Only keep the entities whose path contains this string.
+        #[arg(short, long, default_value = "")]
+        entity: String,
+        /// The timeline of --start and --end.
+        #[arg(long, default_value = "log_time")]
+        timeline: String,
+        /// The first time to keep, in nanoseconds for temporal timelines.
+        #[arg(long)]
+        start: Option<i64>,
+        /// The last time to keep, in nanoseconds for temporal timelines.
+        #[arg(long)]
+        end: Option<i64>,
+        /// The store id or application id of the recordings to keep.
+        #[arg(long, default_value = "")]
+        store: String,
+        /// Also copy the blueprints of the kept recordings.
+        #[arg(long)]
+        blueprint: bool,
+    },
+    /// Merge RRD files, e.g. the rotated log files of one session, into a single recording.
+    Merge {
+        /// The RRD files to merge, in session order.
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// The RRD file to write.
+        #[arg(short, long)]
+        output: String,
+        /// The store id of the merged recording. Keeps the id of the first recording if omitted.
+        #[arg(long, default_value = "")]
+        store_id: String,
+        /// A timeline to offset so that the files follow each other, e.g. log_tick. Can be
+        /// repeated.
+        #[arg(long = "offset-timeline", value_name = "TIMELINE")]
+        offset_timelines: Vec<String>,
+    },
+    /// Split an RRD file into per-episode files.
+    Split {
+        /// The RRD file.
+        file: String,
+        /// The directory of the episode files.
+        #[arg(short, long)]
+        output: String,
+        #[command(flatten)]
+        delimiter: EpisodeDelimiter,
+        /// The timeline used to delimit the episodes.
+        #[arg(long, default_value = "log_time")]
+        timeline: String,
+    },
+    /// Run a SQL query over the entities, e.g. 'SELECT count(*) FROM "/action/gripper"'.
+    #[cfg(feature = "sql")]
+    Sql {
+        /// The RRD file.
+        file: String,
+        /// The SQL query. Every entity is a table named by its entity path.
+        query: String,
+    },
+    /// Export a recording to another format.
+    #[command(subcommand)]
+    Export(Export),
+    /// Serve the recordings of a folder over HTTP.
+    #[cfg(feature = "server")]
+    Serve {
+        /// The folder holding the .rrd files.
+        folder: String,
+        /// The address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+    },
+    /// Serve the recordings of a folder over Arrow Flight.
+    #[cfg(feature = "flight")]
+    Flight {
+        /// The folder holding the .rrd files.
+        folder: String,
+        /// The address to listen on.
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        bind: String,
+    },
+}
+
+#[derive(Args)]
+struct EntityFilter {
+    /// Only show the entities whose path contains this string.
+    #[arg(short, long, default_value = "")]
+    entity: String,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct EpisodeDelimiter {
+    /// The entity whose rows start the episodes, e.g. /meta/episode.
+    #[arg(long)]
+    marker_entity: Option<String>,
+    /// The minimal gap between two episodes, in nanoseconds for temporal timelines.
+    #[arg(long)]
+    gap_threshold: Option<i64>,
+}
+
+#[derive(Args)]
+struct Input {
+    /// The RRD file.
+    file: String,
+    /// Only export the entities whose path contains this string.
+    #[arg(short, long, default_value = "")]
+    entity: String,
+}
+
+#[derive(Args)]
+struct Samples {
+    /// Only export "scalar" or "tensor" entities.
+    #[arg(long, default_value = "")]
+    data_type: String,
+    /// The number of samples per second.
+    #[arg(long, default_value_t = 30)]
+    fps: u32,
+    /// The temporal timeline used to align the entities.
+    #[arg(long, default_value = "log_time")]
+    timeline: String,
+    /// The maximum number of samples in one shard.
+    #[arg(long, default_value_t = 1000)]
+    samples_per_shard: usize,
+}
+
+#[derive(Subcommand)]
+enum Export {
+    /// One Parquet file per entity.
+    Parquet {
+        #[command(flatten)]
+        input: Input,
+        /// The output directory.
+        #[arg(short, long)]
+        output: String,
+        /// The compression codec: zstd, snappy or none.
+        #[arg(long, default_value = "zstd")]
+        compression: String,
+    },
+    /// One CSV file per entity.
+    Csv {
+        #[command(flatten)]
+        input: Input,
+        /// The output directory.
+        #[arg(short, long)]
+        output: String,
+        /// Only export "scalar" or "tensor" entities.
+        #[arg(long, default_value = "")]
+        data_type: String,
+        /// The field delimiter.
+        #[arg(long, default_value = ",")]
+        delimiter: String,
+        /// How temporal timelines are written: raw, seconds or iso8601.
+        #[arg(long, default_value = "raw")]
+        time_format: String,
+    },
+    /// A NumPy .npz archive.
+    Npz {
+        #[command(flatten)]
+        input: Input,
+        /// The output file.
+        #[arg(short, long)]
+        output: String,
+        /// Deflate the arrays.
+        #[arg(long)]
+        compressed: bool,
+    },
+    /// A Zarr store.
+    Zarr {
+        #[command(flatten)]
+        input: Input,
+        /// The output directory.
+        #[arg(short, long)]
+        output: String,
+        /// The Zarr format version: 2 or 3.
+        #[arg(long, default_value_t = 2)]
+        zarr_format: u8,
+        /// The number of rows per Zarr chunk.
+        #[arg(long, default_value_t = 1024)]
+        chunk_rows: usize,
+        /// The compression codec: zstd or none.
+        #[arg(long, default_value = "zstd")]
+        compression: String,
+    },
+    /// An MCAP file with one channel per entity.
+    Mcap {
+        #[command(flatten)]
+        input: Input,
+        /// The output file.
+        #[arg(short, long)]
+        output: String,
+        /// The temporal timeline used as message time.
+        #[arg(long, default_value = "log_time")]
+        timeline: String,
+        /// The compression codec: zstd, lz4 or none.
+        #[arg(long, default_value = "zstd")]
+        compression: String,
+    },
+    /// Time-aligned samples as TFRecord shards.
+    Tfrecord {
+        #[command(flatten)]
+        input: Input,
+        /// The output directory.
+        #[arg(short, long)]
+        output: String,
+        #[command(flatten)]
+        samples: Samples,
+    },
+    /// Time-aligned samples as WebDataset .tar shards.
+    Webdataset {
+        #[command(flatten)]
+        input: Input,
+        /// The output directory.
+        #[arg(short, long)]
+        output: String,
+        #[command(flatten)]
+        samples: Samples,
+    },
+    /// A LeRobot dataset, one episode per RRD file.
+    Lerobot {
+        /// The RRD files.
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// The output directory.
+        #[arg(short, long)]
+        output: String,
+        /// The JSON file mapping the entities to the dataset features.
+        #[arg(long)]
+        config: String,
+    },
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Ls { file } => ls(&file),
+        Command::Schema { file, filter } => schema(&file, &filter.entity),
+        Command::Stats { file, filter } => stats(&file, &filter.entity),
+        Command::Cat {
+            file,
+            entity,
+            timeline,
+            start,
+            end,
+            limit,
+        } => cat(
+            &file,
+            &entity,
+            &timeline,
+            start.unwrap_or(i64::MIN),
+            end.unwrap_or(i64::MAX),
+            limit,
+        ),
+        Command::Meta { file, entity } => {
+            for meta in query_meta_entities(&file, &entity)? {
+                println!("{} ({})", meta.entity_path, meta.media_type);
+                println!("{}", meta.text);
+            }
+            Ok(())
+        }
+        Command::Index { file } => {
+            println!("Wrote {}", build_index(&file)?);
+            Ok(())
+        }
+        Command::Filter {
+            file,
+            output,
+            entity,
+            timeline,
+            start,
+            end,
+            store,
+            blueprint,
+        } => {
+            let time_range = (start.is_some() || end.is_some())
+                .then(|| (start.unwrap_or(i64::MIN), end.unwrap_or(i64::MAX)));
+            let count = rerun_query::write_filtered_rrd(
+                &file, &output, &entity, time_range, &store, &timeline, blueprint,
+            )?;
+            println!("Wrote {} messages to {}", count, output);
+            Ok(())
+        }
+        Command::Merge {
+            files,
+            output,
+            store_id,
+            offset_timelines,
+        } => {
+            let count = rerun_query::merge_rrds(files, &output, &store_id, offset_timelines)?;
+            println!("Wrote {} messages to {}", count, output);
+            Ok(())
+        }
+        Command::Split {
+            file,
+            output,
+            delimiter,
+            timeline,
+        } => {
+            let episodes = rerun_query::split_rrd(
+                &file,
+                &output,
+                delimiter.marker_entity.as_deref().unwrap_or(""),
+                delimiter.gap_threshold,
+                &timeline,
+            )?;
+            for episode in episodes {
+                println!("Wrote {}", episode);
+            }
+            Ok(())
+        }
+        #[cfg(feature = "sql")]
+        Command::Sql { file, query } => {
+            let batches = rerun_query::sql::sql(&file, &query)?;
+            datafusion::arrow::util::pretty::print_batches(&batches)
+                .map_err(|e| rerun_query::Error::Encode(format!("Print error: {}", e)))
+        }
+        Command::Export(export) => self::export(export),
+        #[cfg(feature = "server")]
+        Command::Serve { folder, bind } => {
+            println!("Serving {} on http://{}", folder, bind);
+            actix_web::rt::System::new()
+                .block_on(rerun_query::server::serve(folder, &bind))
+                .map_err(|e| rerun_query::Error::Invalid(format!("Server error: {}", e)))
+        }
+        #[cfg(feature = "flight")]
+        Command::Flight { folder, bind } => {
+            println!("Serving {} on grpc://{}", folder, bind);
+            tokio::runtime::Runtime::new()
+                .and_then(|runtime| runtime.block_on(rerun_query::flight::serve(folder, &bind)))
+                .map_err(|e| rerun_query::Error::Invalid(format!("Server error: {}", e)))
+        }
+    }
+}
+
+fn ls(file: &str) -> Result<()> {
+    for store in summarize_stores(file)? {
+        println!(
+            "{} {} (application {}, {} rows)",
+            store_kind_name(store.kind),
+            store.store_id,
+            store.application_id.as_deref().unwrap_or("unknown"),
+            store.num_rows
+        );
+        for entity in &store.entities {
+            println!("  {}", entity.entity_path);
+        }
+    }
+    Ok(())
+}
+
+fn schema(file: &str, entity_filter: &str) -> Result<()> {
+    for entity in recording_entities(file, entity_filter)? {
+        println!("{}", entity.entity_path);
+        for (name, timeline) in &entity.timelines {
+            println!("  {}: {}", name, time_type_name(timeline.typ));
+        }
+        for (name, data_type) in &entity.components {
+            println!("  {}: {}", name, type_name(data_type));
+        }
+    }
+    Ok(())
+}
+
+fn stats(file: &str, entity_filter: &str) -> Result<()> {
+    for entity in recording_entities(file, entity_filter)? {
+        println!(
+            "{}: {} chunks, {} rows ({} static)",
+            entity.entity_path, entity.num_chunks, entity.num_rows, entity.num_static_rows
+        );
+        for (name, timeline) in &entity.timelines {
+            let span = match timeline.typ {
+                TimeType::Time => format!(
+                    "{:.3} s",
+                    (timeline.max as f64 - timeline.min as f64) * 1e-9
+                ),
+                TimeType::Sequence => format!("{} ticks", timeline.max - timeline.min),
+            };
+            println!(
+                "  {}: {} rows, {} .. {} ({})",
+                name,
+                timeline.num_rows,
+                format_time(timeline.typ, timeline.min),
+                format_time(timeline.typ, timeline.max),
+                span
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The entities of the recordings whose path contains `entity_filter`.
+fn recording_entities(file: &str, entity_filter: &str) -> Result<Vec<EntitySummary>> {
+    Ok(summarize_stores(file)?
+        .into_iter()
+        .filter(|store| store.kind == StoreKind::Recording)
+        .flat_map(|store| store.entities)
+        .filter(|entity| entity.entity_path.contains(entity_filter))
+        .collect())
+}
+
+fn cat(file: &str, entity: &str, timeline: &str, start: i64, end: i64, limit: usize) -> Result<()> {
+    let bundle = load_bundle(file)?;
+
+    let mut chunks: Vec<Chunk> = bundle
+        .entity_dbs()
+        .filter(|rrd| rrd.store_kind() == StoreKind::Recording)
+        .flat_map(|rrd| query_entity_chunks(rrd, entity, |_| true))
+        .filter_map(|chunk| {
+            if chunk.is_static() {
+                return Some(chunk);
+            }
+            let time_line = *chunk
+                .timelines()
+                .keys()
+                .find(|candidate| candidate.name().as_str() == timeline)?;
+            Some(chunk.sorted_by_timeline_if_unsorted(&time_line))
+        })
+        .collect();
+    // Static rows apply to every time and come first, then the chunks in time order
+    chunks.sort_by_key(|chunk| chunk_start(chunk, timeline));
+
+    let mut header = Vec::new();
+    let mut printed = 0;
+    for chunk in &chunks {
+        let columns = chunk_columns(chunk)?;
+        let names: Vec<&str> = columns
+            .iter()
+            .map(|(field, _)| field.name.as_str())
+            .collect();
+        if names != header {
+            println!("{}", names.join("\t"));
+            header = names.iter().map(|name| name.to_string()).collect();
+        }
+
+        let times = chunk
+            .timelines()
+            .iter()
+            .find(|(candidate, _)| candidate.name().as_str() == timeline)
+            .map(|(_, time_column)| time_column.times_raw());
+        let displays: Vec<_> = columns
+            .iter()
+            .map(|(_, array)| get_display(array.as_ref(), "null"))
+            .collect();
+        for row in 0..chunk.num_rows() {
+            if printed == limit {
+                return Ok(());
+            }
+            if times.is_some_and(|times| times[row] < start || times[row] > end) {
+                continue;
+            }
+
+            let mut fields = Vec::with_capacity(displays.len());
+            for display in &displays {
+                let mut field = String::new();
+                display(&mut field, row).map_err(|e| rerun_query::Error::Invalid(e.to_string()))?;
+                fields.push(field);
+            }
+            println!("{}", fields.join("\t"));
+            printed += 1;
+        }
+    }
+
+    if printed == 0 {
+        return Err(rerun_query::Error::NoData(format!(
+            "No rows found for {} on timeline {}",
+            entity, timeline
+        )));
+    }
+    Ok(())
+}
+
+/// The first time of a chunk on `timeline`, `None` for static chunks.
+fn chunk_start(chunk: &Chunk, timeline: &str) -> Option<i64> {
+    chunk
+        .timelines()
+        .iter()
+        .find(|(candidate, _)| candidate.name().as_str() == timeline)
+        .map(|(_, time_column)| time_column.time_range().min().as_i64())
+}
+
+fn export(export: Export) -> Result<()> {
+    let written = match export {
+        Export::Parquet {
+            input,
+            output,
+            compression,
+        } => rerun_query::export_parquet(&input.file, &output, &input.entity, &compression)?,
+        Export::Csv {
+            input,
+            output,
+            data_type,
+            delimiter,
+            time_format,
+        } => rerun_query::export_csv(
+            &input.file,
+            &output,
+            &data_type,
+            &input.entity,
+            &delimiter,
+            &time_format,
+        )?,
+        Export::Npz {
+            input,
+            output,
+            compressed,
+        } => rerun_query::export_npz(&input.file, &output, &input.entity, compressed)?,
+        Export::Zarr {
+            input,
+            output,
+            zarr_format,
+            chunk_rows,
+            compression,
+        } => rerun_query::export_zarr(
+            &input.file,
+            &output,
+            &input.entity,
+            zarr_format,
+            chunk_rows,
+            &compression,
+        )?,
+        Export::Mcap {
+            input,
+            output,
+            timeline,
+            compression,
+        } => {
+            let count = rerun_query::export_mcap(
+                &input.file,
+                &output,
+                &input.entity,
+                &timeline,
+                &compression,
+            )?;
+            println!("Wrote {} messages to {}", count, output);
+            return Ok(());
+        }
+        Export::Tfrecord {
+            input,
+            output,
+            samples,
+        } => rerun_query::export_tfrecord(
+            &input.file,
+            &output,
+            &samples.data_type,
+            &input.entity,
+            samples.fps,
+            &samples.timeline,
+            samples.samples_per_shard,
+        )?,
+        Export::Webdataset {
+            input,
+            output,
+            samples,
+        } => rerun_query::export_webdataset(
+            &input.file,
+            &output,
+            &samples.data_type,
+            &input.entity,
+            samples.fps,
+            &samples.timeline,
+            samples.samples_per_shard,
+        )?,
+        Export::Lerobot {
+            files,
+            output,
+            config,
+        } => {
+            let config = std::fs::read_to_string(&config).map_err(rerun_query::Error::Open)?;
+            rerun_query::export_lerobot(files, &output, &config)?
+        }
+    };
+
+    for path in written {
+        println!("{}", path);
+    }
+    Ok(())
+}
+
+fn format_time(typ: TimeType, time: i64) -> String {
+    match typ {
+        TimeType::Time => {
+            DateTime::from_timestamp_nanos(time).to_rfc3339_opts(SecondsFormat::Nanos, true)
+        }
+        TimeType::Sequence => time.to_string(),
+    }
+}
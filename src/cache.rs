@@ -0,0 +1,119 @@
+//! An in-process cache of decoded recordings, so that repeated queries on an unchanged file skip
+//! decoding entirely.
+//!
+//! Recordings are keyed by canonical path, and dropped when the size or the modification time of
+//! the file changes. The least recently used recordings are evicted once the estimated memory of
+//! the cached recordings exceeds the budget set by [`set_cache_budget`].
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use log::debug;
+use re_entity_db::StoreBundle;
+
+use crate::{index::file_stamp, read_bundle, Error, Result};
+
+/// The default memory budget of the cache: 1 GiB.
+const DEFAULT_BUDGET: u64 = 1 << 30;
+
+static CACHE: Mutex<BundleCache> = Mutex::new(BundleCache {
+    budget: DEFAULT_BUDGET,
+    entries: Vec::new(),
+});
+
+struct CachedBundle {
+    path: PathBuf,
+    stamp: (u64, u64),
+    size_bytes: u64,
+    bundle: Arc<StoreBundle>,
+}
+
+/// The cached recordings, the most recently used last.
+struct BundleCache {
+    budget: u64,
+    entries: Vec<CachedBundle>,
+}
+
+impl BundleCache {
+    fn get(&mut self, path: &Path, stamp: (u64, u64)) -> Option<Arc<StoreBundle>> {
+        let position = self.entries.iter().position(|entry| entry.path == path)?;
+        let entry = self.entries.remove(position);
+        if entry.stamp != stamp {
+            debug!("Dropping the outdated recording {}", path.display());
+            return None;
+        }
+        let bundle = entry.bundle.clone();
+        self.entries.push(entry);
+        Some(bundle)
+    }
+
+    fn insert(&mut self, entry: CachedBundle) {
+        self.entries.retain(|cached| cached.path != entry.path);
+        if entry.size_bytes <= self.budget {
+            self.entries.push(entry);
+        }
+        self.evict();
+    }
+
+    /// Evict the least recently used recordings until the cache fits in its budget.
+    fn evict(&mut self) {
+        let mut size_bytes: u64 = self.entries.iter().map(|entry| entry.size_bytes).sum();
+        while size_bytes > self.budget {
+            let entry = self.entries.remove(0);
+            debug!("Evicting the recording {}", entry.path.display());
+            size_bytes -= entry.size_bytes;
+        }
+    }
+}
+
+fn cache() -> MutexGuard<'static, BundleCache> {
+    // The cache holds no invariant that a panic could break
+    CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Set the memory budget of the cache of decoded recordings, in bytes, and evict the least
+/// recently used recordings beyond it. The default budget is 1 GiB, and 0 disables the cache.
+pub fn set_cache_budget(max_bytes: u64) {
+    let mut cache = cache();
+    cache.budget = max_bytes;
+    cache.evict();
+}
+
+/// Drop every decoded recording of the cache.
+pub fn clear_cache() {
+    cache().entries.clear();
+}
+
+/// The decoded recording of an RRD file if it is cached and the file didn't change since.
+pub(crate) fn cached_bundle(file_path: &str) -> Result<Option<Arc<StoreBundle>>> {
+    let path = fs::canonicalize(file_path).map_err(Error::Open)?;
+    let stamp = file_stamp(&path)?;
+    Ok(cache().get(&path, stamp))
+}
+
+/// Decode an RRD file, or return its cached recording if the file didn't change since.
+pub(crate) fn load_cached_bundle(file_path: &str) -> Result<Arc<StoreBundle>> {
+    let path = fs::canonicalize(file_path).map_err(Error::Open)?;
+    let stamp = file_stamp(&path)?;
+    if let Some(bundle) = cache().get(&path, stamp) {
+        debug!("Using the cached recording {}", path.display());
+        return Ok(bundle);
+    }
+
+    // Decode without holding the lock, so that other files can be loaded meanwhile
+    let bundle = Arc::new(read_bundle(File::open(&path).map_err(Error::Open)?)?);
+    let size_bytes = bundle
+        .entity_dbs()
+        .map(|rrd| rrd.store().stats().total().total_size_bytes)
+        .sum();
+    cache().insert(CachedBundle {
+        path,
+        stamp,
+        size_bytes,
+        bundle: bundle.clone(),
+    });
+    Ok(bundle)
+}
@@ -0,0 +1,436 @@
+//! A C ABI over the data entities of a recording, built with the `capi` feature.
+//!
+//! The header is `include/rerun_query.h`, generated by cbindgen. A recording is opened once into
+//! an opaque handle holding every scalar and tensor entity as `double` values, which are then
+//! copied into buffers owned by the caller:
+//!
+//! ```c
+//! RqRecording *recording = NULL;
+//! if (rq_open("recording.rrd", &recording) != RQ_STATUS_OK) {
+//!     fprintf(stderr, "%s\n", rq_last_error());
+//!     return 1;
+//! }
+//! RqEntityInfo info;
+//! rq_entity_info(recording, "/action/joints/position", &info);
+//! int64_t *times = malloc(info.num_rows * sizeof(int64_t));
+//! double *values = malloc(info.num_rows * info.width * sizeof(double));
+//! rq_read_column(recording, "/action/joints/position", "log_time", times, values, info.num_rows);
+//! rq_close(recording);
+//! ```
+//!
+//! Functions return an [`Status`] and never unwind into the caller. The message of the last
+//! error of the calling thread is returned by [`rq_last_error`].
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use re_arrow2::{
+    array::{Array, Float64Array},
+    compute::cast::{cast, CastOptions},
+    datatypes::DataType as ArrowDataType,
+};
+
+use crate::{query_data_entities, ChunkData, DataChunk, Error, Result};
+
+/// The outcome of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The call succeeded.
+    Ok = 0,
+    /// The file could not be opened or read.
+    Open = 1,
+    /// The file could not be decoded.
+    Decode = 2,
+    /// The file holds no recording with data entities.
+    StoreNotFound = 3,
+    /// No entity or timeline matches the arguments.
+    NoData = 4,
+    /// An argument is invalid, e.g. a null pointer or a string that is not UTF-8.
+    Invalid = 5,
+    /// A buffer of the caller is too small.
+    BufferTooSmall = 6,
+    /// An output could not be written or encoded.
+    Write = 7,
+    /// The call panicked, which is a bug.
+    Panic = 8,
+    /// The values of the entity have a type that is not supported.
+    UnsupportedComponent = 9,
+}
+
+/// The type of the values of an entity.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    /// One value per row.
+    Scalar = 0,
+    /// `width` values per row, the flattened elements of a tensor.
+    Tensor = 1,
+}
+
+/// The shape of the values of an entity.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EntityInfo {
+    /// Whether the entity holds scalars or tensors.
+    pub data_type: DataType,
+    /// The number of rows.
+    pub num_rows: usize,
+    /// The number of values per row: 1 for scalars, the number of tensor elements for tensors.
+    pub width: usize,
+}
+
+/// An opened recording, with the values of its data entities.
+pub struct Recording {
+    entities: Vec<Entity>,
+}
+
+struct Entity {
+    path: CString,
+    data_type: DataType,
+    num_rows: usize,
+    width: usize,
+    /// The times of every row per timeline, `INT64_MIN` for rows without a time on the timeline.
+    timelines: BTreeMap<String, Vec<i64>>,
+    /// The values of every row, `width` per row, NaN for missing values.
+    values: Vec<f64>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).expect("nul bytes are replaced");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+/// Run the body of an exported function, storing the message of its error.
+fn ffi(body: impl FnOnce() -> Result<(), (Status, String)>) -> Status {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => Status::Ok,
+        Ok(Err((status, message))) => {
+            set_last_error(message);
+            status
+        }
+        Err(_) => {
+            set_last_error("The call panicked".to_string());
+            Status::Panic
+        }
+    }
+}
+
+impl From<Error> for (Status, String) {
+    fn from(e: Error) -> Self {
+        let status = match e.kind() {
+            Error::Open(_) => Status::Open,
+            Error::Decode(_) => Status::Decode,
+            Error::StoreNotFound(_) => Status::StoreNotFound,
+            Error::NoData(_) => Status::NoData,
+            Error::UnsupportedComponent(_) => Status::UnsupportedComponent,
+            Error::Invalid(_) => Status::Invalid,
+            Error::Write(_) | Error::Encode(_) | Error::WithContext(..) => Status::Write,
+        };
+        (status, e.to_string())
+    }
+}
+
+fn invalid(message: &str) -> (Status, String) {
+    (Status::Invalid, message.to_string())
+}
+
+/// # Safety
+///
+/// `string` must be null or a valid NUL-terminated string.
+unsafe fn str_arg<'a>(string: *const c_char, name: &str) -> Result<&'a str, (Status, String)> {
+    if string.is_null() {
+        return Err(invalid(&format!("`{}` is null", name)));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| invalid(&format!("`{}` is not UTF-8", name)))
+}
+
+impl Recording {
+    fn open(file_path: &str) -> Result<Self> {
+        let mut entities: BTreeMap<String, Vec<DataChunk>> = BTreeMap::new();
+        for data_chunk in query_data_entities(file_path, "", "")? {
+            entities
+                .entry(data_chunk.entity_path.clone())
+                .or_default()
+                .push(data_chunk);
+        }
+
+        let entities = entities
+            .into_iter()
+            .map(|(entity_path, data_chunks)| Entity::new(&entity_path, &data_chunks))
+            .collect::<Result<_>>()?;
+        Ok(Self { entities })
+    }
+
+    fn entity(&self, entity_path: &str) -> Result<&Entity> {
+        self.entities
+            .iter()
+            .find(|entity| entity.path.as_bytes() == entity_path.as_bytes())
+            .ok_or_else(|| Error::NoData(format!("No data entity at {}", entity_path)))
+    }
+}
+
+impl Entity {
+    fn new(entity_path: &str, data_chunks: &[DataChunk]) -> Result<Self> {
+        let data_type = match data_chunks[0].data {
+            ChunkData::Scalar(_) => DataType::Scalar,
+            ChunkData::Tensor(_) => DataType::Tensor,
+        };
+        let num_rows = data_chunks
+            .iter()
+            .map(|data_chunk| data_chunk.data.len())
+            .sum();
+
+        // Null tensor rows have no elements, so the width is the size of the first tensor
+        let width = match data_type {
+            DataType::Scalar => 1,
+            DataType::Tensor => data_chunks
+                .iter()
+                .find_map(|data_chunk| match &data_chunk.data {
+                    ChunkData::Tensor(tensors) => (0..tensors.len())
+                        .find(|&index| tensors.is_valid(index))
+                        .map(|index| tensors.value(index).len()),
+                    ChunkData::Scalar(_) => None,
+                })
+                .unwrap_or(0),
+        };
+
+        let mut timelines: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+        let mut values = Vec::with_capacity(num_rows * width);
+        let mut row = 0;
+        for data_chunk in data_chunks {
+            for (timeline, times) in &data_chunk.timelines {
+                let column = timelines
+                    .entry(timeline.clone())
+                    .or_insert_with(|| vec![i64::MIN; num_rows]);
+                column[row..row + times.len()].copy_from_slice(times);
+            }
+            row += data_chunk.data.len();
+
+            match &data_chunk.data {
+                ChunkData::Scalar(scalars) if data_type == DataType::Scalar => {
+                    values.extend(
+                        scalars
+                            .iter()
+                            .map(|value| value.copied().unwrap_or(f64::NAN)),
+                    );
+                }
+                ChunkData::Tensor(tensors) if data_type == DataType::Tensor => {
+                    for index in 0..tensors.len() {
+                        if !tensors.is_valid(index) {
+                            values.extend(std::iter::repeat_n(f64::NAN, width));
+                            continue;
+                        }
+                        let elements = tensors.value(index);
+                        if elements.len() != width {
+                            return Err(Error::Invalid(format!(
+                                "The tensors of {} change size from {} to {}",
+                                entity_path,
+                                width,
+                                elements.len()
+                            )));
+                        }
+                        values.extend(f64_values(elements.as_ref())?);
+                    }
+                }
+                _ => {
+                    return Err(Error::Invalid(format!(
+                        "{} mixes scalars and tensors",
+                        entity_path
+                    )))
+                }
+            }
+        }
+
+        Ok(Self {
+            path: CString::new(entity_path)
+                .map_err(|_| Error::Invalid(format!("Invalid entity path: {}", entity_path)))?,
+            data_type,
+            num_rows,
+            width,
+            timelines,
+            values,
+        })
+    }
+}
+
+fn f64_values(elements: &dyn Array) -> Result<Vec<f64>> {
+    let elements = cast(elements, &ArrowDataType::Float64, CastOptions::default())
+        .map_err(|e| Error::Invalid(format!("Data conversion error: {}", e)))?;
+    let elements = elements
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .expect("cast to Float64");
+    Ok(elements
+        .iter()
+        .map(|value| value.copied().unwrap_or(f64::NAN))
+        .collect())
+}
+
+/// Open an RRD file and decode the scalar and tensor entities of its recording.
+///
+/// On success, `*recording` is a handle to release with [`rq_close`].
+///
+/// # Safety
+///
+/// `file_path` must be a NUL-terminated string and `recording` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn rq_open(
+    file_path: *const c_char,
+    recording: *mut *mut Recording,
+) -> Status {
+    ffi(|| {
+        let file_path = str_arg(file_path, "file_path")?;
+        if recording.is_null() {
+            return Err(invalid("`recording` is null"));
+        }
+        *recording = Box::into_raw(Box::new(Recording::open(file_path)?));
+        Ok(())
+    })
+}
+
+/// Release a recording opened with [`rq_open`]. Does nothing if `recording` is null.
+///
+/// # Safety
+///
+/// `recording` must be null or a handle returned by [`rq_open`], not yet closed.
+#[no_mangle]
+pub unsafe extern "C" fn rq_close(recording: *mut Recording) {
+    if !recording.is_null() {
+        drop(Box::from_raw(recording));
+    }
+}
+
+/// The number of data entities of a recording, 0 if `recording` is null.
+///
+/// # Safety
+///
+/// `recording` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rq_entity_count(recording: *const Recording) -> usize {
+    recording
+        .as_ref()
+        .map_or(0, |recording| recording.entities.len())
+}
+
+/// The path of the data entity at `index`, sorted by path, or null if out of range.
+///
+/// The string is owned by the recording and valid until [`rq_close`].
+///
+/// # Safety
+///
+/// `recording` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn rq_entity_path(
+    recording: *const Recording,
+    index: usize,
+) -> *const c_char {
+    recording
+        .as_ref()
+        .and_then(|recording| recording.entities.get(index))
+        .map_or(ptr::null(), |entity| entity.path.as_ptr())
+}
+
+/// Get the type and the shape of the values of an entity, to size the buffers of
+/// [`rq_read_column`].
+///
+/// # Safety
+///
+/// `recording` must be a valid handle, `entity_path` a NUL-terminated string and `info` a valid
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn rq_entity_info(
+    recording: *const Recording,
+    entity_path: *const c_char,
+    info: *mut EntityInfo,
+) -> Status {
+    ffi(|| {
+        let recording = recording
+            .as_ref()
+            .ok_or_else(|| invalid("`recording` is null"))?;
+        let entity = recording.entity(str_arg(entity_path, "entity_path")?)?;
+        if info.is_null() {
+            return Err(invalid("`info` is null"));
+        }
+        *info = EntityInfo {
+            data_type: entity.data_type,
+            num_rows: entity.num_rows,
+            width: entity.width,
+        };
+        Ok(())
+    })
+}
+
+/// Copy the rows of an entity into buffers of the caller, in the order they were logged.
+///
+/// `times` receives a time per row on `timeline`, in nanoseconds for temporal timelines and
+/// `INT64_MIN` for rows without a time on the timeline. `values` receives `width` values per row,
+/// NaN for missing values. Either buffer may be null to skip it. `capacity` is the number of rows
+/// the buffers can hold, which must be at least the `num_rows` of [`rq_entity_info`].
+///
+/// # Safety
+///
+/// `recording` must be a valid handle, `entity_path` and `timeline` NUL-terminated strings, and
+/// the buffers null or valid for `capacity` rows.
+#[no_mangle]
+pub unsafe extern "C" fn rq_read_column(
+    recording: *const Recording,
+    entity_path: *const c_char,
+    timeline: *const c_char,
+    times: *mut i64,
+    values: *mut f64,
+    capacity: usize,
+) -> Status {
+    ffi(|| {
+        let recording = recording
+            .as_ref()
+            .ok_or_else(|| invalid("`recording` is null"))?;
+        let entity = recording.entity(str_arg(entity_path, "entity_path")?)?;
+        if capacity < entity.num_rows {
+            return Err((
+                Status::BufferTooSmall,
+                format!(
+                    "{} has {} rows, the buffers hold {}",
+                    entity.path.to_string_lossy(),
+                    entity.num_rows,
+                    capacity
+                ),
+            ));
+        }
+
+        if !times.is_null() {
+            let timeline = str_arg(timeline, "timeline")?;
+            let column = entity.timelines.get(timeline).ok_or_else(|| {
+                Error::NoData(format!(
+                    "{} has no timeline {}",
+                    entity.path.to_string_lossy(),
+                    timeline
+                ))
+            })?;
+            slice::from_raw_parts_mut(times, column.len()).copy_from_slice(column);
+        }
+        if !values.is_null() {
+            slice::from_raw_parts_mut(values, entity.values.len()).copy_from_slice(&entity.values);
+        }
+        Ok(())
+    })
+}
+
+/// The message of the last error of the calling thread, or an empty string.
+///
+/// The string is valid until the next failing call on the thread.
+#[no_mangle]
+pub extern "C" fn rq_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
@@ -0,0 +1,160 @@
+//! A minimal reader for the OMG CDR encoding used by ROS 2 messages.
+//!
+//! Only the plain (non-XCDR2) representations written by the ROS 2 middlewares are supported.
+//! Alignment is relative to the end of the 4 byte encapsulation header.
+
+use std::fmt;
+
+/// A malformed or unsupported CDR message.
+#[derive(Debug)]
+pub(crate) struct CdrError {
+    offset: usize,
+    message: String,
+}
+
+impl fmt::Display for CdrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for CdrError {}
+
+pub(crate) struct CdrReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    little_endian: bool,
+}
+
+impl<'a> CdrReader<'a> {
+    const HEADER_LEN: usize = 4;
+
+    /// Start reading a CDR message, including its encapsulation header.
+    pub fn new(data: &'a [u8]) -> Result<Self, CdrError> {
+        if data.len() < Self::HEADER_LEN {
+            return Err(CdrError {
+                offset: 0,
+                message: "Missing CDR encapsulation header".to_string(),
+            });
+        }
+        let little_endian = match data[1] {
+            0x00 => false,
+            0x01 => true,
+            representation => {
+                return Err(CdrError {
+                    offset: 1,
+                    message: format!("Unsupported CDR representation {:#04x}", representation),
+                })
+            }
+        };
+
+        Ok(Self {
+            data,
+            pos: Self::HEADER_LEN,
+            little_endian,
+        })
+    }
+
+    fn error(&self, message: impl Into<String>) -> CdrError {
+        CdrError {
+            offset: self.pos,
+            message: message.into(),
+        }
+    }
+
+    fn align(&mut self, alignment: usize) {
+        let relative = self.pos - Self::HEADER_LEN;
+        self.pos += (alignment - relative % alignment) % alignment;
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CdrError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| {
+                self.error(format!("Unexpected end of message reading {} bytes", len))
+            })?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], CdrError> {
+        self.align(N);
+        let bytes = self.take(N)?;
+        Ok(bytes.try_into().expect("take returns exactly N bytes"))
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, CdrError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, CdrError> {
+        let bytes = self.take_array()?;
+        Ok(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32, CdrError> {
+        Ok(self.read_u32()? as i32)
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, CdrError> {
+        let bytes = self.take_array()?;
+        Ok(if self.little_endian {
+            f64::from_le_bytes(bytes)
+        } else {
+            f64::from_be_bytes(bytes)
+        })
+    }
+
+    /// Read the length prefix of a sequence, checking it against the remaining bytes.
+    fn read_len(&mut self, element_size: usize) -> Result<usize, CdrError> {
+        let len = self.read_u32()? as usize;
+        if len.saturating_mul(element_size) > self.data.len() - self.pos {
+            return Err(self.error(format!("Sequence of {} elements exceeds the message", len)));
+        }
+        Ok(len)
+    }
+
+    pub fn read_string(&mut self) -> Result<String, CdrError> {
+        let len = self.read_len(1)?;
+        let bytes = self.take(len)?;
+        // The length includes the terminating NUL
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("Invalid UTF-8 string"))
+    }
+
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], CdrError> {
+        let len = self.read_len(1)?;
+        self.take(len)
+    }
+
+    pub fn read_f64_sequence(&mut self) -> Result<Vec<f64>, CdrError> {
+        let len = self.read_len(8)?;
+        (0..len).map(|_| self.read_f64()).collect()
+    }
+
+    pub fn read_string_sequence(&mut self) -> Result<Vec<String>, CdrError> {
+        let len = self.read_len(4)?;
+        (0..len).map(|_| self.read_string()).collect()
+    }
+
+    /// Read a `builtin_interfaces/msg/Time` as nanoseconds.
+    pub fn read_time(&mut self) -> Result<i64, CdrError> {
+        let sec = self.read_i32()? as i64;
+        let nanosec = self.read_u32()? as i64;
+        Ok(sec * 1_000_000_000 + nanosec)
+    }
+
+    /// Read a `std_msgs/msg/Header`, returning its stamp in nanoseconds and its frame id.
+    pub fn read_header(&mut self) -> Result<(i64, String), CdrError> {
+        let stamp = self.read_time()?;
+        let frame_id = self.read_string()?;
+        Ok((stamp, frame_id))
+    }
+}
@@ -0,0 +1,455 @@
+//! Conversion of Rerun chunk columns into plain Arrow columns.
+//!
+//! Rerun stores every component as a list array whose items are the (possibly nested) Rerun
+//! datatypes. Exporters want flat, well typed columns instead, e.g. a `Float64` column for
+//! scalars and a `List<Float32>` column for tensors.
+
+use re_arrow2::{
+    array::{
+        self, new_empty_array, new_null_array, Array, ListArray, PrimitiveArray, StructArray,
+        UnionArray,
+    },
+    bitmap::MutableBitmap,
+    chunk::Chunk as ArrowChunk,
+    compute::concatenate::concatenate,
+    datatypes::{DataType, Field, Schema, TimeUnit},
+    error::Error,
+    offset::Offsets,
+};
+use re_chunk::{Chunk, ComponentName};
+use re_log_types::TimeType;
+
+/// A named column, ready to be written by an exporter.
+pub(crate) type Column = (Field, Box<dyn Array>);
+
+/// A record batch of columns, e.g. a Parquet row group.
+pub(crate) type Batch = ArrowChunk<Box<dyn Array>>;
+
+/// Convert every timeline of a chunk into a named column.
+///
+/// Temporal timelines become nanosecond timestamps, sequence timelines plain `Int64`.
+pub(crate) fn timeline_columns(chunk: &Chunk) -> Vec<Column> {
+    chunk
+        .timelines()
+        .iter()
+        .map(|(timeline, time_column)| {
+            let times = PrimitiveArray::<i64>::from_slice(time_column.times_raw())
+                .to(timeline_type(timeline.typ()));
+            let field = Field::new(timeline.name().as_str(), times.data_type().clone(), false);
+            (field, times.boxed())
+        })
+        .collect()
+}
+
+fn timeline_type(time_type: TimeType) -> DataType {
+    match time_type {
+        TimeType::Time => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        TimeType::Sequence => DataType::Int64,
+    }
+}
+
+/// The schema of the record batches of [`entity_table`], without converting any row.
+///
+/// The element type of a tensor column is read from the first tensor of the chunks. Chunks
+/// without any tensor do not constrain it, and it is `Float64` if no chunk holds a tensor.
+pub(crate) fn entity_schema(chunks: &[Chunk]) -> Result<Schema, Error> {
+    let mut fields: Vec<(String, Option<DataType>)> = Vec::new();
+
+    for chunk in chunks {
+        let mut chunk_fields: Vec<(String, Option<DataType>)> = chunk
+            .timelines()
+            .keys()
+            .map(|timeline| {
+                let data_type = timeline_type(timeline.typ());
+                (timeline.name().to_string(), Some(data_type))
+            })
+            .collect();
+        for (component_name, list_array) in chunk.components() {
+            if let Some(data_type) = component_type(component_name, list_array)? {
+                chunk_fields.push((component_name.short_name().to_string(), data_type));
+            }
+        }
+
+        for (name, data_type) in chunk_fields {
+            match fields.iter_mut().find(|(existing, _)| *existing == name) {
+                Some((_, Some(existing))) => match &data_type {
+                    Some(data_type) if data_type != existing => {
+                        return Err(Error::InvalidArgumentError(format!(
+                            "Column '{}' changes type from {:?} to {:?}",
+                            name, existing, data_type
+                        )));
+                    }
+                    _ => {}
+                },
+                Some((_, existing)) => *existing = data_type,
+                None => fields.push((name, data_type)),
+            }
+        }
+    }
+
+    // Timelines may be missing from some chunks too, so every column has to be nullable
+    Ok(Schema::from(
+        fields
+            .into_iter()
+            .map(|(name, data_type)| {
+                let data_type = data_type
+                    .unwrap_or_else(|| ListArray::<i32>::default_datatype(DataType::Float64));
+                Field::new(name, data_type, true)
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// The type of the column of [`component_column`], `Ok(None)` for a component that cannot be
+/// exported, and `Ok(Some(None))` for a tensor component without any tensor.
+fn component_type(
+    component_name: &ComponentName,
+    list_array: &ListArray<i32>,
+) -> Result<Option<Option<DataType>>, Error> {
+    if component_name.is_indicator_component() {
+        return Ok(None);
+    }
+
+    match component_name.as_str() {
+        "rerun.components.Scalar" => Ok(Some(Some(DataType::Float64))),
+        "rerun.components.TensorData" => Ok(Some(
+            tensor_element_type(list_array)?.map(ListArray::<i32>::default_datatype),
+        )),
+        _ if has_union(list_array.data_type()) => Ok(None),
+        _ => Ok(Some(Some(list_array.data_type().clone()))),
+    }
+}
+
+/// Convert the chunks of one entity into record batches sharing one schema, one per chunk.
+///
+/// Chunks of the same entity do not necessarily share the same timelines and components, so the
+/// schema is the union of all chunk columns, see [`entity_schema`], and columns missing from a
+/// chunk are null.
+pub(crate) fn entity_table(chunks: &[Chunk]) -> Result<(Schema, Vec<Batch>), Error> {
+    let schema = entity_schema(chunks)?;
+
+    let batches = chunks
+        .iter()
+        .map(|chunk| {
+            let mut columns = timeline_columns(chunk);
+            columns.extend(component_columns(chunk)?);
+
+            let arrays: Vec<Box<dyn Array>> = schema
+                .fields
+                .iter()
+                .map(|field| {
+                    match columns
+                        .iter()
+                        .position(|(column, _)| column.name == field.name)
+                    {
+                        // A tensor column without any tensor has the default element type
+                        Some(index) if columns[index].1.data_type() != &field.data_type => {
+                            new_null_array(field.data_type.clone(), chunk.num_rows())
+                        }
+                        Some(index) => columns.swap_remove(index).1,
+                        None => new_null_array(field.data_type.clone(), chunk.num_rows()),
+                    }
+                })
+                .collect();
+            ArrowChunk::try_new(arrays)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok((schema, batches))
+}
+
+/// Convert every exportable component of a chunk into a named column.
+///
+/// Columns are named after the short component name (`Scalar`, `TensorData`, ...). Indicator
+/// components and components without a flat representation are skipped.
+pub(crate) fn component_columns(chunk: &Chunk) -> Result<Vec<Column>, Error> {
+    let mut columns = Vec::new();
+    for (component_name, list_array) in chunk.components() {
+        if let Some(column) = component_column(component_name, list_array)? {
+            let field = Field::new(
+                component_name.short_name(),
+                column.data_type().clone(),
+                true,
+            );
+            columns.push((field, column));
+        }
+    }
+    Ok(columns)
+}
+
+/// Convert a single component column, or return `None` if it cannot be exported.
+pub(crate) fn component_column(
+    component_name: &ComponentName,
+    list_array: &ListArray<i32>,
+) -> Result<Option<Box<dyn Array>>, Error> {
+    if component_name.is_indicator_component() {
+        return Ok(None);
+    }
+
+    match component_name.as_str() {
+        "rerun.components.Scalar" => Ok(Some(scalar_column(list_array).boxed())),
+        "rerun.components.TensorData" => Ok(Some(tensor_column(list_array)?.boxed())),
+        _ if has_union(list_array.data_type()) => Ok(None),
+        _ => Ok(Some(list_array.clone().boxed())),
+    }
+}
+
+/// Flatten a `Scalar` component into one `Float64` value per row.
+///
+/// Rows without a value become nulls.
+pub(crate) fn scalar_column(list_array: &ListArray<i32>) -> PrimitiveArray<f64> {
+    (0..list_array.len())
+        .map(|i| {
+            if !list_array.is_valid(i) {
+                return None;
+            }
+            let sub_array = list_array.value(i);
+            sub_array
+                .as_any()
+                .downcast_ref::<array::Float64Array>()
+                .filter(|values| !values.is_empty() && values.is_valid(0))
+                .map(|values| values.value(0))
+        })
+        .collect()
+}
+
+/// Flatten a `TensorData` component into one list of tensor elements per row.
+///
+/// The element type of the resulting `List` column is the native type of the tensor buffer,
+/// which must be the same for every row of the column.
+pub(crate) fn tensor_column(list_array: &ListArray<i32>) -> Result<ListArray<i32>, Error> {
+    let mut buffers: Vec<Box<dyn Array>> = Vec::with_capacity(list_array.len());
+    let mut offsets = Offsets::<i32>::with_capacity(list_array.len());
+    let mut validity = MutableBitmap::with_capacity(list_array.len());
+    let mut inner_type: Option<DataType> = None;
+
+    for i in 0..list_array.len() {
+        let buffer = if list_array.is_valid(i) {
+            tensor_buffer(list_array.value(i).as_ref())?
+        } else {
+            None
+        };
+
+        match buffer {
+            Some(buffer) => {
+                match &inner_type {
+                    Some(data_type) if data_type != buffer.data_type() => {
+                        return Err(Error::InvalidArgumentError(format!(
+                            "Tensor buffer type changed from {:?} to {:?} at row {}",
+                            data_type,
+                            buffer.data_type(),
+                            i
+                        )));
+                    }
+                    Some(_) => {}
+                    None => inner_type = Some(buffer.data_type().clone()),
+                }
+                offsets.try_push_usize(buffer.len())?;
+                validity.push(true);
+                buffers.push(buffer);
+            }
+            None => {
+                offsets.extend_constant(1);
+                validity.push(false);
+            }
+        }
+    }
+
+    let inner_type = inner_type.unwrap_or(DataType::Float64);
+    let values = if buffers.is_empty() {
+        new_empty_array(inner_type.clone())
+    } else {
+        let buffers: Vec<&dyn Array> = buffers.iter().map(|buffer| buffer.as_ref()).collect();
+        concatenate(&buffers)?
+    };
+
+    ListArray::try_new(
+        ListArray::<i32>::default_datatype(inner_type),
+        offsets.into(),
+        values,
+        validity.into(),
+    )
+}
+
+/// The native type of the elements of the first tensor of a `TensorData` column, or `None` if
+/// the column holds no tensor.
+pub(crate) fn tensor_element_type(list_array: &ListArray<i32>) -> Result<Option<DataType>, Error> {
+    for i in 0..list_array.len() {
+        if !list_array.is_valid(i) {
+            continue;
+        }
+        if let Some(buffer) = tensor_buffer(list_array.value(i).as_ref())? {
+            return Ok(Some(buffer.data_type().clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Extract the element buffer of the first tensor stored in a `TensorData` row.
+fn tensor_buffer(row: &dyn Array) -> Result<Option<Box<dyn Array>>, Error> {
+    let Some(struct_array) = row.as_any().downcast_ref::<StructArray>() else {
+        return Err(Error::InvalidArgumentError(format!(
+            "Expected TensorData to be a StructArray, got {:?}",
+            row.data_type()
+        )));
+    };
+    if struct_array.is_empty() || !struct_array.is_valid(0) {
+        return Ok(None);
+    }
+
+    let buffer = struct_field(struct_array, "buffer")?;
+    let Some(union_array) = buffer.as_any().downcast_ref::<UnionArray>() else {
+        return Err(Error::InvalidArgumentError(format!(
+            "Expected tensor buffer to be a UnionArray, got {:?}",
+            buffer.data_type()
+        )));
+    };
+
+    let field_index = union_array.types()[0] as usize;
+    let value_index = match union_array.offsets() {
+        Some(offsets) => offsets[0] as usize,
+        None => 0,
+    };
+    let field = &union_array.fields()[field_index];
+
+    match field.as_any().downcast_ref::<ListArray<i32>>() {
+        Some(list_array) if list_array.is_valid(value_index) => {
+            Ok(Some(list_array.value(value_index)))
+        }
+        Some(_) => Ok(None),
+        // The `_null_markers` variant of the union
+        None => Ok(None),
+    }
+}
+
+/// A dimension of a tensor.
+#[cfg(feature = "export")]
+pub(crate) struct TensorDimension {
+    pub size: u64,
+    pub name: Option<String>,
+}
+
+/// Extract the shape of the first tensor stored in a `TensorData` column.
+///
+/// Returns `None` if the column holds no tensor.
+#[cfg(feature = "export")]
+pub(crate) fn tensor_shape(list_array: &ListArray<i32>) -> Option<Vec<TensorDimension>> {
+    let row = (0..list_array.len())
+        .filter(|&i| list_array.is_valid(i))
+        .map(|i| list_array.value(i))
+        .find(|row| !row.is_empty())?;
+    let struct_array = row.as_any().downcast_ref::<StructArray>()?;
+
+    let shape = struct_field(struct_array, "shape").ok()?;
+    let dimensions = shape.as_any().downcast_ref::<ListArray<i32>>()?.value(0);
+    let dimensions = dimensions.as_any().downcast_ref::<StructArray>()?;
+    let sizes = struct_field(dimensions, "size").ok()?;
+    let sizes = sizes.as_any().downcast_ref::<array::UInt64Array>()?;
+    let names = struct_field(dimensions, "name").ok()?;
+    let names = names.as_any().downcast_ref::<array::Utf8Array<i32>>()?;
+
+    Some(
+        sizes
+            .values_iter()
+            .zip(names.iter())
+            .map(|(size, name)| TensorDimension {
+                size: *size,
+                name: name.map(str::to_string),
+            })
+            .collect(),
+    )
+}
+
+/// Extract the bytes of the first blob stored in a `Blob` (or `ImageBuffer`) row.
+#[cfg(feature = "export")]
+pub(crate) fn blob_bytes(list_array: &ListArray<i32>, row: usize) -> Option<Vec<u8>> {
+    if !list_array.is_valid(row) {
+        return None;
+    }
+    let blobs = list_array.value(row);
+    let blobs = blobs.as_any().downcast_ref::<ListArray<i32>>()?;
+    if blobs.is_empty() || !blobs.is_valid(0) {
+        return None;
+    }
+    let bytes = blobs.value(0);
+    let bytes = bytes.as_any().downcast_ref::<PrimitiveArray<u8>>()?;
+    Some(bytes.values().to_vec())
+}
+
+/// Extract the first string stored in a `Utf8` based row (`Text`, `MediaType`, ...).
+#[cfg(feature = "export")]
+pub(crate) fn first_str(list_array: &ListArray<i32>, row: usize) -> Option<String> {
+    if !list_array.is_valid(row) {
+        return None;
+    }
+    let strings = list_array.value(row);
+    let strings = strings.as_any().downcast_ref::<array::Utf8Array<i32>>()?;
+    if strings.is_empty() || !strings.is_valid(0) {
+        return None;
+    }
+    Some(strings.value(0).to_string())
+}
+
+/// The format of a raw image, see `rerun.datatypes.ImageFormat`.
+///
+/// The enums are kept as their raw Rerun values.
+#[cfg(feature = "export")]
+pub(crate) struct ImageFormat {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: Option<u8>,
+    pub color_model: Option<u8>,
+    pub channel_datatype: Option<u8>,
+}
+
+/// Extract the first image format stored in an `ImageFormat` row.
+#[cfg(feature = "export")]
+pub(crate) fn image_format(list_array: &ListArray<i32>, row: usize) -> Option<ImageFormat> {
+    if !list_array.is_valid(row) {
+        return None;
+    }
+    let formats = list_array.value(row);
+    let formats = formats.as_any().downcast_ref::<StructArray>()?;
+    if formats.is_empty() || !formats.is_valid(0) {
+        return None;
+    }
+
+    let u32_field = |name: &str| -> Option<u32> {
+        let values = struct_field(formats, name).ok()?;
+        let values = values.as_any().downcast_ref::<array::UInt32Array>()?;
+        values.get(0)
+    };
+    let u8_field = |name: &str| -> Option<u8> {
+        let values = struct_field(formats, name).ok()?;
+        let values = values.as_any().downcast_ref::<array::UInt8Array>()?;
+        values.get(0)
+    };
+
+    Some(ImageFormat {
+        width: u32_field("width")?,
+        height: u32_field("height")?,
+        pixel_format: u8_field("pixel_format"),
+        color_model: u8_field("color_model"),
+        channel_datatype: u8_field("channel_datatype"),
+    })
+}
+
+fn struct_field<'a>(struct_array: &'a StructArray, name: &str) -> Result<&'a dyn Array, Error> {
+    struct_array
+        .fields()
+        .iter()
+        .position(|field| field.name == name)
+        .map(|index| struct_array.values()[index].as_ref())
+        .ok_or_else(|| Error::InvalidArgumentError(format!("Missing struct field '{}'", name)))
+}
+
+fn has_union(data_type: &DataType) -> bool {
+    match data_type.to_logical_type() {
+        DataType::Union(_, _, _) => true,
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            has_union(field.data_type())
+        }
+        DataType::Struct(fields) => fields.iter().any(|field| has_union(field.data_type())),
+        _ => false,
+    }
+}
@@ -0,0 +1,325 @@
+//! Export of RRD data entities to CSV files.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use chrono::{DateTime, SecondsFormat};
+use log::debug;
+use re_arrow2::array::{get_display, Array, ListArray};
+use re_chunk::{Chunk, ComponentName};
+use re_log_types::{TimeType, Timeline};
+
+use crate::{columns, entity_file_path, index::load_recording, query_data_chunks, Error, Result};
+
+/// How the values of temporal timelines are written.
+#[derive(Clone, Copy)]
+enum TimeFormat {
+    /// Integer nanoseconds, as stored in the recording.
+    Raw,
+    /// Floating point seconds.
+    Seconds,
+    /// RFC 3339 / ISO 8601 timestamps in UTC.
+    Iso8601,
+}
+
+impl TimeFormat {
+    fn parse(time_format: &str) -> Result<Self> {
+        match time_format {
+            "raw" | "" => Ok(Self::Raw),
+            "seconds" => Ok(Self::Seconds),
+            "iso8601" => Ok(Self::Iso8601),
+            _ => Err(Error::Invalid(format!(
+                "Unsupported time format: {}. Use \"raw\", \"seconds\" or \"iso8601\"",
+                time_format
+            ))),
+        }
+    }
+
+    fn format(self, timeline: &Timeline, time: i64) -> String {
+        // Sequence timelines are plain counters, only temporal timelines get formatted
+        if timeline.typ() == TimeType::Sequence {
+            return time.to_string();
+        }
+
+        match self {
+            Self::Raw => time.to_string(),
+            Self::Seconds => (time as f64 * 1e-9).to_string(),
+            Self::Iso8601 => {
+                DateTime::from_timestamp_nanos(time).to_rfc3339_opts(SecondsFormat::Nanos, true)
+            }
+        }
+    }
+}
+
+/// The CSV columns written for one component.
+struct ComponentColumns {
+    component_name: ComponentName,
+    headers: Vec<String>,
+}
+
+/// Export the data entities (scalar or tensor) of an RRD file to CSV, one file per entity.
+///
+/// Each file mirrors the entity path inside `out_dir`, e.g. `/action/joints/position` is written
+/// to `<out_dir>/action/joints/position.csv`. The header holds the timeline names followed by the
+/// value columns: `Scalar` for scalars, and one column per tensor element for tensors, named after
+/// the tensor dimensions (e.g. `joint_0`, `joint_1`, ...) or `TensorData_<i>` if they are unnamed.
+/// Every logged row of the entity becomes one CSV row. Tensor entities without any tensor have no
+/// shape to name the columns after, and are rejected.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `out_dir` - The directory to write the CSV files to. Created if missing.
+/// * `data_type_filter` - A string slice that holds the data type to filter. Set to "scalar" or "tensor" to filter by data type.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to export all data.
+/// * `delimiter` - The field delimiter, a single ASCII character.
+/// * `time_format` - How temporal timelines are written: "raw" (nanoseconds), "seconds" or "iso8601".
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The paths of the written CSV files.
+pub fn export_csv(
+    file_path: &str,
+    out_dir: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    delimiter: &str,
+    time_format: &str,
+) -> Result<Vec<String>> {
+    let delimiter = match delimiter.as_bytes() {
+        [delimiter] => *delimiter,
+        _ => {
+            return Err(Error::Invalid(format!(
+                "Invalid delimiter: {:?}. Use a single ASCII character",
+                delimiter
+            )))
+        }
+    };
+    let time_format = TimeFormat::parse(time_format)?;
+
+    let recording = load_recording(file_path)?;
+    let rrd = recording.entity_db();
+
+    let mut chunks_per_entity: BTreeMap<String, Vec<Chunk>> = BTreeMap::new();
+    for chunk in query_data_chunks(rrd, data_type_filter, entity_path_filter) {
+        chunks_per_entity
+            .entry(chunk.entity_path().to_string())
+            .or_default()
+            .push(chunk);
+    }
+
+    if chunks_per_entity.is_empty() {
+        return Err(Error::NoData(
+            "No data found for the specified entity and data type".to_string(),
+        )
+        .with_path(file_path)
+        .with_entity(entity_path_filter));
+    }
+
+    let mut written = Vec::new();
+    for (entity_path, chunks) in &chunks_per_entity {
+        let path = entity_file_path(Path::new(out_dir), entity_path, "csv")
+            .map_err(|e| e.with_path(file_path))?;
+        debug!(
+            "Writing {} chunks of {} to {:?}",
+            chunks.len(),
+            entity_path,
+            path
+        );
+
+        write_csv(&path, chunks, delimiter, time_format)
+            .map_err(|e| e.with_path(file_path).with_entity(entity_path))?;
+        written.push(path.to_string_lossy().into_owned());
+    }
+
+    Ok(written)
+}
+
+/// Write the chunks of a single entity to one CSV file.
+fn write_csv(path: &Path, chunks: &[Chunk], delimiter: u8, time_format: TimeFormat) -> Result<()> {
+    let timelines: BTreeSet<Timeline> = chunks
+        .iter()
+        .flat_map(|chunk| chunk.timelines().keys().copied())
+        .collect();
+
+    let mut components: Vec<ComponentColumns> = Vec::new();
+    for chunk in chunks {
+        for (component_name, list_array) in chunk.components() {
+            if components
+                .iter()
+                .any(|component| component.component_name == *component_name)
+            {
+                continue;
+            }
+            let headers = match component_name.as_str() {
+                "rerun.components.Scalar" => vec![component_name.short_name().to_string()],
+                // Chunks without any tensor leave the headers to the next chunks
+                "rerun.components.TensorData" => match tensor_headers(list_array) {
+                    Some(headers) => headers,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            components.push(ComponentColumns {
+                component_name: *component_name,
+                headers,
+            });
+        }
+    }
+
+    let tensor = ComponentName::from("rerun.components.TensorData");
+    if chunks
+        .iter()
+        .any(|chunk| chunk.components().contains_key(&tensor))
+        && !components
+            .iter()
+            .any(|component| component.component_name == tensor)
+    {
+        return Err(Error::Invalid(format!(
+            "Cannot determine the shape of {}",
+            tensor
+        )));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(Error::Write)?;
+    }
+    let mut writer = ::csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)
+        .map_err(csv_error)?;
+
+    let header = timelines
+        .iter()
+        .map(|timeline| timeline.name().to_string())
+        .chain(
+            components
+                .iter()
+                .flat_map(|component| component.headers.iter().cloned()),
+        );
+    writer.write_record(header).map_err(csv_error)?;
+
+    for chunk in chunks {
+        // Flatten the components once per chunk rather than once per row
+        let mut values: Vec<Option<Box<dyn Array>>> = Vec::with_capacity(components.len());
+        for component in &components {
+            let column = match chunk.components().get(&component.component_name) {
+                Some(list_array) => {
+                    columns::component_column(&component.component_name, list_array)
+                        .map_err(|e| Error::Encode(format!("CSV export error: {}", e)))?
+                }
+                None => None,
+            };
+            values.push(column);
+        }
+
+        for row in 0..chunk.num_rows() {
+            let mut record: Vec<String> = timelines
+                .iter()
+                .map(|timeline| match chunk.timelines().get(timeline) {
+                    Some(time_column) => time_format.format(timeline, time_column.times_raw()[row]),
+                    None => String::new(),
+                })
+                .collect();
+
+            for (component, column) in components.iter().zip(&values) {
+                append_values(&mut record, component, column.as_deref(), row)?;
+            }
+
+            writer.write_record(&record).map_err(csv_error)?;
+        }
+    }
+
+    writer.flush().map_err(Error::Write)
+}
+
+/// Append the CSV fields of one component at `row` to `record`.
+///
+/// Missing components and null values are written as empty fields.
+fn append_values(
+    record: &mut Vec<String>,
+    component: &ComponentColumns,
+    column: Option<&dyn Array>,
+    row: usize,
+) -> Result<()> {
+    let num_columns = component.headers.len();
+    let Some(column) = column.filter(|column| column.is_valid(row)) else {
+        record.extend(std::iter::repeat_n(String::new(), num_columns));
+        return Ok(());
+    };
+
+    // Tensors are flattened into lists, scalars are plain values
+    let (values, start, len) = match column.as_any().downcast_ref::<ListArray<i32>>() {
+        Some(list_array) => {
+            let (start, end) = list_array.offsets().start_end(row);
+            (list_array.values().as_ref(), start, end - start)
+        }
+        None => (column, row, 1),
+    };
+
+    if len != num_columns {
+        return Err(Error::Invalid(format!(
+            "Row {} of {} has {} values, expected {}",
+            row, component.component_name, len, num_columns
+        )));
+    }
+
+    let display = get_display(values, "");
+    for i in start..start + len {
+        let mut field = String::new();
+        display(&mut field, i).map_err(|e| Error::Encode(format!("CSV export error: {}", e)))?;
+        record.push(field);
+    }
+
+    Ok(())
+}
+
+/// Name one column per tensor element, using the tensor dimension names where present.
+///
+/// A `[2, 3]` tensor with dimensions named `arm` and `axis` gets the columns `arm_0_axis_0`,
+/// `arm_0_axis_1`, ..., `arm_1_axis_2`. Unnamed tensors get `TensorData_0`, `TensorData_1`, ...
+///
+/// Returns `None` if the column holds no tensor.
+fn tensor_headers(list_array: &ListArray<i32>) -> Option<Vec<String>> {
+    let shape = columns::tensor_shape(list_array)?;
+    let num_elements = shape
+        .iter()
+        .map(|dimension| dimension.size as usize)
+        .product();
+
+    if shape.iter().all(|dimension| dimension.name.is_none()) {
+        return Some(
+            (0..num_elements)
+                .map(|i| format!("TensorData_{}", i))
+                .collect(),
+        );
+    }
+
+    let headers = (0..num_elements)
+        .map(|flat_index| {
+            // Row-major: the last dimension varies fastest
+            let mut remainder = flat_index;
+            let mut parts = Vec::with_capacity(shape.len());
+            for (axis, dimension) in shape.iter().enumerate().rev() {
+                let size = (dimension.size as usize).max(1);
+                let index = remainder % size;
+                remainder /= size;
+                let name = match &dimension.name {
+                    Some(name) => name.clone(),
+                    None => format!("dim{}", axis),
+                };
+                parts.push(format!("{}_{}", name, index));
+            }
+            parts.reverse();
+            parts.join("_")
+        })
+        .collect();
+    Some(headers)
+}
+
+fn csv_error(e: ::csv::Error) -> Error {
+    Error::Write(e.into())
+}
@@ -0,0 +1,129 @@
+//! The error type of the crate.
+
+use std::fmt;
+
+/// Everything that can go wrong while reading, querying, exporting or rewriting recordings.
+#[derive(Debug)]
+pub enum Error {
+    /// An input file could not be opened or read.
+    Open(std::io::Error),
+    /// An output file could not be written.
+    Write(std::io::Error),
+    /// An input file could not be decoded.
+    Decode(String),
+    /// The data could not be encoded in the output format.
+    Encode(String),
+    /// The recording store to query was not found.
+    StoreNotFound(String),
+    /// No data matches the filters.
+    NoData(String),
+    /// A component has a type that the operation doesn't support.
+    UnsupportedComponent(String),
+    /// An argument is invalid, or the data doesn't allow the operation.
+    Invalid(String),
+    /// An error with where it happened, see [`Error::context`].
+    WithContext(Box<Error>, ErrorContext),
+}
+
+/// Where an error happened, as far as it is known.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The path of the RRD file being read.
+    pub path: Option<String>,
+    /// The path of the entity, or the entity filter of the query.
+    pub entity: Option<String>,
+    /// The full name of the component, e.g. `rerun.components.Scalar`.
+    pub component: Option<String>,
+    /// The index of the message of the file that could not be decoded, from 0.
+    pub message_index: Option<usize>,
+}
+
+/// A `Result` with [`Error`] as error type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// The error without its context, never [`Error::WithContext`].
+    pub fn kind(&self) -> &Error {
+        match self {
+            Self::WithContext(e, _) => e,
+            e => e,
+        }
+    }
+
+    /// Where the error happened, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::WithContext(_, context) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Set the path of the file being read, unless already known.
+    pub(crate) fn with_path(mut self, path: &str) -> Self {
+        self.context_mut()
+            .path
+            .get_or_insert_with(|| path.to_string());
+        self
+    }
+
+    /// Set the entity, unless already known.
+    pub(crate) fn with_entity(mut self, entity: &str) -> Self {
+        self.context_mut()
+            .entity
+            .get_or_insert_with(|| entity.to_string());
+        self
+    }
+
+    /// Set the component, unless already known.
+    pub(crate) fn with_component(mut self, component: &str) -> Self {
+        self.context_mut()
+            .component
+            .get_or_insert_with(|| component.to_string());
+        self
+    }
+
+    /// Set the index of the message that could not be decoded, unless already known.
+    pub(crate) fn with_message_index(mut self, message_index: usize) -> Self {
+        self.context_mut()
+            .message_index
+            .get_or_insert(message_index);
+        self
+    }
+
+    fn context_mut(&mut self) -> &mut ErrorContext {
+        if !matches!(self, Self::WithContext(..)) {
+            let e = std::mem::replace(self, Self::Invalid(String::new()));
+            *self = Self::WithContext(Box::new(e), ErrorContext::default());
+        }
+        match self {
+            Self::WithContext(_, context) => context,
+            _ => unreachable!("the error was wrapped above"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open(e) => write!(f, "File open error: {}", e),
+            Self::Write(e) => write!(f, "File write error: {}", e),
+            Self::Decode(message) => write!(f, "Decoding error: {}", message),
+            Self::Encode(message)
+            | Self::StoreNotFound(message)
+            | Self::NoData(message)
+            | Self::UnsupportedComponent(message)
+            | Self::Invalid(message) => f.write_str(message),
+            Self::WithContext(e, _) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Open(e) | Self::Write(e) => Some(e),
+            Self::WithContext(e, _) => e.source(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! An Arrow Flight service over a folder of RRD files, built with the `flight` feature.
+//!
+//! A ticket is the JSON encoding of a [`FlightTicket`]: a recording of the folder and the
+//! [`TableQuery`] of one of its entities, e.g.
+//!
+//! ```text
+//! {"file": "sample.rrd", "entity": "/action/joints/position", "start": 1724121014346522000,
+//!  "end": 1724121044346522000, "timeline": "log_time", "components": ["TensorData"]}
+//! ```
+//!
+//! `DoGet` streams the rows of the entity as record batches, one per chunk. `ListFlights` lists
+//! a flight per entity of every recording, skipping the files without a recording to query, or of
+//! the recording named by the criteria expression, and `GetFlightInfo` and `GetSchema` accept a
+//! command descriptor holding a ticket.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow_format::flight::{
+    data::{
+        flight_descriptor::DescriptorType, Action, ActionType, Criteria, Empty, FlightData,
+        FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest, HandshakeResponse,
+        PutResult, Result as ActionResult, SchemaResult, Ticket,
+    },
+    service::flight_service_server::{FlightService, FlightServiceServer},
+};
+use futures::stream::{self, BoxStream, StreamExt};
+use log::warn;
+use re_arrow2::{
+    datatypes::Schema,
+    io::flight::{
+        default_ipc_fields, serialize_batch, serialize_schema, serialize_schema_to_info,
+        serialize_schema_to_result, WriteOptions,
+    },
+};
+use re_chunk::Chunk;
+use serde::{Deserialize, Serialize};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+use crate::{
+    folder_rrd_path, index::load_recording, query_chunks, query_entity_table, EntityTable, Error,
+    Result, TableQuery,
+};
+
+/// The rows of an entity of a recording to stream, encoded as JSON in the Flight tickets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlightTicket {
+    /// The name of the `.rrd` file inside the served folder.
+    pub file: String,
+    /// The entity, time range and components to stream.
+    #[serde(flatten)]
+    pub query: TableQuery,
+}
+
+impl FlightTicket {
+    /// Decode a ticket from its JSON encoding.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).map_err(|e| Error::Invalid(format!("Invalid ticket: {}", e)))
+    }
+
+    /// Encode the ticket as JSON.
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("tickets serialize to JSON")
+    }
+}
+
+/// The Flight service of a folder of recordings.
+pub struct RecordingService {
+    folder_path: Arc<PathBuf>,
+}
+
+impl RecordingService {
+    /// Serve the `.rrd` files of `folder_path`.
+    pub fn new(folder_path: impl Into<PathBuf>) -> Self {
+        Self {
+            folder_path: Arc::new(folder_path.into()),
+        }
+    }
+}
+
+/// Serve the recordings of `folder_path` over Arrow Flight on `addr`, e.g. `127.0.0.1:50051`,
+/// until stopped.
+pub async fn serve(folder_path: impl Into<PathBuf>, addr: &str) -> std::io::Result<()> {
+    let addr: SocketAddr = addr.parse().map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Invalid address {}: {}", addr, e),
+        )
+    })?;
+
+    Server::builder()
+        .add_service(FlightServiceServer::new(RecordingService::new(folder_path)))
+        .serve(addr)
+        .await
+        .map_err(std::io::Error::other)
+}
+
+impl From<Error> for Status {
+    fn from(e: Error) -> Self {
+        let message = e.to_string();
+        match e.kind() {
+            Error::Open(_) | Error::StoreNotFound(_) | Error::NoData(_) => {
+                Status::not_found(message)
+            }
+            Error::Invalid(_) => Status::invalid_argument(message),
+            Error::Decode(_) => Status::data_loss(message),
+            Error::UnsupportedComponent(_) => Status::unimplemented(message),
+            Error::Write(_) | Error::Encode(_) | Error::WithContext(..) => {
+                Status::internal(message)
+            }
+        }
+    }
+}
+
+/// Run blocking work, such as decoding a recording, off the async runtime.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T, Status> {
+    Ok(tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))??)
+}
+
+#[tonic::async_trait]
+impl FlightService for RecordingService {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoActionStream = BoxStream<'static, Result<ActionResult, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("The service requires no handshake"))
+    }
+
+    async fn list_flights(
+        &self,
+        request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let folder_path = self.folder_path.clone();
+        let rrd_name = String::from_utf8(request.into_inner().expression)
+            .map_err(|_| Status::invalid_argument("The criteria must be a recording name"))?;
+        let flights = blocking(move || {
+            if !rrd_name.is_empty() {
+                return recording_flights(&folder_path, &rrd_name);
+            }
+
+            let mut flights = Vec::new();
+            for rrd_name in list_recordings(&folder_path)? {
+                match recording_flights(&folder_path, &rrd_name) {
+                    Ok(recording) => flights.extend(recording),
+                    // Other files of the folder may hold no recording to query, or be corrupt
+                    Err(e) if matches!(e.kind(), Error::StoreNotFound(_) | Error::Decode(_)) => {
+                        warn!("Skipping {}: {}", rrd_name, e)
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(flights)
+        })
+        .await?;
+        Ok(Response::new(
+            stream::iter(flights.into_iter().map(Ok)).boxed(),
+        ))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let ticket = descriptor_ticket(&request.into_inner())?;
+        let folder_path = self.folder_path.clone();
+        let flight = blocking(move || {
+            let table = query_ticket(&folder_path, &ticket)?;
+            flight_info(&ticket, &table.schema, table.num_rows())
+        })
+        .await?;
+        Ok(Response::new(flight))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let ticket = descriptor_ticket(&request.into_inner())?;
+        let folder_path = self.folder_path.clone();
+        let table = blocking(move || query_ticket(&folder_path, &ticket)).await?;
+        Ok(Response::new(serialize_schema_to_result(
+            &table.schema,
+            None,
+        )))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let ticket = FlightTicket::decode(&request.into_inner().ticket)?;
+        let folder_path = self.folder_path.clone();
+        let table = blocking(move || query_ticket(&folder_path, &ticket)).await?;
+
+        let ipc_fields = default_ipc_fields(&table.schema.fields);
+        let schema = serialize_schema(&table.schema, Some(&ipc_fields));
+        let options = WriteOptions { compression: None };
+        let batches = table.batches.into_iter().flat_map(move |batch| {
+            match serialize_batch(&batch, &ipc_fields, &options) {
+                Ok((dictionaries, batch)) => dictionaries
+                    .into_iter()
+                    .chain(std::iter::once(batch))
+                    .map(Ok)
+                    .collect::<Vec<_>>(),
+                Err(e) => vec![Err(Status::from(Error::Encode(format!(
+                    "Arrow Flight error: {}",
+                    e
+                ))))],
+            }
+        });
+        Ok(Response::new(
+            stream::iter(std::iter::once(Ok(schema)).chain(batches)).boxed(),
+        ))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("The recordings are read-only"))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("The recordings are read-only"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("The service has no actions"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(stream::empty().boxed()))
+    }
+}
+
+/// The ticket of a command descriptor.
+fn descriptor_ticket(descriptor: &FlightDescriptor) -> Result<FlightTicket> {
+    if descriptor.r#type != DescriptorType::Cmd as i32 {
+        return Err(Error::Invalid(
+            "The descriptor must be a command holding a ticket".to_string(),
+        ));
+    }
+    FlightTicket::decode(&descriptor.cmd)
+}
+
+fn query_ticket(folder_path: &Path, ticket: &FlightTicket) -> Result<EntityTable> {
+    query_entity_table(&folder_rrd_path(folder_path, &ticket.file)?, &ticket.query)
+}
+
+/// The flight of a ticket, returning `num_rows` rows with the given schema.
+fn flight_info(ticket: &FlightTicket, schema: &Schema, num_rows: usize) -> Result<FlightInfo> {
+    let schema = serialize_schema_to_info(schema, None)
+        .map_err(|e| Error::Encode(format!("Arrow Flight error: {}", e)))?;
+    let ticket = ticket.encode();
+    Ok(FlightInfo {
+        schema,
+        flight_descriptor: Some(FlightDescriptor {
+            r#type: DescriptorType::Cmd as i32,
+            cmd: ticket.clone(),
+            path: Vec::new(),
+        }),
+        endpoint: vec![FlightEndpoint {
+            ticket: Some(Ticket { ticket }),
+            location: Vec::new(),
+        }],
+        total_records: num_rows as i64,
+        total_bytes: -1,
+    })
+}
+
+/// The names of the `.rrd` files of the folder, sorted.
+fn list_recordings(folder_path: &Path) -> Result<Vec<String>> {
+    let mut rrd_names = Vec::new();
+    for entry in fs::read_dir(folder_path).map_err(Error::Open)? {
+        let path = entry.map_err(Error::Open)?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension == "rrd") {
+            if let Some(rrd_name) = path.file_name().and_then(|name| name.to_str()) {
+                rrd_names.push(rrd_name.to_string());
+            }
+        }
+    }
+    rrd_names.sort();
+    Ok(rrd_names)
+}
+
+/// A flight per entity of a recording, with all its rows, described without converting them.
+fn recording_flights(folder_path: &Path, rrd_name: &str) -> Result<Vec<FlightInfo>> {
+    let file_path = folder_rrd_path(folder_path, rrd_name)?;
+    let recording = load_recording(&file_path)?;
+    let rrd = recording.entity_db();
+
+    let mut entities: BTreeMap<String, Vec<Chunk>> = BTreeMap::new();
+    for chunk in query_chunks(rrd, |_| true) {
+        entities
+            .entry(chunk.entity_path().to_string())
+            .or_default()
+            .push(chunk);
+    }
+
+    entities
+        .into_iter()
+        .map(|(entity_path, chunks)| {
+            let ticket = FlightTicket {
+                file: rrd_name.to_string(),
+                query: TableQuery {
+                    entity: entity_path,
+                    ..Default::default()
+                },
+            };
+            let num_rows = chunks.iter().map(Chunk::num_rows).sum();
+            flight_info(&ticket, &EntityTable::empty(&chunks, &[])?.schema, num_rows)
+        })
+        .collect()
+}
@@ -0,0 +1,336 @@
+//! A sidecar index of the chunks of an RRD file, so that queries for a few entities or a time
+//! range only decode the messages they need.
+//!
+//! [`build_index`] writes `<file>.idx` next to the recording: the byte range of every message and,
+//! for chunks, their entity path, components, number of rows and time range per timeline. The
+//! queries use the index of a file when it exists, and rebuild it when the file changed since.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use log::{debug, warn};
+use re_chunk::Chunk;
+use re_entity_db::{EntityDb, StoreBundle};
+use re_log_encoding::decoder::{Decoder, VersionPolicy};
+use re_log_types::{LogMsg, StoreId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::cached_bundle, decode_bundle, get_action_entity_db, load_bundle, rewrite::decode_error,
+    Error, Result,
+};
+
+/// The version of the index format, indexes of other versions are rebuilt.
+const INDEX_VERSION: u32 = 1;
+
+/// The messages of an RRD file and where to find them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RrdIndex {
+    /// The version of the index format.
+    pub version: u32,
+    /// The size of the indexed file in bytes.
+    pub file_size: u64,
+    /// The modification time of the indexed file, in nanoseconds since the Unix epoch.
+    pub modified: u64,
+    /// The size of the file header, which precedes the first message.
+    pub header_size: u64,
+    /// Every message of the file, in file order.
+    pub messages: Vec<MessageEntry>,
+}
+
+/// A message of an RRD file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageEntry {
+    /// The offset of the message in the file, message header included.
+    pub offset: u64,
+    /// The size of the message in bytes, message header included.
+    pub len: u64,
+    /// The id of the store of the message.
+    pub store_id: String,
+    /// The content of the message if it is a chunk.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk: Option<ChunkEntry>,
+}
+
+/// The content of a chunk message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    /// The path of the entity of the chunk.
+    pub entity_path: String,
+    /// The full names of the components, e.g. `rerun.components.Scalar`.
+    pub components: Vec<String>,
+    /// The number of rows.
+    pub num_rows: u64,
+    /// The first and last raw times per timeline name, empty for static chunks.
+    pub time_ranges: BTreeMap<String, (i64, i64)>,
+}
+
+impl ChunkEntry {
+    fn new(chunk: &Chunk) -> Self {
+        Self {
+            entity_path: chunk.entity_path().to_string(),
+            components: chunk
+                .component_names()
+                .map(|name| name.to_string())
+                .collect(),
+            num_rows: chunk.num_rows() as u64,
+            time_ranges: chunk
+                .timelines()
+                .iter()
+                .map(|(timeline, time_column)| {
+                    let range = time_column.time_range();
+                    (
+                        timeline.name().to_string(),
+                        (range.min().as_i64(), range.max().as_i64()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether the chunk holds the component named `component`.
+    pub(crate) fn has_component(&self, component: &str) -> bool {
+        self.components.iter().any(|name| name == component)
+    }
+
+    /// Whether the chunk may hold rows kept by `trim_chunk` for this time range: static chunks
+    /// always do, and temporal chunks without `timeline` never do.
+    pub(crate) fn overlaps(&self, timeline: &str, time_range: Option<(i64, i64)>) -> bool {
+        let Some((start, end)) = time_range else {
+            return true;
+        };
+        if self.time_ranges.is_empty() {
+            return true;
+        }
+        self.time_ranges
+            .get(timeline)
+            .is_some_and(|(min, max)| *min <= end && start <= *max)
+    }
+}
+
+impl RrdIndex {
+    /// Index the messages of an RRD file by decoding it entirely.
+    fn build(file_path: &str) -> Result<Self> {
+        let (file_size, modified) = file_stamp(file_path)?;
+        let file = File::open(file_path).map_err(Error::Open)?;
+        let mut decoder =
+            Decoder::new(VersionPolicy::Warn, BufReader::new(file)).map_err(decode_error)?;
+        let header_size = decoder.size_bytes();
+
+        let mut messages = Vec::new();
+        let mut offset = header_size;
+        while let Some(message) = decoder.next() {
+            let message_index = messages.len();
+            let at_message = |e: Error| e.with_message_index(message_index);
+            let message = message.map_err(decode_error).map_err(at_message)?;
+            let chunk = match &message {
+                LogMsg::ArrowMsg(_, arrow_msg) => Some(ChunkEntry::new(
+                    &Chunk::from_arrow_msg(arrow_msg)
+                        .map_err(decode_error)
+                        .map_err(at_message)?,
+                )),
+                _ => None,
+            };
+            messages.push(MessageEntry {
+                offset,
+                len: decoder.size_bytes() - offset,
+                store_id: message.store_id().to_string(),
+                chunk,
+            });
+            offset = decoder.size_bytes();
+        }
+
+        Ok(Self {
+            version: INDEX_VERSION,
+            file_size,
+            modified,
+            header_size,
+            messages,
+        })
+    }
+
+    /// The index of an RRD file, rebuilt if the file changed since it was written, or `None` if
+    /// the file is not indexed.
+    fn load(file_path: &str) -> Result<Option<Self>> {
+        let Ok(encoded) = fs::read(index_path(file_path)) else {
+            return Ok(None);
+        };
+        match serde_json::from_slice::<Self>(&encoded) {
+            Ok(index)
+                if index.version == INDEX_VERSION
+                    && (index.file_size, index.modified) == file_stamp(file_path)? =>
+            {
+                return Ok(Some(index));
+            }
+            Ok(_) => debug!("Rebuilding the outdated index of {}", file_path),
+            Err(e) => warn!("Rebuilding the invalid index of {}: {}", file_path, e),
+        }
+
+        let index = Self::build(file_path)?;
+        if let Err(e) = index.write(file_path) {
+            warn!("Failed to update the index of {}: {}", file_path, e);
+        }
+        Ok(Some(index))
+    }
+
+    fn write(&self, file_path: &str) -> Result<String> {
+        let path = index_path(file_path);
+        let encoded = serde_json::to_vec(self)
+            .map_err(|e| Error::Encode(format!("Index encoding error: {}", e)))?;
+        fs::write(&path, encoded).map_err(Error::Write)?;
+        Ok(path)
+    }
+
+    /// The id of the store holding the `/action` entities, as found by `get_action_entity_db`.
+    fn action_store_id(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .find(|message| {
+                message
+                    .chunk
+                    .as_ref()
+                    .is_some_and(|chunk| chunk.entity_path.contains("/action"))
+            })
+            .map(|message| message.store_id.as_str())
+    }
+
+    /// Read the file header and the messages for which `keep` returns true, as an RRD stream, and
+    /// the indexes of the messages in the file.
+    fn read_messages(
+        &self,
+        file_path: &str,
+        keep: impl Fn(&MessageEntry) -> bool,
+    ) -> Result<(Vec<u8>, Vec<usize>)> {
+        let mut file = File::open(file_path).map_err(Error::Open)?;
+        let mut encoded = vec![0; self.header_size as usize];
+        file.read_exact(&mut encoded).map_err(Error::Open)?;
+
+        let mut message_indexes = Vec::new();
+        for (message_index, message) in self.messages.iter().enumerate() {
+            if !keep(message) {
+                continue;
+            }
+            message_indexes.push(message_index);
+            let start = encoded.len();
+            encoded.resize(start + message.len as usize, 0);
+            file.seek(SeekFrom::Start(message.offset))
+                .and_then(|_| file.read_exact(&mut encoded[start..]))
+                .map_err(Error::Open)?;
+        }
+        Ok((encoded, message_indexes))
+    }
+}
+
+/// Index the chunks of an RRD file, and write the index next to the file as `<file>.idx`.
+///
+/// Once a file is indexed, `query_data_entities`, `query_meta_entities` and `query_entity_table`
+/// only decode the chunks matching their entity, data type and time filters. The index is rebuilt
+/// by the next query when the file is modified, and removing it restores full decoding.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+///
+/// # Returns
+///
+/// * `Result<String>` - The path of the written index.
+pub fn build_index(file_path: &str) -> Result<String> {
+    let index = RrdIndex::build(file_path).map_err(|e| e.with_path(file_path))?;
+    debug!("Indexed {} messages of {}", index.messages.len(), file_path);
+    index.write(file_path)
+}
+
+/// The recording of an RRD file holding the `/action` entities, see `get_action_entity_db`.
+pub(crate) struct ActionRecording {
+    bundle: Arc<StoreBundle>,
+    store_id: StoreId,
+}
+
+impl ActionRecording {
+    fn new(bundle: Arc<StoreBundle>) -> Result<Self> {
+        let store_id = get_action_entity_db(&bundle)?.store_id().clone();
+        Ok(Self { bundle, store_id })
+    }
+
+    pub(crate) fn entity_db(&self) -> &EntityDb {
+        self.bundle
+            .get(&self.store_id)
+            .expect("the store id is found in the bundle")
+    }
+}
+
+/// Decode every chunk of the recording of an RRD file holding the `/action` entities, for the
+/// exports of whole recordings. Errors carry the path of the file.
+#[cfg(any(feature = "export", feature = "flight", feature = "sql"))]
+pub(crate) fn load_recording(file_path: &str) -> Result<ActionRecording> {
+    load_action_recording(file_path, |_| true)
+}
+
+/// Decode the recording of an RRD file holding the `/action` entities.
+///
+/// A cached recording is used as is. Otherwise, if the file is indexed, only the chunks for which
+/// `keep_chunk` returns true are decoded, so `keep_chunk` must keep every chunk the caller queries
+/// from the recording.
+pub(crate) fn load_action_recording(
+    file_path: &str,
+    keep_chunk: impl Fn(&ChunkEntry) -> bool,
+) -> Result<ActionRecording> {
+    load_indexed_recording(file_path, keep_chunk).map_err(|e| e.with_path(file_path))
+}
+
+fn load_indexed_recording(
+    file_path: &str,
+    keep_chunk: impl Fn(&ChunkEntry) -> bool,
+) -> Result<ActionRecording> {
+    if let Some(bundle) = cached_bundle(file_path)? {
+        return ActionRecording::new(bundle);
+    }
+    let Some(index) = RrdIndex::load(file_path)? else {
+        return ActionRecording::new(load_bundle(file_path)?);
+    };
+
+    // Without `/action` entities, the index cannot tell the recording store from the others, so
+    // the whole file is decoded and `get_action_entity_db` falls back to its single recording
+    let Some(store_id) = index.action_store_id() else {
+        return ActionRecording::new(load_bundle(file_path)?);
+    };
+    let not_found = || Error::StoreNotFound("No EntityDb found with action entity".to_string());
+    let (encoded, message_indexes) = index.read_messages(file_path, |message| {
+        message.store_id == store_id && message.chunk.as_ref().is_none_or(&keep_chunk)
+    })?;
+    // The selected chunks may not include `/action` entities, so the store is found by id
+    let bundle = decode_bundle(encoded.as_slice(), |message_index| {
+        message_indexes[message_index]
+    })?;
+    let store_id = bundle
+        .entity_dbs()
+        .map(|rrd| rrd.store_id())
+        .find(|candidate| candidate.id.as_str() == store_id)
+        .ok_or_else(not_found)?
+        .clone();
+    Ok(ActionRecording {
+        bundle: Arc::new(bundle),
+        store_id,
+    })
+}
+
+fn index_path(file_path: &str) -> String {
+    format!("{}.idx", file_path)
+}
+
+/// The size and modification time of a file, which change when it is rewritten.
+pub(crate) fn file_stamp(file_path: impl AsRef<Path>) -> Result<(u64, u64)> {
+    let metadata = fs::metadata(file_path).map_err(Error::Open)?;
+    let modified = metadata
+        .modified()
+        .map_err(Error::Open)?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64);
+    Ok((metadata.len(), modified))
+}
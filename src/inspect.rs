@@ -0,0 +1,184 @@
+//! Summaries of the stores and entities of a recording, for listing and triage.
+
+use std::collections::BTreeMap;
+
+use re_arrow2::{
+    array::{Array, ListArray},
+    datatypes::{DataType, Field},
+};
+use re_chunk::Chunk;
+use re_entity_db::EntityDb;
+use re_log_types::{StoreKind, TimeType};
+
+use crate::{columns, load_bundle, query_chunks, Error, Result};
+
+/// A store of an RRD file: a recording or a blueprint.
+#[derive(Debug, Clone)]
+pub struct StoreSummary {
+    /// The id of the store.
+    pub store_id: String,
+    /// Whether the store is a recording or a blueprint.
+    pub kind: StoreKind,
+    /// The application id from the store info, if any.
+    pub application_id: Option<String>,
+    /// The number of rows of the store.
+    pub num_rows: u64,
+    /// The entities of the store, sorted by path.
+    pub entities: Vec<EntitySummary>,
+}
+
+/// The chunks logged at one entity path.
+#[derive(Debug, Clone, Default)]
+pub struct EntitySummary {
+    /// The path of the entity.
+    pub entity_path: String,
+    /// The number of chunks.
+    pub num_chunks: usize,
+    /// The number of rows, static rows included.
+    pub num_rows: usize,
+    /// The number of static rows.
+    pub num_static_rows: usize,
+    /// The Arrow type of the items of every component, by component name.
+    pub components: BTreeMap<String, DataType>,
+    /// The times covered on every timeline, by timeline name.
+    pub timelines: BTreeMap<String, TimelineSummary>,
+}
+
+/// The times an entity covers on a timeline.
+#[derive(Debug, Clone)]
+pub struct TimelineSummary {
+    /// Whether the timeline holds nanoseconds or sequence numbers.
+    pub typ: TimeType,
+    /// The number of rows on the timeline.
+    pub num_rows: usize,
+    /// The first time, in nanoseconds for temporal timelines.
+    pub min: i64,
+    /// The last time, in nanoseconds for temporal timelines.
+    pub max: i64,
+}
+
+/// Summarize every store of an RRD file, recordings first.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+///
+/// # Returns
+///
+/// * `Result<Vec<StoreSummary>>` - The stores, with the summary of every entity.
+pub fn summarize_stores(file_path: &str) -> Result<Vec<StoreSummary>> {
+    let bundle = load_bundle(file_path)?;
+
+    let mut stores: Vec<StoreSummary> = bundle.entity_dbs().map(summarize_store).collect();
+    stores.sort_by_key(|store| store.kind != StoreKind::Recording);
+    Ok(stores)
+}
+
+/// Summarize the entities of one store.
+pub fn summarize_store(rrd: &EntityDb) -> StoreSummary {
+    let mut entities: BTreeMap<String, EntitySummary> = BTreeMap::new();
+    for chunk in query_chunks(rrd, |_| true) {
+        let entity_path = chunk.entity_path().to_string();
+        let entity = entities
+            .entry(entity_path.clone())
+            .or_insert_with(|| EntitySummary {
+                entity_path,
+                ..Default::default()
+            });
+        add_chunk(entity, &chunk);
+    }
+
+    StoreSummary {
+        store_id: rrd.store_id().id.to_string(),
+        kind: rrd.store_kind(),
+        application_id: rrd.app_id().map(|id| id.to_string()),
+        num_rows: rrd.num_rows(),
+        entities: entities.into_values().collect(),
+    }
+}
+
+fn add_chunk(entity: &mut EntitySummary, chunk: &Chunk) {
+    entity.num_chunks += 1;
+    entity.num_rows += chunk.num_rows();
+    if chunk.is_static() {
+        entity.num_static_rows += chunk.num_rows();
+    }
+
+    for (component_name, list_array) in chunk.components() {
+        entity
+            .components
+            .entry(component_name.to_string())
+            .or_insert_with(|| ListArray::<i32>::get_child_type(list_array.data_type()).clone());
+    }
+
+    for (timeline, time_column) in chunk.timelines() {
+        let range = time_column.time_range();
+        let (min, max) = (range.min().as_i64(), range.max().as_i64());
+        entity
+            .timelines
+            .entry(timeline.name().to_string())
+            .and_modify(|summary| {
+                summary.num_rows += time_column.num_rows();
+                summary.min = summary.min.min(min);
+                summary.max = summary.max.max(max);
+            })
+            .or_insert(TimelineSummary {
+                typ: timeline.typ(),
+                num_rows: time_column.num_rows(),
+                min,
+                max,
+            });
+    }
+}
+
+/// Convert a chunk into flat named columns: its timelines, then its exportable components.
+///
+/// The columns are the ones written by the exporters, see `export_parquet`.
+pub fn chunk_columns(chunk: &Chunk) -> Result<Vec<(Field, Box<dyn Array>)>> {
+    let mut columns = columns::timeline_columns(chunk);
+    columns.extend(
+        columns::component_columns(chunk)
+            .map_err(|e| Error::Invalid(format!("Data conversion error: {}", e)))?,
+    );
+    Ok(columns)
+}
+
+/// A compact name of an Arrow type, e.g. `List<Float64>` or `Struct<size: UInt64, name: Utf8>`.
+pub fn type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::List(field) | DataType::LargeList(field) => {
+            format!("List<{}>", type_name(field.data_type()))
+        }
+        DataType::FixedSizeList(field, size) => {
+            format!("FixedSizeList<{}; {}>", type_name(field.data_type()), size)
+        }
+        DataType::Struct(fields) => format!("Struct<{}>", field_names(fields)),
+        DataType::Union(fields, _, _) => format!("Union<{}>", field_names(fields)),
+        DataType::Extension(_, inner, _) => type_name(inner),
+        data_type => format!("{:?}", data_type),
+    }
+}
+
+/// The name of a store kind: `recording` or `blueprint`.
+pub fn store_kind_name(kind: StoreKind) -> &'static str {
+    match kind {
+        StoreKind::Recording => "recording",
+        StoreKind::Blueprint => "blueprint",
+    }
+}
+
+/// The name of a timeline type: `time` or `sequence`.
+pub fn time_type_name(typ: TimeType) -> &'static str {
+    match typ {
+        TimeType::Time => "time",
+        TimeType::Sequence => "sequence",
+    }
+}
+
+fn field_names(fields: &[Field]) -> String {
+    fields
+        .iter()
+        .map(|field| format!("{}: {}", field.name, type_name(field.data_type())))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,603 @@
+//! Export of RRD recordings to LeRobot datasets.
+//!
+//! Every RRD file becomes one episode. The configured entities are resampled to a fixed frame
+//! rate and written with LeRobot's parquet layout and metadata files:
+//!
+//! ```text
+//! <out_dir>/data/chunk-000/episode_000000.parquet
+//! <out_dir>/meta/info.json
+//! <out_dir>/meta/episodes.jsonl
+//! <out_dir>/meta/episodes_stats.jsonl
+//! <out_dir>/meta/tasks.jsonl
+//! ```
+
+use std::{collections::BTreeMap, fs, io::Write, path::Path, sync::Arc};
+
+use log::debug;
+use re_arrow2::{
+    array::{Array, BinaryArray, Float32Array, Int64Array, ListArray, StructArray, Utf8Array},
+    chunk::Chunk as ArrowChunk,
+    datatypes::{DataType, Field, Schema},
+    error::Error as ArrowError,
+    io::parquet::write::CompressionOptions,
+    offset::OffsetsBuffer,
+};
+use re_entity_db::EntityDb;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    index::load_recording,
+    meta_text, parquet, query_meta_chunks,
+    resample::{frame_times, latest_at, load_feature, FeatureSamples},
+    Error, Result,
+};
+
+const CODEBASE_VERSION: &str = "v2.1";
+const DATA_PATH: &str = "data/chunk-{episode_chunk:03d}/episode_{episode_index:06d}.parquet";
+
+/// The mapping from RRD entities to a LeRobot dataset.
+#[derive(Deserialize)]
+struct LeRobotConfig {
+    /// The frame rate of the dataset.
+    fps: u32,
+
+    /// The robot type recorded in `info.json`.
+    #[serde(default)]
+    robot_type: Option<String>,
+
+    /// The temporal timeline used to align the entities.
+    #[serde(default = "default_timeline")]
+    timeline: String,
+
+    /// The task of every episode, unless `task_entity` is set and found.
+    #[serde(default)]
+    task: Option<String>,
+
+    /// A meta entity whose text holds the task of the episode.
+    #[serde(default)]
+    task_entity: Option<String>,
+
+    /// LeRobot feature keys (`action`, `observation.state`, `observation.images.<camera>`, ...)
+    /// mapped to the entity paths holding them.
+    features: BTreeMap<String, String>,
+
+    /// The number of episodes per data chunk directory.
+    #[serde(default = "default_chunks_size")]
+    chunks_size: usize,
+}
+
+fn default_timeline() -> String {
+    "log_time".to_string()
+}
+
+fn default_chunks_size() -> usize {
+    1000
+}
+
+/// The description of a feature in `info.json`.
+struct FeatureInfo {
+    dtype: &'static str,
+    shape: Vec<u64>,
+    names: Value,
+}
+
+/// Export RRD recordings to a LeRobot dataset, one episode per file.
+///
+/// The `config` is a JSON object describing the mapping:
+///
+/// ```json
+/// {
+///     "fps": 30,
+///     "robot_type": "r1",
+///     "timeline": "log_time",
+///     "task": "Pick up the cube",
+///     "task_entity": "/meta/task",
+///     "features": {
+///         "action": "/action/joints/position",
+///         "observation.state": "/observation/joints/position",
+///         "observation.images.cam_high": "/observation/images/cam_high"
+///     }
+/// }
+/// ```
+///
+/// Scalar and tensor entities become `float32` features, `EncodedImage` entities (JPEG or PNG)
+/// become `image` features stored inline in the parquet files, with the channel count read from
+/// the image header. The stats of image features are computed per channel on a sample of the
+/// decoded frames, as LeRobot does (see [`image_stats`]). All features are resampled to
+/// `fps` on the given temporal timeline, using the latest sample at or before each frame, over the
+/// time range covered by every feature. The task is read from the text of `task_entity` if it is
+/// present in the recording, and falls back to `task` otherwise.
+///
+/// # Arguments
+///
+/// * `file_paths` - The RRD files to export, in episode order.
+/// * `out_dir` - The root directory of the dataset. Created if missing.
+/// * `config` - The JSON mapping config.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The paths of the written episode parquet files.
+pub fn export_lerobot(file_paths: Vec<String>, out_dir: &str, config: &str) -> Result<Vec<String>> {
+    let config: LeRobotConfig = serde_json::from_str(config)
+        .map_err(|e| Error::Invalid(format!("Invalid LeRobot config: {}", e)))?;
+    if config.fps == 0 || config.chunks_size == 0 {
+        return Err(Error::Invalid(
+            "Invalid LeRobot config: fps and chunks_size must be positive".to_string(),
+        ));
+    }
+    if config.features.is_empty() {
+        return Err(Error::Invalid(
+            "Invalid LeRobot config: no features".to_string(),
+        ));
+    }
+
+    let out_dir = Path::new(out_dir);
+    let mut tasks: Vec<String> = Vec::new();
+    let mut features_info: Option<BTreeMap<String, FeatureInfo>> = None;
+    let mut episodes = Vec::new();
+    let mut episodes_stats = Vec::new();
+    let mut written = Vec::new();
+    let mut total_frames = 0;
+
+    for (episode_index, file_path) in file_paths.iter().enumerate() {
+        let recording = load_recording(file_path)?;
+        let rrd = recording.entity_db();
+
+        let task = episode_task(rrd, &config);
+        let task_index = match tasks.iter().position(|existing| *existing == task) {
+            Some(index) => index,
+            None => {
+                tasks.push(task.clone());
+                tasks.len() - 1
+            }
+        };
+
+        let mut samples = BTreeMap::new();
+        for (key, entity_path) in &config.features {
+            samples.insert(
+                key.clone(),
+                load_feature(rrd, entity_path, &config.timeline)
+                    .map_err(|e| e.with_path(file_path).with_entity(entity_path))?,
+            );
+        }
+
+        let frame_times = frame_times(&samples, config.fps).ok_or_else(|| {
+            Error::NoData("No overlapping data for the configured features".to_string())
+                .with_path(file_path)
+        })?;
+        let num_frames = frame_times.len();
+        debug!("Episode {} has {} frames", episode_index, num_frames);
+
+        let info = describe_features(&samples)?;
+        match &features_info {
+            Some(existing) => check_features(existing, &info, file_path)?,
+            None => features_info = Some(info),
+        }
+
+        let path = out_dir.join(format!(
+            "data/chunk-{:03}/episode_{:06}.parquet",
+            episode_index / config.chunks_size,
+            episode_index
+        ));
+        let mut stats = write_episode(
+            &path,
+            &samples,
+            &frame_times,
+            episode_index,
+            total_frames,
+            task_index,
+        )
+        .map_err(export_error)?;
+        for (key, samples) in &samples {
+            if let FeatureSamples::Image { frames, .. } = samples {
+                let rows = latest_at(samples.times(), &frame_times);
+                let frames: Vec<&[u8]> = rows.iter().map(|&row| frames[row].as_slice()).collect();
+                let image_stats = image_stats(&frames).map_err(|e| e.with_path(file_path))?;
+                stats.insert(key.clone(), image_stats);
+            }
+        }
+
+        episodes.push(json!({
+            "episode_index": episode_index,
+            "tasks": [task],
+            "length": num_frames,
+        }));
+        episodes_stats.push(json!({
+            "episode_index": episode_index,
+            "stats": stats,
+        }));
+        total_frames += num_frames;
+        written.push(path.to_string_lossy().into_owned());
+    }
+
+    let Some(features_info) = features_info else {
+        return Err(Error::Invalid("No RRD files to export".to_string()));
+    };
+
+    let total_episodes = file_paths.len();
+    let mut features = serde_json::Map::new();
+    for (key, info) in &features_info {
+        features.insert(
+            key.clone(),
+            json!({ "dtype": info.dtype, "shape": info.shape, "names": info.names }),
+        );
+    }
+    features.insert(
+        "timestamp".to_string(),
+        json!({ "dtype": "float32", "shape": [1], "names": null }),
+    );
+    for index_column in ["frame_index", "episode_index", "index", "task_index"] {
+        features.insert(
+            index_column.to_string(),
+            json!({ "dtype": "int64", "shape": [1], "names": null }),
+        );
+    }
+
+    let info = json!({
+        "codebase_version": CODEBASE_VERSION,
+        "robot_type": config.robot_type,
+        "total_episodes": total_episodes,
+        "total_frames": total_frames,
+        "total_tasks": tasks.len(),
+        "total_videos": 0,
+        "total_chunks": total_episodes.div_ceil(config.chunks_size),
+        "chunks_size": config.chunks_size,
+        "fps": config.fps,
+        "splits": { "train": format!("0:{}", total_episodes) },
+        "data_path": DATA_PATH,
+        "video_path": null,
+        "features": features,
+    });
+
+    let meta_dir = out_dir.join("meta");
+    fs::create_dir_all(&meta_dir).map_err(Error::Write)?;
+    let info = serde_json::to_string_pretty(&info)
+        .map_err(|e| Error::Encode(format!("LeRobot export error: {}", e)))?;
+    fs::write(meta_dir.join("info.json"), info).map_err(Error::Write)?;
+    write_jsonl(&meta_dir.join("episodes.jsonl"), &episodes)?;
+    write_jsonl(&meta_dir.join("episodes_stats.jsonl"), &episodes_stats)?;
+    let tasks: Vec<Value> = tasks
+        .iter()
+        .enumerate()
+        .map(|(task_index, task)| json!({ "task_index": task_index, "task": task }))
+        .collect();
+    write_jsonl(&meta_dir.join("tasks.jsonl"), &tasks)?;
+
+    Ok(written)
+}
+
+/// Read the task of an episode from its task entity, falling back to the configured task.
+fn episode_task(rrd: &EntityDb, config: &LeRobotConfig) -> String {
+    config
+        .task_entity
+        .as_deref()
+        .filter(|entity_path| !entity_path.is_empty())
+        .and_then(|entity_path| query_meta_chunks(rrd, entity_path).first().map(meta_text))
+        .map(|(_media_type, text)| text)
+        .or_else(|| config.task.clone())
+        .unwrap_or_default()
+}
+
+fn describe_features(
+    samples: &BTreeMap<String, FeatureSamples>,
+) -> Result<BTreeMap<String, FeatureInfo>> {
+    let mut features = BTreeMap::new();
+    for (key, samples) in samples {
+        let info = match samples {
+            FeatureSamples::Numeric { values, .. } => {
+                let len = values[0].len();
+                if values.iter().any(|value| value.len() != len) {
+                    return Err(Error::Invalid(format!(
+                        "Feature {} changes shape between samples",
+                        key
+                    )));
+                }
+                FeatureInfo {
+                    dtype: "float32",
+                    shape: vec![len as u64],
+                    names: Value::Null,
+                }
+            }
+            FeatureSamples::Image { frames, .. } => {
+                let (width, height, channels) = image_header(&frames[0]).ok_or_else(|| {
+                    Error::Invalid(format!("Feature {} is not a JPEG or PNG image", key))
+                })?;
+                FeatureInfo {
+                    dtype: "image",
+                    shape: vec![height as u64, width as u64, channels as u64],
+                    names: json!(["height", "width", "channel"]),
+                }
+            }
+        };
+        features.insert(key.clone(), info);
+    }
+    Ok(features)
+}
+
+/// Make sure every episode has the same features as the first one.
+fn check_features(
+    expected: &BTreeMap<String, FeatureInfo>,
+    actual: &BTreeMap<String, FeatureInfo>,
+    file_path: &str,
+) -> Result<()> {
+    for (key, info) in actual {
+        let expected = &expected[key];
+        if expected.dtype != info.dtype || expected.shape != info.shape {
+            return Err(Error::Invalid(format!(
+                "Feature {} of {} has dtype {} and shape {:?}, expected {} and {:?}",
+                key, file_path, info.dtype, info.shape, expected.dtype, expected.shape
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Read the width, height and channel count of a PNG or JPEG image from its header.
+///
+/// The channel count is the one of the decoded pixels: palette PNGs decode to RGB, and JPEGs to
+/// grayscale if they have a single component and to RGB otherwise.
+fn image_header(bytes: &[u8]) -> Option<(u32, u32, usize)> {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    if bytes.starts_with(PNG_SIGNATURE) {
+        // The IHDR chunk always comes first
+        let width = u32::from_be_bytes(bytes.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(bytes.get(20..24)?.try_into().ok()?);
+        let channels = match bytes.get(25)? {
+            0 => 1,
+            2 | 3 => 3,
+            4 => 2,
+            6 => 4,
+            _ => return None,
+        };
+        return Some((width, height, channels));
+    }
+
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    // Walk the JPEG segments up to the first start-of-frame marker
+    let mut offset = 2;
+    while offset + 4 <= bytes.len() {
+        if bytes[offset] != 0xFF {
+            return None;
+        }
+        let marker = bytes[offset + 1];
+        let length = u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+        let is_start_of_frame =
+            matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_start_of_frame {
+            let height = u16::from_be_bytes(bytes.get(offset + 5..offset + 7)?.try_into().ok()?);
+            let width = u16::from_be_bytes(bytes.get(offset + 7..offset + 9)?.try_into().ok()?);
+            let channels = if *bytes.get(offset + 9)? == 1 { 1 } else { 3 };
+            return Some((width as u32, height as u32, channels));
+        }
+        offset += 2 + length;
+    }
+    None
+}
+
+/// Write the parquet file of one episode and return the stats of its numeric and index columns.
+fn write_episode(
+    path: &Path,
+    samples: &BTreeMap<String, FeatureSamples>,
+    frame_times: &[i64],
+    episode_index: usize,
+    first_index: usize,
+    task_index: usize,
+) -> Result<serde_json::Map<String, Value>, ArrowError> {
+    let num_frames = frame_times.len();
+    let mut fields = Vec::new();
+    let mut arrays: Vec<Box<dyn Array>> = Vec::new();
+    let mut stats = serde_json::Map::new();
+
+    for (key, samples) in samples {
+        let rows = latest_at(samples.times(), frame_times);
+        let array: Box<dyn Array> = match samples {
+            FeatureSamples::Numeric { values, .. } => {
+                let frames: Vec<&[f32]> = rows.iter().map(|&row| values[row].as_slice()).collect();
+                stats.insert(key.clone(), vector_stats(&frames));
+
+                let len = frames.first().map_or(0, |frame| frame.len());
+                let flat: Vec<f32> = frames.concat();
+                let offsets: Vec<i32> = (0..=num_frames).map(|i| (i * len) as i32).collect();
+                ListArray::<i32>::try_new(
+                    ListArray::<i32>::default_datatype(DataType::Float32),
+                    OffsetsBuffer::try_from(offsets)?,
+                    Float32Array::from_vec(flat).boxed(),
+                    None,
+                )?
+                .boxed()
+            }
+            FeatureSamples::Image { frames, .. } => {
+                let bytes =
+                    BinaryArray::<i32>::from_iter_values(rows.iter().map(|&row| &frames[row]));
+                let paths = Utf8Array::<i32>::new_null(DataType::Utf8, num_frames);
+                StructArray::try_new(
+                    DataType::Struct(Arc::new(vec![
+                        Field::new("bytes", DataType::Binary, true),
+                        Field::new("path", DataType::Utf8, true),
+                    ])),
+                    vec![bytes.boxed(), paths.boxed()],
+                    None,
+                )?
+                .boxed()
+            }
+        };
+        fields.push(Field::new(key.as_str(), array.data_type().clone(), false));
+        arrays.push(array);
+    }
+
+    let start = frame_times[0];
+    let timestamps: Vec<f32> = frame_times
+        .iter()
+        .map(|time| ((time - start) as f64 * 1e-9) as f32)
+        .collect();
+    let frame_index: Vec<i64> = (0..num_frames as i64).collect();
+    let episode_index = vec![episode_index as i64; num_frames];
+    let index: Vec<i64> = (0..num_frames as i64)
+        .map(|frame| first_index as i64 + frame)
+        .collect();
+    let task_index = vec![task_index as i64; num_frames];
+
+    stats.insert("timestamp".to_string(), scalar_stats(&timestamps));
+    fields.push(Field::new("timestamp", DataType::Float32, false));
+    arrays.push(Float32Array::from_vec(timestamps).boxed());
+
+    for (name, values) in [
+        ("frame_index", frame_index),
+        ("episode_index", episode_index),
+        ("index", index),
+        ("task_index", task_index),
+    ] {
+        let as_f32: Vec<f32> = values.iter().map(|value| *value as f32).collect();
+        stats.insert(name.to_string(), scalar_stats(&as_f32));
+        fields.push(Field::new(name, DataType::Int64, false));
+        arrays.push(Int64Array::from_vec(values).boxed());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    parquet::write_row_groups(
+        path,
+        Schema::from(fields),
+        std::iter::once(ArrowChunk::try_new(arrays)),
+        CompressionOptions::Snappy,
+    )?;
+
+    Ok(stats)
+}
+
+/// Per-element min, max, mean, standard deviation and count of vector frames.
+fn vector_stats(frames: &[&[f32]]) -> Value {
+    let len = frames.first().map_or(0, |frame| frame.len());
+    let count = frames.len() as f64;
+
+    let mut min = vec![f64::INFINITY; len];
+    let mut max = vec![f64::NEG_INFINITY; len];
+    let mut sum = vec![0.0; len];
+    let mut sum_squares = vec![0.0; len];
+    for frame in frames {
+        for (i, value) in frame.iter().enumerate() {
+            let value = *value as f64;
+            min[i] = min[i].min(value);
+            max[i] = max[i].max(value);
+            sum[i] += value;
+            sum_squares[i] += value * value;
+        }
+    }
+
+    let mean: Vec<f64> = sum.iter().map(|sum| sum / count).collect();
+    let std: Vec<f64> = sum_squares
+        .iter()
+        .zip(&mean)
+        .map(|(sum_squares, mean)| (sum_squares / count - mean * mean).max(0.0).sqrt())
+        .collect();
+
+    json!({
+        "min": min,
+        "max": max,
+        "mean": mean,
+        "std": std,
+        "count": [frames.len()],
+    })
+}
+
+/// Per-channel min, max, mean, standard deviation and count of image frames, with pixel values
+/// scaled to [0, 1] and LeRobot's `(channel, 1, 1)` shape.
+///
+/// Like LeRobot's `compute_episode_stats`, the stats are computed on a sample of evenly spaced
+/// frames (all of them below 100 frames, `num_frames^0.75` clamped to [100, 10000] otherwise),
+/// and images of 300 pixels or more are subsampled to about 150 pixels on their longest side.
+fn image_stats(frames: &[&[u8]]) -> Result<Value> {
+    let num_frames = frames.len();
+    let num_samples = if num_frames < 100 {
+        num_frames
+    } else {
+        ((num_frames as f64).powf(0.75) as usize).clamp(100, 10_000)
+    };
+    let channels = frames
+        .first()
+        .and_then(|frame| image_header(frame))
+        .map_or(3, |(_width, _height, channels)| channels);
+
+    let mut min = vec![f64::INFINITY; channels];
+    let mut max = vec![f64::NEG_INFINITY; channels];
+    let mut sum = vec![0.0; channels];
+    let mut sum_squares = vec![0.0; channels];
+    let mut count = 0.0;
+    for sample in 0..num_samples {
+        let index = if num_samples > 1 {
+            (sample as f64 * (num_frames - 1) as f64 / (num_samples - 1) as f64).round() as usize
+        } else {
+            0
+        };
+        let image = image::load_from_memory(frames[index])
+            .map_err(|e| Error::Decode(format!("Failed to decode image frame {}: {}", index, e)))?;
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels = match channels {
+            1 => image.into_luma8().into_raw(),
+            2 => image.into_luma_alpha8().into_raw(),
+            4 => image.into_rgba8().into_raw(),
+            _ => image.into_rgb8().into_raw(),
+        };
+
+        let step = if width.max(height) < 300 {
+            1
+        } else {
+            width.max(height) / 150
+        };
+        for y in (0..height).step_by(step) {
+            for x in (0..width).step_by(step) {
+                let pixel = &pixels[(y * width + x) * channels..][..channels];
+                for (c, value) in pixel.iter().enumerate() {
+                    let value = *value as f64 / 255.0;
+                    min[c] = min[c].min(value);
+                    max[c] = max[c].max(value);
+                    sum[c] += value;
+                    sum_squares[c] += value * value;
+                }
+                count += 1.0;
+            }
+        }
+    }
+
+    let mean: Vec<f64> = sum.iter().map(|sum| sum / count).collect();
+    let std: Vec<f64> = sum_squares
+        .iter()
+        .zip(&mean)
+        .map(|(sum_squares, mean)| (sum_squares / count - mean * mean).max(0.0).sqrt())
+        .collect();
+    let per_channel =
+        |values: &[f64]| -> Value { values.iter().map(|value| json!([[value]])).collect() };
+
+    Ok(json!({
+        "min": per_channel(&min),
+        "max": per_channel(&max),
+        "mean": per_channel(&mean),
+        "std": per_channel(&std),
+        "count": [num_samples],
+    }))
+}
+
+fn scalar_stats(values: &[f32]) -> Value {
+    let frames: Vec<&[f32]> = values.iter().map(std::slice::from_ref).collect();
+    vector_stats(&frames)
+}
+
+fn write_jsonl(path: &Path, lines: &[Value]) -> Result<()> {
+    let mut file = fs::File::create(path).map_err(Error::Write)?;
+    for line in lines {
+        writeln!(file, "{}", line).map_err(Error::Write)?;
+    }
+    Ok(())
+}
+
+fn export_error(e: ArrowError) -> Error {
+    match e {
+        ArrowError::Io(e) => Error::Write(e),
+        e => Error::Encode(format!("LeRobot export error: {}", e)),
+    }
+}
@@ -1,36 +1,93 @@
-use env_logger;
-use log::{debug, error};
-use numpy::{PyArray1, PyArray2};
-use pyo3::{
-    exceptions::{PyIOError, PyValueError},
-    prelude::*,
-    types::{IntoPyDict, PyDict, PyList},
+//! Query and export the data of Rerun `.rrd` recordings.
+//!
+//! The crate is usable both as a Rust library and, with the default `python` feature, as the
+//! native module of the `requery` Python package. The Rust API returns plain Rust and Arrow types:
+//!
+//! ```no_run
+//! let chunks = rerun_query::query_data_entities("/path/to/file.rrd", "scalar", "/action")?;
+//! for chunk in chunks {
+//!     println!("{}: {} rows", chunk.entity_path, chunk.data.len());
+//! }
+//! # Ok::<(), rerun_query::Error>(())
+//! ```
+
+use log::debug;
+use re_arrow2::array::{self, Array, ListArray, PrimitiveArray};
+use re_chunk::{Chunk, ChunkId, ComponentName, LatestAtQuery, RangeQuery};
+use re_entity_db::{EntityDb, StoreBundle};
+use re_log_encoding::decoder::{Decoder, VersionPolicy};
+use re_log_types::{EntityPath, StoreKind, Timeline};
+#[cfg(any(feature = "export", feature = "server", feature = "flight"))]
+use std::path::Path;
+#[cfg(feature = "export")]
+use std::path::PathBuf;
+use std::{collections::BTreeMap, io::Read, sync::Arc};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod batch;
+mod cache;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "export")]
+mod cdr;
+mod columns;
+#[cfg(feature = "export")]
+mod csv;
+mod error;
+#[cfg(feature = "flight")]
+pub mod flight;
+mod index;
+mod inspect;
+#[cfg(feature = "export")]
+mod lerobot;
+#[cfg(feature = "export")]
+mod mcap;
+#[cfg(feature = "export")]
+mod mcap_import;
+#[cfg(feature = "export")]
+mod npz;
+#[cfg(feature = "export")]
+mod parquet;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "python")]
+mod python_async;
+#[cfg(feature = "export")]
+mod resample;
+mod rewrite;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "sql")]
+pub mod sql;
+mod table;
+#[cfg(feature = "export")]
+mod tfrecord;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "export")]
+mod webdataset;
+#[cfg(feature = "export")]
+mod zarr;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::batch::{query_data_entities_batch, scan_files, DataQuery};
+pub use crate::{
+    cache::{clear_cache, set_cache_budget},
+    error::{Error, ErrorContext, Result},
+    index::{build_index, ChunkEntry, MessageEntry, RrdIndex},
+    inspect::{
+        chunk_columns, store_kind_name, summarize_store, summarize_stores, time_type_name,
+        type_name, EntitySummary, StoreSummary, TimelineSummary,
+    },
+    rewrite::{merge_rrds, split_rrd, write_filtered_rrd},
+    table::{query_entity_table, EntityTable, TableQuery},
 };
-use re_arrow2::{
-    array::{self, Array},
-    datatypes::DataType,
+#[cfg(feature = "export")]
+pub use crate::{
+    csv::export_csv, lerobot::export_lerobot, mcap::export_mcap, mcap_import::import_mcap,
+    npz::export_npz, parquet::export_parquet, tfrecord::export_tfrecord,
+    webdataset::export_webdataset, zarr::export_zarr,
 };
-use re_chunk::{Chunk, ComponentName};
-use re_entity_db::{EntityDb, StoreBundle};
-use re_log_encoding::decoder::VersionPolicy;
-use re_log_types::LogMsg;
-use std::{collections::HashMap, fs::File};
-// pub async fn get_entity_dbs(
-//     data: &web::Data<AppState>,
-//     rrd_name: &str,
-// ) -> Result<Vec<String>, String> {
-//     let file_path = data.folder_path.join(rrd_name);
-//     let encoded = File::open(&file_path).map_err(|e| e.to_string())?;
-//     let bundle = StoreBundle::from_rrd(re_log_encoding::decoder::VersionPolicy::Warn, encoded)
-//         .map_err(|e| e.to_string())?;
-
-//     let mut entity_dbs = Vec::new();
-//     for rrd in bundle.entity_dbs() {
-//         entity_dbs.push(rrd.to_string());
-//     }
-
-//     Ok(entity_dbs)
-// }
 
 /// Retrieve the list of all entity paths from a specific RRD file.
 ///
@@ -40,20 +97,20 @@ use std::{collections::HashMap, fs::File};
 ///
 /// # Returns
 ///
-/// * `PyResult<Vec<String>>` - A list of entity paths if successful, otherwise raises an IOError or ValueError.
+/// * `Result<Vec<String>>` - A list of entity paths if successful, otherwise an open or decoding error.
 ///
 /// # Example
 ///
 /// ```python
 /// entities = requery.list_entity_paths("/path/to/file.rrd")
 /// ```
-#[pyfunction]
-pub fn list_entity_paths(file_path: &str) -> PyResult<Vec<String>> {
-    let encoded = File::open(&file_path)
-        .map_err(|e| PyErr::new::<PyIOError, _>(format!("File open error: {}", e)))?;
-    let bundle: StoreBundle = StoreBundle::from_rrd(VersionPolicy::Warn, encoded)
-        .map_err(|e| PyErr::new::<PyValueError, _>(format!("Decoding error: {}", e)))?;
+pub fn list_entity_paths(file_path: &str) -> Result<Vec<String>> {
+    let bundle = load_bundle(file_path)?;
+    Ok(bundle_entity_paths(&bundle))
+}
 
+/// The entity paths of every recording of a `StoreBundle`.
+pub(crate) fn bundle_entity_paths(bundle: &StoreBundle) -> Vec<String> {
     let mut entities = Vec::new();
     for rrd in bundle.entity_dbs() {
         for entity in rrd.entity_paths() {
@@ -61,325 +118,260 @@ pub fn list_entity_paths(file_path: &str) -> PyResult<Vec<String>> {
         }
     }
 
-    Ok(entities)
+    entities
 }
 
-// fn is_blueprint_entity_db(entity_db: &EntityDb) -> bool {
-//     // Use the iterator provided by entity_paths() and check if any path contains "/viewport" or "/space_view"
-//     entity_db.entity_paths().iter().any(|path| {
-//         path.to_string().contains("/viewport") || path.to_string().contains("/space_view")
-//     })
-// }
-
 fn is_action_entity_db(entity_db: &EntityDb) -> bool {
-    // Use the iterator provided by entity_paths() and check if any path contains "/viewport" or "/space_view"
+    // Check if any entity path contains "/action"
     entity_db
         .entity_paths()
         .iter()
         .any(|path| path.to_string().contains("/action"))
 }
 
-fn get_action_entity_db(bundle: &StoreBundle) -> &EntityDb {
-    bundle
+/// Find the recording of a `StoreBundle` that holds the `/action` entities.
+///
+/// Bundles without `/action` entities, such as the RRD files written by `import_mcap`, fall back
+/// to their recording if they hold a single one.
+pub fn get_action_entity_db(bundle: &StoreBundle) -> Result<&EntityDb> {
+    if let Some(entity_db) = bundle
         .entity_dbs()
         .find(|entity_db| is_action_entity_db(entity_db))
-        .expect("No EntityDb found with action entity")
+    {
+        return Ok(entity_db);
+    }
+    let mut recordings = bundle
+        .entity_dbs()
+        .filter(|entity_db| entity_db.store_kind() == StoreKind::Recording);
+    match (recordings.next(), recordings.next()) {
+        (Some(entity_db), None) => Ok(entity_db),
+        _ => Err(Error::StoreNotFound(
+            "No EntityDb found with action entity".to_string(),
+        )),
+    }
 }
-#[pyclass]
-pub struct DataChunk {
-    entity_path: String,
-    timelines: HashMap<String, Py<PyArray1<i64>>>,
-    data: Data,
-}
-
-#[pyclass]
-pub enum Data {
-    Tensor { data: Py<PyArray2<Py<PyAny>>> },
-    Scalar { data: Py<PyArray1<Py<PyAny>>> },
-}
-
-#[pymethods]
-impl DataChunk {
-    #[new]
-    pub fn new(
-        py: Python,
-        entity_path: String,
-        timelines: HashMap<String, Py<PyArray1<i64>>>,
-        data: PyObject, // Accept PyObject and determine if it's tensor or scalar data
-    ) -> PyResult<Self> {
-        // Determine if the provided data is a Tensor or Scalar
-        let data_enum = if let Ok(tensor_data) = data.extract::<Py<PyArray2<Py<PyAny>>>>(py) {
-            Data::Tensor { data: tensor_data }
-        } else if let Ok(scalar_data) = data.extract::<Py<PyArray1<Py<PyAny>>>>(py) {
-            Data::Scalar { data: scalar_data }
-        } else {
-            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                "Provided data is neither Tensor nor Scalar",
-            ));
-        };
 
-        Ok(DataChunk {
-            entity_path,
-            timelines,
-            data: data_enum,
-        })
-    }
+/// Open and decode an RRD file into a `StoreBundle`.
+///
+/// The decoded recordings are cached until the file changes, see [`set_cache_budget`].
+pub fn load_bundle(file_path: &str) -> Result<Arc<StoreBundle>> {
+    cache::load_cached_bundle(file_path).map_err(|e| e.with_path(file_path))
+}
 
-    #[getter]
-    pub fn entity_path(&self) -> &str {
-        &self.entity_path
+/// Decode an RRD stream, such as the bytes of a file already in memory, into a `StoreBundle`.
+///
+/// Decoding errors carry the index of the message that failed, see [`ErrorContext`].
+pub fn read_bundle(encoded: impl Read) -> Result<StoreBundle> {
+    decode_bundle(encoded, |message_index| message_index)
+}
+
+/// Decode an RRD stream like [`read_bundle`], with `file_index` mapping the index of a message in
+/// the stream to its index in the file, for streams of selected messages.
+pub(crate) fn decode_bundle(
+    encoded: impl Read,
+    file_index: impl Fn(usize) -> usize,
+) -> Result<StoreBundle> {
+    let decoder = Decoder::new(VersionPolicy::Warn, encoded).map_err(rewrite::decode_error)?;
+    let mut bundle = StoreBundle::default();
+    for (message_index, message) in decoder.enumerate() {
+        let at_message = |e: Error| e.with_message_index(file_index(message_index));
+        let message = message.map_err(rewrite::decode_error).map_err(at_message)?;
+        bundle
+            .entry(message.store_id())
+            .add(&message)
+            .map_err(rewrite::decode_error)
+            .map_err(at_message)?;
     }
+    Ok(bundle)
+}
 
-    #[getter]
-    pub fn timelines(&self, py: Python) -> Py<PyDict> {
-        let dict_items: Vec<(&str, Py<PyArray1<i64>>)> = self
-            .timelines
-            .iter()
-            .map(|(k, v)| (k.as_str(), v.clone()))
-            .collect();
+/// Collect the chunks of an `EntityDb` for which `predicate` returns true, in the order they were
+/// logged.
+pub fn query_chunks(rrd: &EntityDb, predicate: impl Fn(&Chunk) -> bool) -> Vec<Chunk> {
+    let mut chunks: Vec<&Arc<Chunk>> = rrd
+        .store()
+        .iter_chunks()
+        .filter(|chunk| predicate(chunk))
+        .collect();
+    // The store iterates by chunk id, the row ids keep the logging order like `to_messages`
+    chunks.sort_by_key(|chunk| chunk.row_id_range().map(|(min, _)| min));
+
+    chunks
+        .into_iter()
+        .map(|chunk| Chunk::clone(chunk))
+        .collect()
+}
 
-        dict_items.into_py_dict_bound(py).unbind()
+/// Collect the chunks logged exactly at `entity_path` for which `predicate` returns true, in the
+/// order they were logged.
+///
+/// Unlike [`query_chunks`], only the chunks of that entity are visited, through the per-entity
+/// indexes of the store.
+pub fn query_entity_chunks(
+    rrd: &EntityDb,
+    entity_path: &str,
+    predicate: impl Fn(&Chunk) -> bool,
+) -> Vec<Chunk> {
+    let store = rrd.store();
+    let entity_path = EntityPath::parse_forgiving(entity_path);
+    let mut chunks: BTreeMap<ChunkId, Arc<Chunk>> = BTreeMap::new();
+
+    // A static component is stored in a single chunk, whatever the query time
+    for component_name in store.all_components(&entity_path).into_iter().flatten() {
+        if store.entity_has_static_component(&entity_path, &component_name) {
+            let query = LatestAtQuery::latest(Timeline::log_tick());
+            for chunk in store.latest_at_relevant_chunks(&query, &entity_path, component_name) {
+                chunks.insert(chunk.id(), chunk);
+            }
+        }
     }
-
-    #[getter]
-    pub fn data(&self, py: Python) -> PyObject {
-        match &self.data {
-            Data::Tensor { data } => data.clone_ref(py).into(),
-            Data::Scalar { data } => data.clone_ref(py).into(),
+    for timeline in rrd.timelines() {
+        let query = RangeQuery::everything(*timeline);
+        for chunk in store.range_relevant_chunks_for_all_components(&query, &entity_path) {
+            chunks.insert(chunk.id(), chunk);
         }
     }
+
+    let mut chunks: Vec<Arc<Chunk>> = chunks
+        .into_values()
+        .filter(|chunk| predicate(chunk))
+        .collect();
+    chunks.sort_by_key(|chunk| chunk.row_id_range().map(|(min, _)| min));
+
+    chunks
+        .into_iter()
+        .map(|chunk| Chunk::clone(&chunk))
+        .collect()
 }
 
-impl Default for DataChunk {
-    fn default() -> Self {
-        Python::with_gil(|py| DataChunk {
-            entity_path: String::new(),
-            timelines: HashMap::new(),
-            data: Data::Tensor {
-                data: PyArray2::zeros_bound(py, (0, 0), false).into(),
-            },
-        })
+/// Collect the scalar and tensor chunks of an `EntityDb` that match the given filters.
+///
+/// The filters have the same meaning as in [`query_data_entities`]. When the entity filter is
+/// contained in the path of a single entity, e.g. it is the full path of an entity without
+/// children, only the chunks of that entity are visited, see [`query_entity_chunks`].
+pub fn query_data_chunks(
+    rrd: &EntityDb,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+) -> Vec<Chunk> {
+    let is_data =
+        |chunk: &Chunk| matches_data_type(chunk, data_type_filter) && is_data_chunk(chunk);
+    if let Some(entity_path) = single_matching_entity(rrd, entity_path_filter) {
+        return query_entity_chunks(rrd, &entity_path, is_data);
     }
+    query_chunks(rrd, |chunk| {
+        matches_entity_path(chunk, entity_path_filter) && is_data(chunk)
+    })
 }
 
-fn to_data_chunk(py: Python, chunk: &Chunk) -> PyResult<Py<DataChunk>> {
-    let entity_path = chunk.entity_path().to_string();
-    debug!("Entity Path: {}", entity_path);
-
-    // Handle timelines
-    let mut timelines = HashMap::new();
-    for (timeline, time_column) in chunk.timelines() {
-        debug!(
-            "Timeline: {:?} {:?} {:?}",
-            timeline.name(),
-            time_column.num_rows(),
-            time_column.times_raw().len()
-        );
-
-        let time_array = PyArray1::from_vec_bound(py, time_column.times_raw().to_vec()).unbind();
-        timelines.insert(timeline.name().to_string(), time_array);
+/// The path of the only entity of an `EntityDb` containing `entity_path_filter`, if exactly one
+/// does.
+fn single_matching_entity(rrd: &EntityDb, entity_path_filter: &str) -> Option<String> {
+    if entity_path_filter.is_empty() {
+        return None;
     }
-
-    // Handle data
-    let data = if is_tensor_chunk(chunk) {
-        to_tensor_data(py, chunk)?
-    } else if is_scalar_chunk(chunk) {
-        to_scalar_data(py, chunk)?
-    } else {
-        return Err(PyErr::new::<PyValueError, _>("Unsupported chunk type"));
-    };
-
-    Py::new(
-        py,
-        DataChunk {
-            entity_path,
-            timelines,
-            data,
-        },
-    )
+    let mut matching = rrd
+        .entity_paths()
+        .into_iter()
+        .map(|entity_path| entity_path.to_string())
+        .filter(|entity_path| entity_path.contains(entity_path_filter));
+    let entity_path = matching.next()?;
+    matching.next().is_none().then_some(entity_path)
 }
 
-fn to_tensor_data(py: Python, chunk: &Chunk) -> PyResult<Data> {
-    let mut all_rows = Vec::new();
-
-    if let Some((_, tensor_data)) = chunk.components().first_key_value() {
-        for i in 0..tensor_data.len() {
-            let sub_array = tensor_data.value(i);
-            // debug!("sub_array: {:?}", sub_array);
-
-            if let Some(struct_array) = sub_array.as_any().downcast_ref::<array::StructArray>() {
-                if let Some(buffer_array) = struct_array.values().get(1) {
-                    let row = match_array_to_numpy(py, buffer_array.as_ref())?;
-                    all_rows.push(row);
-                } else {
-                    error!("Buffer field not found in StructArray");
-                }
-            } else {
-                error!(
-                    "Failed to downcast sub_array to StructArray, {:?}",
-                    sub_array
-                );
-            }
-        }
-
-        let tensor_array = PyArray2::from_vec2_bound(py, &all_rows)?.unbind();
-        Ok(Data::Tensor { data: tensor_array })
+/// Collect the text chunks of an `EntityDb` logged exactly at `entity_path`.
+///
+/// An empty `entity_path` returns the text chunks of all entities.
+pub fn query_meta_chunks(rrd: &EntityDb, entity_path: &str) -> Vec<Chunk> {
+    if entity_path.is_empty() {
+        query_chunks(rrd, is_meta_chunk)
     } else {
-        Err(PyErr::new::<PyValueError, _>(
-            "No tensor data found in chunk",
-        ))
+        query_entity_chunks(rrd, entity_path, is_meta_chunk)
     }
 }
 
-fn to_scalar_data(py: Python, chunk: &Chunk) -> PyResult<Data> {
-    let mut all_rows = Vec::new();
-
-    if let Some((_, scalar_data)) = chunk.components().first_key_value() {
-        for i in 0..scalar_data.len() {
-            let sub_array = scalar_data.value(i);
-            // debug!("sub_array: {:?}", sub_array);
+/// Build the output path for an entity inside `out_dir`, mirroring the entity path hierarchy.
+///
+/// `/action/joints/position` with extension `parquet` becomes `<out_dir>/action/joints/position.parquet`.
+/// Entity paths with an empty, `.` or `..` part are rejected, as they would not map to a file
+/// inside `out_dir`.
+#[cfg(feature = "export")]
+fn entity_file_path(out_dir: &Path, entity_path: &str, extension: &str) -> Result<PathBuf> {
+    let relative = relative_entity_path(entity_path)?;
+    let relative = if relative.is_empty() {
+        "root"
+    } else {
+        relative
+    };
 
-            if let Some(scalar_value) = sub_array.as_any().downcast_ref::<array::Float64Array>() {
-                // Assuming the scalar value is a single element in the array
-                let value = scalar_value.value(0).into_py(py);
-                all_rows.push(value);
-            } else {
-                error!(
-                    "Failed to downcast sub_array to Float64Array, {:?}",
-                    sub_array
-                );
-            }
-        }
+    let mut path = out_dir.to_path_buf();
+    path.push(format!("{}.{}", relative, extension));
+    Ok(path)
+}
 
-        let scalar_array = PyArray1::from_vec_bound(py, all_rows).unbind();
-        Ok(Data::Scalar { data: scalar_array })
-    } else {
-        Err(PyErr::new::<PyValueError, _>(
-            "No scalar data found in chunk",
-        ))
+/// The entity path without its leading and trailing `/`, checked to be a relative path that stays
+/// inside the directory it is joined to.
+#[cfg(feature = "export")]
+fn relative_entity_path(entity_path: &str) -> Result<&str> {
+    let relative = entity_path.trim_matches('/');
+    let is_invalid = |part: &str| part.is_empty() || part == "." || part == "..";
+    if !relative.is_empty() && relative.split('/').any(is_invalid) {
+        return Err(Error::Invalid(format!(
+            "Cannot write {} to a file: entity path parts must not be empty, \".\" or \"..\"",
+            entity_path
+        )));
     }
+    Ok(relative)
 }
 
-fn is_tensor_chunk(chunk: &Chunk) -> bool {
-    chunk
-        .component_names()
-        .any(|name| name == "rerun.components.TensorData")
+/// Resolve the path of a recording served from a folder, which must be a `.rrd` file directly
+/// inside the folder.
+#[cfg(any(feature = "server", feature = "flight"))]
+fn folder_rrd_path(folder_path: &Path, rrd_name: &str) -> Result<String> {
+    let is_file_name = Path::new(rrd_name)
+        .file_name()
+        .is_some_and(|file_name| file_name == rrd_name);
+    if !is_file_name || !rrd_name.ends_with(".rrd") {
+        return Err(Error::Invalid(format!(
+            "Invalid recording name: {}",
+            rrd_name
+        )));
+    }
+    Ok(folder_path.join(rrd_name).to_string_lossy().into_owned())
 }
 
-fn is_scalar_chunk(chunk: &Chunk) -> bool {
-    chunk
-        .component_names()
-        .any(|name| name == "rerun.components.Scalar")
+/// A scalar or tensor chunk of an entity.
+#[derive(Debug, Clone)]
+pub struct DataChunk {
+    /// The path of the entity associated with this chunk.
+    pub entity_path: String,
+    /// The raw times of every row, per timeline name: nanoseconds for temporal timelines.
+    pub timelines: BTreeMap<String, Vec<i64>>,
+    /// The values of every row.
+    pub data: ChunkData,
 }
 
-fn is_data_chunk(chunk: &Chunk) -> bool {
-    is_scalar_chunk(chunk) || is_tensor_chunk(chunk)
+/// The values of a [`DataChunk`], one item per row.
+#[derive(Debug, Clone)]
+pub enum ChunkData {
+    /// The elements of the tensor of every row, in the native type of the tensor buffer.
+    Tensor(ListArray<i32>),
+    /// The scalar of every row, null for rows without a value.
+    Scalar(PrimitiveArray<f64>),
 }
 
-fn convert_field_to_py(
-    py: Python,
-    field: &Box<dyn array::Array>,
-    value_index: usize,
-) -> PyResult<PyObject> {
-    match field.data_type() {
-        DataType::Null => Ok(py.None()),
-        DataType::Boolean => {
-            let array = field
-                .as_any()
-                .downcast_ref::<array::BooleanArray>()
-                .unwrap();
-            Ok(array.value(value_index).into_py(py))
+impl ChunkData {
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Tensor(data) => data.len(),
+            Self::Scalar(data) => data.len(),
         }
-        DataType::Int8 => {
-            let array = field.as_any().downcast_ref::<array::Int8Array>().unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::Int16 => {
-            let array = field.as_any().downcast_ref::<array::Int16Array>().unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::Int32 => {
-            let array = field.as_any().downcast_ref::<array::Int32Array>().unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::Int64 => {
-            let array = field.as_any().downcast_ref::<array::Int64Array>().unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::UInt8 => {
-            let array = field.as_any().downcast_ref::<array::UInt8Array>().unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::UInt16 => {
-            let array = field.as_any().downcast_ref::<array::UInt16Array>().unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::UInt32 => {
-            let array = field.as_any().downcast_ref::<array::UInt32Array>().unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::UInt64 => {
-            let array = field.as_any().downcast_ref::<array::UInt64Array>().unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::Float32 => {
-            let array = field
-                .as_any()
-                .downcast_ref::<array::Float32Array>()
-                .unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::Float64 => {
-            let array = field
-                .as_any()
-                .downcast_ref::<array::Float64Array>()
-                .unwrap();
-            Ok(array.value(value_index).into_py(py))
-        }
-        DataType::List(_) => {
-            let list_array = field
-                .as_any()
-                .downcast_ref::<array::ListArray<i32>>()
-                .unwrap();
-            let list_element: Box<dyn array::Array> = list_array.value(value_index);
-            let py_list = PyList::empty_bound(py);
-            for i in 0..list_element.len() {
-                let item = convert_field_to_py(py, &list_element, i)?;
-                py_list.append(item)?;
-            }
-            Ok(py_list.into())
-        }
-        // Add other types as needed
-        _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-            "Unsupported data type: {:?}",
-            field.data_type()
-        ))),
     }
-}
-
-// Helper function to match an Array to the correct NumPy array type and convert it to Vec<PyObject>
-fn match_array_to_numpy(py: Python, array: &dyn array::Array) -> PyResult<Vec<PyObject>> {
-    if let Some(union_array) = array.as_any().downcast_ref::<array::UnionArray>() {
-        let mut result: Vec<Py<PyAny>> = Vec::with_capacity(union_array.len());
-        let fields = union_array.fields();
-        let type_ids = union_array.types();
-        let offsets = union_array.offsets();
-
-        for i in 0..union_array.len() {
-            let field_type = type_ids[i];
-            let value_index = match offsets {
-                Some(offset_buffer) => offset_buffer[i] as usize, // Using the offset if available
-                None => i, // If no offsets, use the index directly
-            };
-            let value = convert_field_to_py(py, &fields[field_type as usize], value_index)?;
-            result.push(value);
-        }
 
-        Ok(result)
-    } else {
-        Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
-            "Expected UnionArray but got a different array type",
-        ))
+    /// Whether there are no rows.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
@@ -389,68 +381,171 @@ fn match_array_to_numpy(py: Python, array: &dyn array::Array) -> PyResult<Vec<Py
 /// # Arguments
 ///
 /// * `file_path` - A string slice that holds the path to the RRD file.
-/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to return all data.
 /// * `data_type_filter` - A string slice that holds the data type to filter. Set to "scalar" or "tensor" to filter by data type.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to return all data.
 ///
 /// # Returns
 ///
-/// * `PyResult<Py<PyList>>` - A list of ActionChunk objects.
-#[pyfunction]
+/// * `Result<Vec<DataChunk>>` - The data chunks, or an error if no chunk matches.
 pub fn query_data_entities(
-    py: Python<'_>,
     file_path: &str,
-    data_type_filter: &str,   // "scalar" or "tensor", or "" for both
-    entity_path_filter: &str, // "" for all entities
-) -> PyResult<Py<PyList>> {
-    let encoded = File::open(&file_path).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyIOError, _>(format!("File open error: {}", e))
-    })?;
-    let bundle: StoreBundle =
-        StoreBundle::from_rrd(re_log_encoding::decoder::VersionPolicy::Warn, encoded).map_err(
-            |e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Decoding error: {}", e)),
-        )?;
+    data_type_filter: &str,
+    entity_path_filter: &str,
+) -> Result<Vec<DataChunk>> {
+    query_data_entities_in_range(
+        file_path,
+        data_type_filter,
+        entity_path_filter,
+        None,
+        "log_time",
+    )
+}
+
+/// Retrieve specific data (scalar or tensor) like [`query_data_entities`], restricted to the rows
+/// whose time on `timeline` is within `time_range`, bounds included.
+///
+/// Static chunks are always kept, and temporal chunks without `timeline` are dropped when a
+/// `time_range` is given. If the file is indexed with [`build_index`], only the chunks matching
+/// the filters are decoded.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `data_type_filter` - A string slice that holds the data type to filter. Set to "scalar" or "tensor" to filter by data type.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to return all data.
+/// * `time_range` - The `(start, end)` times to keep on `timeline`, in nanoseconds for temporal timelines. `None` keeps all times.
+/// * `timeline` - The timeline of `time_range`.
+///
+/// # Returns
+///
+/// * `Result<Vec<DataChunk>>` - The data chunks, or an error if no chunk matches or a chunk has a
+///   type that cannot be converted.
+pub fn query_data_entities_in_range(
+    file_path: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    time_range: Option<(i64, i64)>,
+    timeline: &str,
+) -> Result<Vec<DataChunk>> {
+    let chunks = load_data_chunks(
+        file_path,
+        data_type_filter,
+        entity_path_filter,
+        time_range,
+        timeline,
+    )?;
+    found_data_chunks(
+        file_path,
+        entity_path_filter,
+        chunks.iter().map(to_data_chunk).collect(),
+    )
+}
 
-    let rrd = get_action_entity_db(&bundle);
+/// The chunks of a query of `file_path`, or the error of the query, e.g. of the first chunk that
+/// could not be converted, or an error if no chunk matches the filters.
+pub(crate) fn found_data_chunks<T>(
+    file_path: &str,
+    entity_path_filter: &str,
+    data_chunks: Result<Vec<T>>,
+) -> Result<Vec<T>> {
+    match data_chunks {
+        Ok(data_chunks) if data_chunks.is_empty() => Err(Error::NoData(
+            "No data found for the specified entity and data type".to_string(),
+        )
+        .with_path(file_path)
+        .with_entity(entity_path_filter)),
+        data_chunks => data_chunks.map_err(|e| e.with_path(file_path)),
+    }
+}
+
+/// Load the chunks selected by the filters of [`query_data_entities_in_range`], trimmed to the
+/// time range.
+pub(crate) fn load_data_chunks(
+    file_path: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    time_range: Option<(i64, i64)>,
+    timeline: &str,
+) -> Result<Vec<Chunk>> {
+    let recording = index::load_action_recording(file_path, |entry| {
+        let data_type_matches = match data_type_filter {
+            "scalar" => entry.has_component("rerun.components.Scalar"),
+            "tensor" => entry.has_component("rerun.components.TensorData"),
+            "" => true,
+            _ => false,
+        };
+        data_type_matches
+            && entry.entity_path.contains(entity_path_filter)
+            && entry.overlaps(timeline, time_range)
+    })?;
+    let rrd = recording.entity_db();
     debug!("This rrd file contains {} rows", rrd.num_rows());
 
-    let mut data_chunks: Vec<Py<DataChunk>> = Vec::new();
-
-    let message_iter = rrd.to_messages(None);
-    message_iter.for_each(|message| {
-        message.iter().for_each(|m| match m {
-            LogMsg::ArrowMsg(_store_id, arrow_msg) => match Chunk::from_arrow_msg(&arrow_msg) {
-                Ok(chunk) => {
-                    debug!("Schema: {:#?}", arrow_msg.schema);
-                    if matches_data_type(&chunk, data_type_filter)
-                        && matches_entity_path(&chunk, entity_path_filter)
-                        && is_data_chunk(&chunk)
-                    {
-                        match to_data_chunk(py, &chunk) {
-                            Ok(data_chunk) => {
-                                data_chunks.push(data_chunk);
-                            }
-                            Err(e) => println!("Failed calling to_data_chunk: {:?}", e),
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("Error converting ArrowMsg to Chunk: {}", e);
-                }
-            },
-            _ => {
-                debug!("This LogMsg is not an ArrowMsg: {:?}", m);
-            }
-        });
-    });
+    Ok(query_data_chunks(rrd, data_type_filter, entity_path_filter)
+        .iter()
+        .filter_map(|chunk| rewrite::trim_chunk(chunk, timeline, time_range))
+        .collect())
+}
 
-    if data_chunks.is_empty() {
-        Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-            "No data found for the specified entity and data type",
-        ))
+pub(crate) fn to_data_chunk(chunk: &Chunk) -> Result<DataChunk> {
+    let timelines = chunk
+        .timelines()
+        .iter()
+        .map(|(timeline, time_column)| {
+            (
+                timeline.name().to_string(),
+                time_column.times_raw().to_vec(),
+            )
+        })
+        .collect();
+
+    let component = if is_tensor_chunk(chunk) {
+        "rerun.components.TensorData"
     } else {
-        let py_data_chunks = PyList::new_bound(py, &data_chunks).unbind();
-        Ok(py_data_chunks)
-    }
+        "rerun.components.Scalar"
+    };
+    let list_array = chunk
+        .components()
+        .get(&ComponentName::from(component))
+        .ok_or_else(|| {
+            Error::UnsupportedComponent("Unsupported chunk type".to_string())
+                .with_entity(&chunk.entity_path().to_string())
+        })?;
+
+    let data = if is_tensor_chunk(chunk) {
+        ChunkData::Tensor(columns::tensor_column(list_array).map_err(|e| {
+            Error::UnsupportedComponent(format!("Data conversion error: {}", e))
+                .with_entity(&chunk.entity_path().to_string())
+                .with_component(component)
+        })?)
+    } else {
+        ChunkData::Scalar(columns::scalar_column(list_array))
+    };
+
+    Ok(DataChunk {
+        entity_path: chunk.entity_path().to_string(),
+        timelines,
+        data,
+    })
+}
+
+/// Whether a chunk holds `TensorData`.
+pub fn is_tensor_chunk(chunk: &Chunk) -> bool {
+    chunk
+        .component_names()
+        .any(|name| name == "rerun.components.TensorData")
+}
+
+/// Whether a chunk holds `Scalar`s.
+pub fn is_scalar_chunk(chunk: &Chunk) -> bool {
+    chunk
+        .component_names()
+        .any(|name| name == "rerun.components.Scalar")
+}
+
+/// Whether a chunk holds scalars or tensors.
+pub fn is_data_chunk(chunk: &Chunk) -> bool {
+    is_scalar_chunk(chunk) || is_tensor_chunk(chunk)
 }
 
 fn matches_data_type(chunk: &Chunk, data_type_filter: &str) -> bool {
@@ -470,74 +565,21 @@ fn matches_entity_path(chunk: &Chunk, entity_path_filter: &str) -> bool {
     }
 }
 
-#[pyclass]
-/// A class representing a text chunk extracted from the RRD file.
-///
-/// # Fields
+/// A text chunk extracted from the RRD file.
 ///
-/// * `entity_path` - The path of the entity associated with this chunk.
-/// * `media_type` - The media type of the metadata, such as text/plain.
-/// * `text` - The metadata.
-///
-/// This class is subject to change in the future, as data types are being extended.
-pub enum MetaChunk {
-    Text {
-        entity_path: String,
-        media_type: String,
-        text: String,
-    },
-}
-
-// Implement the conversion to Py<MetaChunk>
-impl IntoPy<Py<MetaChunk>> for MetaChunk {
-    fn into_py(self, py: Python) -> Py<MetaChunk> {
-        Py::new(py, self).unwrap() // Adjust error handling as needed
-    }
+/// This type is subject to change in the future, as data types are being extended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaChunk {
+    /// The path of the entity associated with this chunk.
+    pub entity_path: String,
+    /// The media type of the metadata, such as text/plain.
+    pub media_type: String,
+    /// The metadata.
+    pub text: String,
 }
-#[pymethods]
-impl MetaChunk {
-    #[new]
-    pub fn new(entity_path: String, media_type: String, text: String) -> Self {
-        MetaChunk::Text {
-            entity_path,
-            media_type,
-            text,
-        }
-    }
 
-    #[getter]
-    pub fn entity_path(&self) -> &str {
-        let MetaChunk::Text { entity_path, .. } = self;
-        entity_path
-    }
-
-    #[getter]
-    pub fn media_type(&self) -> &str {
-        let MetaChunk::Text { media_type, .. } = self;
-        media_type
-    }
-
-    #[getter]
-    pub fn text(&self) -> &str {
-        let MetaChunk::Text { text, .. } = self;
-        text
-    }
-
-    fn __repr__(&self) -> PyResult<String> {
-        let MetaChunk::Text {
-            entity_path,
-            media_type,
-            text,
-        } = self;
-
-        Ok(format!(
-            "<MetaChunk(entity_path='{}', media_type='{}', text='{}')>",
-            entity_path, media_type, text
-        ))
-    }
-}
-
-fn is_meta_chunk(chunk: &Chunk) -> bool {
+/// Whether a chunk holds `Text`.
+pub fn is_meta_chunk(chunk: &Chunk) -> bool {
     chunk
         .component_names()
         .any(|name| name == "rerun.components.Text")
@@ -553,63 +595,42 @@ fn is_meta_chunk(chunk: &Chunk) -> bool {
 ///
 /// # Returns
 ///
-/// * `PyResult<Py<PyList>>` - A list of MetaChunk objects.
-#[pyfunction]
-#[pyo3(text_signature = "(file_path: str, entity_path: str) -> List[MetaChunk]")]
-pub fn query_meta_entities(
-    py: Python<'_>,
-    file_path: &str,
-    entity_path: &str,
-) -> PyResult<Py<PyList>> {
-    let encoded = File::open(&file_path)
-        .map_err(|e| PyErr::new::<PyIOError, _>(format!("File open error: {}", e)))?;
-    let bundle: StoreBundle = StoreBundle::from_rrd(VersionPolicy::Warn, encoded)
-        .map_err(|e| PyErr::new::<PyValueError, _>(format!("Decoding error: {}", e)))?;
-
-    let rrd = get_action_entity_db(&bundle);
-
-    let mut meta_chunks: Vec<Py<MetaChunk>> = Vec::new();
-
-    let message_iter = rrd.to_messages(None);
-    message_iter.for_each(|message| {
-        message.iter().for_each(|m| match m {
-            LogMsg::ArrowMsg(_store_id, arrow_msg) => match Chunk::from_arrow_msg(&arrow_msg) {
-                Ok(chunk) => {
-                    if (entity_path.is_empty() || entity_path == &chunk.entity_path().to_string())
-                        && is_meta_chunk(&chunk)
-                    {
-                        debug!("Meta chunk: {:?}", chunk);
-                        match to_meta_chunk(py, &chunk) {
-                            Ok(meta_chunk) => {
-                                meta_chunks.push(meta_chunk);
-                            }
-                            Err(e) => println!("Failed calling to_meta_chunk: {:?}", e),
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("Error converting ArrowMsg to Chunk: {}", e);
-                }
-            },
-            _ => {
-                debug!("This LogMsg is not an ArrowMsg: {:?}", m);
+/// * `Result<Vec<MetaChunk>>` - The text chunks, or an error if no chunk matches.
+pub fn query_meta_entities(file_path: &str, entity_path: &str) -> Result<Vec<MetaChunk>> {
+    let recording = index::load_action_recording(file_path, |entry| {
+        (entity_path.is_empty() || entry.entity_path == entity_path)
+            && entry.has_component("rerun.components.Text")
+    })?;
+    meta_entities(recording.entity_db(), entity_path).map_err(|e| e.with_path(file_path))
+}
+
+/// The text chunks of an `EntityDb`, as in [`query_meta_entities`].
+pub(crate) fn meta_entities(rrd: &EntityDb, entity_path: &str) -> Result<Vec<MetaChunk>> {
+    let meta_chunks: Vec<MetaChunk> = query_meta_chunks(rrd, entity_path)
+        .iter()
+        .map(|chunk| {
+            debug!("Meta chunk: {:?}", chunk);
+            let (media_type, text) = meta_text(chunk);
+            MetaChunk {
+                entity_path: chunk.entity_path().to_string(),
+                media_type,
+                text,
             }
-        });
-    });
+        })
+        .collect();
 
     if meta_chunks.is_empty() {
-        Err(PyErr::new::<PyValueError, _>(
-            "No meta entities found for the specified entity and data type",
-        ))
+        Err(Error::NoData(
+            "No meta entities found for the specified entity and data type".to_string(),
+        )
+        .with_entity(entity_path))
     } else {
-        let py_meta_chunks = PyList::new_bound(py, &meta_chunks).unbind();
-        Ok(py_meta_chunks)
+        Ok(meta_chunks)
     }
 }
 
-fn to_meta_chunk(py: Python, chunk: &Chunk) -> PyResult<Py<MetaChunk>> {
-    let entity_path = chunk.entity_path().to_string();
-
+/// Extract the media type and text of the first row of a text chunk.
+fn meta_text(chunk: &Chunk) -> (String, String) {
     let media_type_component = ComponentName::from("rerun.components.MediaType");
     let text_component = ComponentName::from("rerun.components.Text");
 
@@ -649,26 +670,5 @@ fn to_meta_chunk(py: Python, chunk: &Chunk) -> PyResult<Py<MetaChunk>> {
         "no text".to_string()
     };
 
-    let meta_chunk = MetaChunk::Text {
-        entity_path,
-        media_type,
-        text,
-    };
-
-    Py::new(py, meta_chunk)
-}
-
-/// A Python module implemented in Rust.
-/// This module is a plugin for the Python package `rerun-query`.
-#[pymodule]
-fn requery(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
-    env_logger::init();
-
-    m.add_function(wrap_pyfunction_bound!(query_data_entities, m)?)?;
-    m.add_function(wrap_pyfunction_bound!(query_meta_entities, m)?)?;
-    m.add_function(wrap_pyfunction_bound!(list_entity_paths, m)?)?;
-    m.add_class::<DataChunk>()?;
-    m.add_class::<MetaChunk>()?;
-    m.add_class::<Data>()?;
-    Ok(())
+    (media_type, text)
 }
@@ -0,0 +1,530 @@
+//! Export of RRD entities to MCAP files, for use with Foxglove and ROS 2 tooling.
+//!
+//! Every entity becomes one channel whose topic is the entity path. Messages are JSON encoded
+//! and described by JSON schemas: images and text use the well known Foxglove schemas
+//! (`foxglove.CompressedImage`, `foxglove.RawImage` and `foxglove.Log`), scalars and tensors
+//! small `requery.Scalar` and `requery.Tensor` schemas.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use ::mcap::{records::MessageHeader, Compression, McapError, WriteOptions};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use log::{debug, warn};
+use re_arrow2::{
+    array::{Array, Float64Array, ListArray},
+    compute::cast::{cast, CastOptions},
+    datatypes::DataType,
+};
+use re_chunk::{Chunk, ComponentName};
+use re_log_types::TimeType;
+use serde_json::{json, Value};
+
+use crate::{columns, index::load_recording, matches_entity_path, query_chunks, Error, Result};
+
+/// The kind of message an entity is exported as.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MessageKind {
+    Scalar,
+    Tensor,
+    CompressedImage,
+    RawImage,
+    Log,
+}
+
+impl MessageKind {
+    /// Pick the message kind of a chunk from its components, or `None` if it is not exported.
+    fn of(chunk: &Chunk) -> Option<Self> {
+        let has = |name: &str| chunk.components().contains_key(&ComponentName::from(name));
+
+        if has("rerun.components.Blob") {
+            Some(Self::CompressedImage)
+        } else if has("rerun.components.ImageBuffer") && has("rerun.components.ImageFormat") {
+            Some(Self::RawImage)
+        } else if has("rerun.components.Text") {
+            Some(Self::Log)
+        } else if has("rerun.components.Scalar") {
+            Some(Self::Scalar)
+        } else if has("rerun.components.TensorData") {
+            Some(Self::Tensor)
+        } else {
+            None
+        }
+    }
+
+    fn schema_name(self) -> &'static str {
+        match self {
+            Self::Scalar => "requery.Scalar",
+            Self::Tensor => "requery.Tensor",
+            Self::CompressedImage => "foxglove.CompressedImage",
+            Self::RawImage => "foxglove.RawImage",
+            Self::Log => "foxglove.Log",
+        }
+    }
+
+    /// The JSON schema of the messages of this kind.
+    fn schema(self) -> Value {
+        let timestamp = json!({
+            "type": "object",
+            "properties": {
+                "sec": { "type": "integer", "minimum": 0 },
+                "nsec": { "type": "integer", "minimum": 0, "maximum": 999_999_999 },
+            },
+        });
+        let bytes = json!({ "type": "string", "contentEncoding": "base64" });
+
+        let properties = match self {
+            Self::Scalar => json!({
+                "timestamp": timestamp,
+                "value": { "type": "number" },
+            }),
+            Self::Tensor => json!({
+                "timestamp": timestamp,
+                "shape": { "type": "array", "items": { "type": "integer", "minimum": 0 } },
+                "names": { "type": "array", "items": { "type": ["string", "null"] } },
+                "data": { "type": "array", "items": { "type": "number" } },
+            }),
+            Self::CompressedImage => json!({
+                "timestamp": timestamp,
+                "frame_id": { "type": "string" },
+                "data": bytes,
+                "format": { "type": "string" },
+            }),
+            Self::RawImage => json!({
+                "timestamp": timestamp,
+                "frame_id": { "type": "string" },
+                "width": { "type": "integer", "minimum": 0 },
+                "height": { "type": "integer", "minimum": 0 },
+                "encoding": { "type": "string" },
+                "step": { "type": "integer", "minimum": 0 },
+                "data": bytes,
+            }),
+            Self::Log => json!({
+                "timestamp": timestamp,
+                "level": { "type": "integer", "enum": [0, 1, 2, 3, 4, 5] },
+                "message": { "type": "string" },
+                "name": { "type": "string" },
+                "file": { "type": "string" },
+                "line": { "type": "integer", "minimum": 0 },
+            }),
+        };
+
+        json!({
+            "title": self.schema_name(),
+            "type": "object",
+            "properties": properties,
+        })
+    }
+}
+
+/// A JSON encoded message, waiting to be written in time order.
+struct PendingMessage {
+    log_time: u64,
+    channel: usize,
+    data: Vec<u8>,
+}
+
+/// Export the entities of an RRD file to an MCAP file.
+///
+/// Every entity becomes one channel with the entity path as topic and JSON encoded messages:
+///
+/// * `EncodedImage` entities as `foxglove.CompressedImage`.
+/// * `Image` entities as `foxglove.RawImage`, for the pixel formats Foxglove can display.
+/// * `Text` and `TextLog` entities as `foxglove.Log`.
+/// * `Scalar` entities as `requery.Scalar` (`{"timestamp", "value"}`).
+/// * `TensorData` entities as `requery.Tensor` (`{"timestamp", "shape", "names", "data"}`),
+///   with the elements flattened in row-major order.
+///
+/// The log time, publish time and `timestamp` field of every message come from the given
+/// temporal timeline. Static data is written at time zero, and temporal data not logged on the
+/// timeline is skipped. Messages are written in time order.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `out_path` - The path of the MCAP file to write. Its directory is created if missing.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to export all entities.
+/// * `timeline` - The temporal timeline used for the message timestamps.
+/// * `compression` - The chunk compression to use: "zstd", "lz4" or "none".
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of messages written.
+pub fn export_mcap(
+    file_path: &str,
+    out_path: &str,
+    entity_path_filter: &str,
+    timeline: &str,
+    compression: &str,
+) -> Result<usize> {
+    let compression = parse_compression(compression)?;
+
+    let recording = load_recording(file_path)?;
+    let rrd = recording.entity_db();
+
+    let chunks = query_chunks(rrd, |chunk| {
+        matches_entity_path(chunk, entity_path_filter) && MessageKind::of(chunk).is_some()
+    });
+
+    let mut channels: Vec<(String, MessageKind)> = Vec::new();
+    let mut messages: Vec<PendingMessage> = Vec::new();
+
+    for chunk in &chunks {
+        let Some(kind) = MessageKind::of(chunk) else {
+            continue;
+        };
+
+        let times: Vec<i64> = if chunk.is_static() {
+            vec![0; chunk.num_rows()]
+        } else {
+            match chunk
+                .timelines()
+                .iter()
+                .find(|(candidate, _)| candidate.name().as_str() == timeline)
+            {
+                Some((candidate, _)) if candidate.typ() != TimeType::Time => {
+                    return Err(Error::Invalid(format!(
+                        "Timeline {} is not a temporal timeline",
+                        timeline
+                    )));
+                }
+                Some((_, time_column)) => time_column.times_raw().to_vec(),
+                None => {
+                    debug!(
+                        "Skipping chunk of {} not logged on {}",
+                        chunk.entity_path(),
+                        timeline
+                    );
+                    continue;
+                }
+            }
+        };
+
+        let topic = chunk.entity_path().to_string();
+        let channel = match channels
+            .iter()
+            .position(|(existing, existing_kind)| *existing == topic && *existing_kind == kind)
+        {
+            Some(index) => index,
+            None => {
+                channels.push((topic, kind));
+                channels.len() - 1
+            }
+        };
+
+        for (row, message) in chunk_messages(chunk, kind, &times)?.into_iter().enumerate() {
+            let Some(message) = message else {
+                continue;
+            };
+            messages.push(PendingMessage {
+                log_time: u64::try_from(times[row]).unwrap_or(0),
+                channel,
+                data: message.to_string().into_bytes(),
+            });
+        }
+    }
+
+    if messages.is_empty() {
+        return Err(Error::NoData(format!(
+            "No data found for the specified entity on timeline {}",
+            timeline
+        ))
+        .with_path(file_path)
+        .with_entity(entity_path_filter));
+    }
+
+    // Readers expect the data section roughly in time order, which chunks do not guarantee
+    messages.sort_by_key(|message| message.log_time);
+
+    write_mcap(Path::new(out_path), &channels, &messages, compression).map_err(export_error)?;
+
+    Ok(messages.len())
+}
+
+/// Convert every row of a chunk into a JSON message, or `None` for rows without a value.
+fn chunk_messages(chunk: &Chunk, kind: MessageKind, times: &[i64]) -> Result<Vec<Option<Value>>> {
+    let component = |name: &str| chunk.components().get(&ComponentName::from(name));
+    let entity_path = chunk.entity_path().to_string();
+
+    let messages = match kind {
+        MessageKind::Scalar => {
+            let Some(list_array) = component("rerun.components.Scalar") else {
+                return Ok(Vec::new());
+            };
+            columns::scalar_column(list_array)
+                .iter()
+                .zip(times)
+                .map(|(value, time)| {
+                    value.map(|value| json!({ "timestamp": timestamp(*time), "value": value }))
+                })
+                .collect()
+        }
+        MessageKind::Tensor => {
+            let Some(list_array) = component("rerun.components.TensorData") else {
+                return Ok(Vec::new());
+            };
+            tensor_messages(list_array, times)?
+        }
+        MessageKind::CompressedImage => {
+            let Some(blobs) = component("rerun.components.Blob") else {
+                return Ok(Vec::new());
+            };
+            let media_types = component("rerun.components.MediaType");
+            (0..chunk.num_rows())
+                .map(|row| {
+                    let bytes = columns::blob_bytes(blobs, row)?;
+                    let media_type = media_types
+                        .and_then(|media_types| columns::first_str(media_types, row))
+                        .or_else(|| sniff_media_type(&bytes).map(str::to_string))?;
+                    let format = media_type.strip_prefix("image/").unwrap_or(&media_type);
+                    Some(json!({
+                        "timestamp": timestamp(times[row]),
+                        "frame_id": entity_path,
+                        "data": STANDARD.encode(&bytes),
+                        "format": format,
+                    }))
+                })
+                .collect()
+        }
+        MessageKind::RawImage => {
+            let (Some(buffers), Some(formats)) = (
+                component("rerun.components.ImageBuffer"),
+                component("rerun.components.ImageFormat"),
+            ) else {
+                return Ok(Vec::new());
+            };
+            (0..chunk.num_rows())
+                .map(|row| {
+                    let bytes = columns::blob_bytes(buffers, row)?;
+                    // Image formats are often logged once and left empty for the following frames
+                    let format = columns::image_format(formats, row).or_else(|| {
+                        (0..formats.len()).find_map(|row| columns::image_format(formats, row))
+                    })?;
+                    let Some((encoding, bytes_per_pixel)) = raw_image_encoding(&format) else {
+                        warn!(
+                            "Skipping image of {} with a pixel format unsupported by Foxglove",
+                            entity_path
+                        );
+                        return None;
+                    };
+                    Some(json!({
+                        "timestamp": timestamp(times[row]),
+                        "frame_id": entity_path,
+                        "width": format.width,
+                        "height": format.height,
+                        "encoding": encoding,
+                        "step": format.width * bytes_per_pixel,
+                        "data": STANDARD.encode(&bytes),
+                    }))
+                })
+                .collect()
+        }
+        MessageKind::Log => {
+            let Some(texts) = component("rerun.components.Text") else {
+                return Ok(Vec::new());
+            };
+            let levels = component("rerun.components.TextLogLevel");
+            (0..chunk.num_rows())
+                .map(|row| {
+                    let message = columns::first_str(texts, row)?;
+                    let level = levels
+                        .and_then(|levels| columns::first_str(levels, row))
+                        .map_or(2, |level| log_level(&level));
+                    Some(json!({
+                        "timestamp": timestamp(times[row]),
+                        "level": level,
+                        "message": message,
+                        "name": entity_path,
+                        "file": "",
+                        "line": 0,
+                    }))
+                })
+                .collect()
+        }
+    };
+
+    Ok(messages)
+}
+
+/// Convert the rows of a `TensorData` column into `requery.Tensor` messages.
+fn tensor_messages(list_array: &ListArray<i32>, times: &[i64]) -> Result<Vec<Option<Value>>> {
+    let tensors = columns::tensor_column(list_array).map_err(arrow_error)?;
+    // JSON has a single number type, so every element type is written as a double
+    let values = cast(
+        tensors.values().as_ref(),
+        &DataType::Float64,
+        CastOptions::default(),
+    )
+    .map_err(arrow_error)?;
+    let values = values
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .expect("cast to Float64 returns a Float64Array");
+
+    let shape = columns::tensor_shape(list_array).unwrap_or_default();
+    let sizes: Vec<u64> = shape.iter().map(|dimension| dimension.size).collect();
+    let names: Vec<Option<&str>> = shape
+        .iter()
+        .map(|dimension| dimension.name.as_deref())
+        .collect();
+
+    Ok(times
+        .iter()
+        .enumerate()
+        .map(|(row, time)| {
+            if !tensors.is_valid(row) {
+                return None;
+            }
+            let (start, end) = tensors.offsets().start_end(row);
+            Some(json!({
+                "timestamp": timestamp(*time),
+                "shape": sizes,
+                "names": names,
+                "data": &values.values()[start..end],
+            }))
+        })
+        .collect())
+}
+
+/// A Foxglove `Time` from nanoseconds.
+fn timestamp(time: i64) -> Value {
+    json!({
+        "sec": time.div_euclid(1_000_000_000),
+        "nsec": time.rem_euclid(1_000_000_000),
+    })
+}
+
+/// Map a Rerun `TextLogLevel` to a Foxglove `LogLevel`.
+fn log_level(level: &str) -> u8 {
+    match level {
+        "TRACE" | "DEBUG" => 1,
+        "INFO" => 2,
+        "WARN" | "WARNING" => 3,
+        "ERROR" => 4,
+        "CRITICAL" | "FATAL" => 5,
+        _ => 0,
+    }
+}
+
+/// Map a Rerun image format to a Foxglove `RawImage` encoding and its bytes per pixel.
+///
+/// Returns `None` for formats Foxglove cannot display, e.g. NV12.
+fn raw_image_encoding(format: &columns::ImageFormat) -> Option<(&'static str, u32)> {
+    // See `rerun.datatypes.PixelFormat`, `ColorModel` and `ChannelDatatype`
+    const YUY2: u8 = 27;
+    const L: u8 = 1;
+    const RGB: u8 = 2;
+    const RGBA: u8 = 3;
+    const U8: u8 = 6;
+    const U16: u8 = 8;
+    const F32: u8 = 34;
+
+    if let Some(pixel_format) = format.pixel_format {
+        return match pixel_format {
+            YUY2 => Some(("yuyv", 2)),
+            _ => None,
+        };
+    }
+
+    match (format.color_model?, format.channel_datatype?) {
+        (L, U8) => Some(("mono8", 1)),
+        (L, U16) => Some(("mono16", 2)),
+        (L, F32) => Some(("32FC1", 4)),
+        (RGB, U8) => Some(("rgb8", 3)),
+        (RGB, U16) => Some(("rgb16", 6)),
+        (RGBA, U8) => Some(("rgba8", 4)),
+        (RGBA, U16) => Some(("rgba16", 8)),
+        _ => None,
+    }
+}
+
+/// Guess the media type of an encoded image from its magic bytes.
+pub(crate) fn sniff_media_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+fn parse_compression(compression: &str) -> Result<Option<Compression>> {
+    match compression {
+        "zstd" => Ok(Some(Compression::Zstd)),
+        "lz4" => Ok(Some(Compression::Lz4)),
+        "none" | "" => Ok(None),
+        _ => Err(Error::Invalid(format!(
+            "Unsupported compression: {}. Use \"zstd\", \"lz4\" or \"none\"",
+            compression
+        ))),
+    }
+}
+
+/// Write the channels and their time-sorted messages to one MCAP file.
+fn write_mcap(
+    path: &Path,
+    channels: &[(String, MessageKind)],
+    messages: &[PendingMessage],
+    compression: Option<Compression>,
+) -> Result<(), McapError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = BufWriter::new(fs::File::create(path)?);
+    let mut writer = WriteOptions::new()
+        .compression(compression)
+        .library(concat!("rerun-query ", env!("CARGO_PKG_VERSION")))
+        .create(file)?;
+
+    let mut schema_ids: BTreeMap<MessageKind, u16> = BTreeMap::new();
+    let mut channel_ids = Vec::with_capacity(channels.len());
+    for (topic, kind) in channels {
+        let schema_id = match schema_ids.get(kind) {
+            Some(schema_id) => *schema_id,
+            None => {
+                let schema = kind.schema().to_string();
+                let schema_id =
+                    writer.add_schema(kind.schema_name(), "jsonschema", schema.as_bytes())?;
+                schema_ids.insert(*kind, schema_id);
+                schema_id
+            }
+        };
+        channel_ids.push(writer.add_channel(schema_id, topic, "json", &BTreeMap::new())?);
+    }
+
+    let mut sequences = vec![0u32; channels.len()];
+    for message in messages {
+        let sequence = &mut sequences[message.channel];
+        *sequence += 1;
+        writer.write_to_known_channel(
+            &MessageHeader {
+                channel_id: channel_ids[message.channel],
+                sequence: *sequence,
+                log_time: message.log_time,
+                publish_time: message.log_time,
+            },
+            &message.data,
+        )?;
+    }
+
+    writer.finish()?;
+    writer.into_inner().flush()?;
+
+    Ok(())
+}
+
+fn arrow_error(e: re_arrow2::error::Error) -> Error {
+    Error::Encode(format!("MCAP export error: {}", e))
+}
+
+fn export_error(e: McapError) -> Error {
+    match e {
+        McapError::Io(e) => Error::Write(e),
+        e => Error::Encode(format!("MCAP export error: {}", e)),
+    }
+}
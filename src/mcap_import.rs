@@ -0,0 +1,529 @@
+//! Import of MCAP files (including ROS 2 bags recorded with the MCAP storage plugin) into RRD.
+//!
+//! ROS 2 messages are CDR encoded. The supported message types are mapped to Rerun archetypes
+//! logged at the entity path of their topic, on the `log_time` timeline of the MCAP messages:
+//!
+//! | ROS 2 message                   | Rerun                                                  |
+//! |---------------------------------|--------------------------------------------------------|
+//! | `sensor_msgs/msg/JointState`    | `Tensor` at `<topic>/position`, `<topic>/velocity` and `<topic>/effort`, joint names as static `TextDocument` at `<topic>/names` |
+//! | `sensor_msgs/msg/Image`         | `Image`                                                |
+//! | `sensor_msgs/msg/CompressedImage` | `EncodedImage`                                       |
+//! | `std_msgs/msg/Float64`          | `Scalar`                                               |
+//! | `rcl_interfaces/msg/Log`        | `TextLog`                                              |
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use ::mcap::{McapError, MessageStream};
+use log::{debug, warn};
+use re_build_info::CrateVersion;
+use re_chunk::{Chunk, ChunkBuilder, RowId};
+use re_log_encoding::{encoder::Encoder, EncodingOptions};
+use re_log_types::{
+    ApplicationId, EntityPath, LogMsg, SetStoreInfo, StoreId, StoreInfo, StoreKind, StoreSource,
+    Time, TimePoint, Timeline,
+};
+use re_types::{
+    archetypes::{EncodedImage, Image, Scalar, Tensor, TextDocument, TextLog},
+    components::{MediaType, TextLogLevel},
+    datatypes::{
+        ChannelDatatype, ColorModel, PixelFormat, TensorBuffer, TensorData, TensorDimension,
+    },
+    AsComponents,
+};
+use serde_json::json;
+
+use crate::{
+    cdr::{CdrError, CdrReader},
+    Error, Result,
+};
+
+/// Rows are flushed into a chunk once an entity has this many pending rows...
+const MAX_CHUNK_ROWS: usize = 1024;
+
+/// ... or this many pending bytes, to keep large images from piling up in memory.
+const MAX_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+/// The rows of one entity waiting to be written as a chunk.
+struct PendingChunk {
+    builder: ChunkBuilder,
+    num_rows: usize,
+    num_bytes: usize,
+}
+
+/// Writes the imported rows as chunks of one recording.
+struct RrdWriter<W: Write> {
+    encoder: Encoder<W>,
+    store_id: StoreId,
+    pending: BTreeMap<EntityPath, PendingChunk>,
+}
+
+impl<W: Write> RrdWriter<W> {
+    fn new(write: W, application_id: &str) -> Result<Self, ImportError> {
+        let mut encoder = Encoder::new(CrateVersion::LOCAL, EncodingOptions::COMPRESSED, write)
+            .map_err(ImportError::encode)?;
+
+        let store_id = StoreId::random(StoreKind::Recording);
+        encoder
+            .append(&LogMsg::SetStoreInfo(SetStoreInfo {
+                row_id: *RowId::new(),
+                info: StoreInfo {
+                    application_id: ApplicationId(application_id.to_string()),
+                    store_id: store_id.clone(),
+                    cloned_from: None,
+                    is_official_example: false,
+                    started: Time::now(),
+                    store_source: StoreSource::Other("rerun-query mcap import".to_string()),
+                    store_version: Some(CrateVersion::LOCAL),
+                },
+            }))
+            .map_err(ImportError::encode)?;
+
+        Ok(Self {
+            encoder,
+            store_id,
+            pending: BTreeMap::new(),
+        })
+    }
+
+    /// Add one row to an entity, flushing its pending rows if they grew too large.
+    fn log(
+        &mut self,
+        entity_path: EntityPath,
+        timepoint: &TimePoint,
+        archetype: &dyn AsComponents,
+        num_bytes: usize,
+    ) -> Result<(), ImportError> {
+        let pending = match self.pending.remove(&entity_path) {
+            Some(pending) => pending,
+            None => PendingChunk {
+                builder: Chunk::builder(entity_path.clone()),
+                num_rows: 0,
+                num_bytes: 0,
+            },
+        };
+        let pending = PendingChunk {
+            builder: pending
+                .builder
+                .with_archetype(RowId::new(), timepoint.clone(), archetype),
+            num_rows: pending.num_rows + 1,
+            num_bytes: pending.num_bytes + num_bytes,
+        };
+
+        if pending.num_rows >= MAX_CHUNK_ROWS || pending.num_bytes >= MAX_CHUNK_BYTES {
+            self.write_chunk(pending.builder)
+        } else {
+            self.pending.insert(entity_path, pending);
+            Ok(())
+        }
+    }
+
+    fn write_chunk(&mut self, builder: ChunkBuilder) -> Result<(), ImportError> {
+        let chunk = builder.build().map_err(ImportError::encode)?;
+        let arrow_msg = chunk.to_arrow_msg().map_err(ImportError::encode)?;
+        self.encoder
+            .append(&LogMsg::ArrowMsg(self.store_id.clone(), arrow_msg))
+            .map_err(ImportError::encode)?;
+        Ok(())
+    }
+
+    /// Write the remaining rows and return the underlying writer.
+    fn finish(mut self) -> Result<W, ImportError> {
+        for pending in std::mem::take(&mut self.pending).into_values() {
+            self.write_chunk(pending.builder)?;
+        }
+        Ok(self.encoder.into_inner())
+    }
+}
+
+/// Everything that can go wrong while importing.
+enum ImportError {
+    Mcap(McapError),
+    Decode { topic: String, error: CdrError },
+    Encode(String),
+}
+
+impl ImportError {
+    fn encode(e: impl std::fmt::Display) -> Self {
+        Self::Encode(e.to_string())
+    }
+}
+
+impl From<ImportError> for Error {
+    fn from(e: ImportError) -> Self {
+        match e {
+            ImportError::Mcap(McapError::Io(e)) => Error::Write(e),
+            ImportError::Mcap(e) => Error::Decode(format!("MCAP: {}", e)),
+            ImportError::Decode { topic, error } => {
+                Error::Decode(format!("message on {}: {}", topic, error))
+            }
+            ImportError::Encode(e) => Error::Encode(format!("RRD encoding error: {}", e)),
+        }
+    }
+}
+
+/// Import an MCAP file, e.g. a ROS 2 bag recorded with the MCAP storage plugin, into an RRD file.
+///
+/// CDR encoded ROS 2 messages of the supported types are logged at the entity path of their
+/// topic, on the `log_time` timeline taken from the MCAP log time:
+///
+/// * `sensor_msgs/msg/JointState` as tensors at `<topic>/position`, `<topic>/velocity` and
+///   `<topic>/effort`, with the joint names as static JSON text at `<topic>/names`.
+/// * `sensor_msgs/msg/Image` as `Image`, for the mono, RGB(A), BGR(A), float and YUV encodings.
+/// * `sensor_msgs/msg/CompressedImage` as `EncodedImage`.
+/// * `std_msgs/msg/Float64` as `Scalar`.
+/// * `rcl_interfaces/msg/Log` as `TextLog`.
+///
+/// Messages of other types are skipped. The imported recording has no `/action` entities, and the
+/// query functions use it as the only recording of the file. ROS 2 bags stored as SQLite (`.db3`)
+/// have to be converted first, e.g. with `ros2 bag convert`.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the MCAP file.
+/// * `out_path` - The path of the RRD file to write. Its directory is created if missing.
+/// * `topic_filter` - Only import the topics containing this string. Set to empty string to import all topics.
+/// * `application_id` - The application id of the recording. Defaults to the MCAP file name.
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of imported messages.
+pub fn import_mcap(
+    file_path: &str,
+    out_path: &str,
+    topic_filter: &str,
+    application_id: &str,
+) -> Result<usize> {
+    let bytes = fs::read(file_path).map_err(Error::Open)?;
+
+    let application_id = if application_id.is_empty() {
+        Path::new(file_path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "mcap".to_string())
+    } else {
+        application_id.to_string()
+    };
+
+    let out_path = Path::new(out_path);
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent).map_err(Error::Write)?;
+    }
+    let file = fs::File::create(out_path).map_err(Error::Write)?;
+
+    let mut writer = RrdWriter::new(BufWriter::new(file), &application_id)?;
+    let num_messages = import_messages(&bytes, topic_filter, &mut writer)?;
+    writer.finish()?.flush().map_err(Error::Write)?;
+
+    if num_messages == 0 {
+        return Err(Error::NoData(
+            "No supported messages found for the specified topic".to_string(),
+        ));
+    }
+
+    Ok(num_messages)
+}
+
+/// Log every supported message of an MCAP file, returning the number of imported messages.
+fn import_messages<W: Write>(
+    bytes: &[u8],
+    topic_filter: &str,
+    writer: &mut RrdWriter<W>,
+) -> Result<usize, ImportError> {
+    let timeline = Timeline::log_time();
+    let mut joint_names_logged: BTreeSet<String> = BTreeSet::new();
+    let mut skipped: BTreeSet<(String, String)> = BTreeSet::new();
+    let mut num_messages = 0;
+
+    for message in MessageStream::new(bytes).map_err(ImportError::Mcap)? {
+        let message = message.map_err(ImportError::Mcap)?;
+        let channel = &message.channel;
+        if !channel.topic.contains(topic_filter) {
+            continue;
+        }
+
+        let schema_name = channel
+            .schema
+            .as_ref()
+            .map(|schema| schema.name.as_str())
+            .unwrap_or_default();
+        if channel.message_encoding != "cdr" {
+            if skipped.insert((channel.topic.clone(), schema_name.to_string())) {
+                debug!(
+                    "Skipping {} messages on {}",
+                    channel.message_encoding, channel.topic
+                );
+            }
+            continue;
+        }
+
+        let topic = channel.topic.as_str();
+        let entity_path = EntityPath::from(topic);
+        let timepoint = TimePoint::default().with(timeline, message.log_time as i64);
+        let decode_error = |error| ImportError::Decode {
+            topic: topic.to_string(),
+            error,
+        };
+        let mut reader = CdrReader::new(&message.data).map_err(decode_error)?;
+
+        match schema_name {
+            "sensor_msgs/msg/JointState" => {
+                let joint_state = read_joint_state(&mut reader).map_err(decode_error)?;
+                if joint_names_logged.insert(topic.to_string()) {
+                    let names = json!({ "joints": joint_state.names }).to_string();
+                    writer.log(
+                        EntityPath::from(format!("{}/names", topic).as_str()),
+                        &TimePoint::default(),
+                        &TextDocument::new(names),
+                        0,
+                    )?;
+                }
+                for (field, values) in [
+                    ("position", joint_state.position),
+                    ("velocity", joint_state.velocity),
+                    ("effort", joint_state.effort),
+                ] {
+                    // Drivers leave the fields they do not report empty
+                    if values.is_empty() {
+                        continue;
+                    }
+                    let num_bytes = values.len() * 8;
+                    let tensor = Tensor::new(TensorData::new(
+                        vec![TensorDimension::unnamed(values.len() as u64)],
+                        TensorBuffer::F64(values.into()),
+                    ));
+                    writer.log(
+                        EntityPath::from(format!("{}/{}", topic, field).as_str()),
+                        &timepoint,
+                        &tensor,
+                        num_bytes,
+                    )?;
+                }
+            }
+            "sensor_msgs/msg/Image" => {
+                let Some(image) = read_image(&mut reader).map_err(decode_error)? else {
+                    if skipped.insert((topic.to_string(), schema_name.to_string())) {
+                        warn!("Skipping images on {} with an unsupported encoding", topic);
+                    }
+                    continue;
+                };
+                let num_bytes = image.num_bytes;
+                writer.log(entity_path, &timepoint, &image.image, num_bytes)?;
+            }
+            "sensor_msgs/msg/CompressedImage" => {
+                let (_stamp, _frame_id) = reader.read_header().map_err(decode_error)?;
+                let format = reader.read_string().map_err(decode_error)?;
+                let data = reader.read_bytes().map_err(decode_error)?;
+                let mut image = EncodedImage::new(data.to_vec());
+                // The format is e.g. "jpeg", "png" or "rgb8; jpeg compressed bgr8"
+                if format.contains("png") {
+                    image = image.with_media_type(MediaType::PNG);
+                } else if format.contains("jpeg") || format.contains("jpg") {
+                    image = image.with_media_type(MediaType::JPEG);
+                }
+                writer.log(entity_path, &timepoint, &image, data.len())?;
+            }
+            "std_msgs/msg/Float64" => {
+                let value = reader.read_f64().map_err(decode_error)?;
+                writer.log(entity_path, &timepoint, &Scalar::new(value), 8)?;
+            }
+            "rcl_interfaces/msg/Log" => {
+                let _stamp = reader.read_time().map_err(decode_error)?;
+                let level = reader.read_u8().map_err(decode_error)?;
+                let name = reader.read_string().map_err(decode_error)?;
+                let msg = reader.read_string().map_err(decode_error)?;
+                let num_bytes = msg.len();
+                let text_log =
+                    TextLog::new(format!("[{}] {}", name, msg)).with_level(log_level(level));
+                writer.log(entity_path, &timepoint, &text_log, num_bytes)?;
+            }
+            _ => {
+                if skipped.insert((topic.to_string(), schema_name.to_string())) {
+                    debug!("Skipping unsupported {} messages on {}", schema_name, topic);
+                }
+                continue;
+            }
+        }
+
+        num_messages += 1;
+    }
+
+    Ok(num_messages)
+}
+
+struct JointState {
+    names: Vec<String>,
+    position: Vec<f64>,
+    velocity: Vec<f64>,
+    effort: Vec<f64>,
+}
+
+fn read_joint_state(reader: &mut CdrReader<'_>) -> Result<JointState, CdrError> {
+    let (_stamp, _frame_id) = reader.read_header()?;
+    Ok(JointState {
+        names: reader.read_string_sequence()?,
+        position: reader.read_f64_sequence()?,
+        velocity: reader.read_f64_sequence()?,
+        effort: reader.read_f64_sequence()?,
+    })
+}
+
+struct DecodedImage {
+    image: Image,
+    num_bytes: usize,
+}
+
+/// Read a `sensor_msgs/msg/Image`, or `None` if its encoding has no Rerun equivalent.
+fn read_image(reader: &mut CdrReader<'_>) -> Result<Option<DecodedImage>, CdrError> {
+    let (_stamp, _frame_id) = reader.read_header()?;
+    let height = reader.read_u32()?;
+    let width = reader.read_u32()?;
+    let encoding = reader.read_string()?;
+    let is_bigendian = reader.read_u8()? != 0;
+    let step = reader.read_u32()? as usize;
+    let data = reader.read_bytes()?;
+
+    enum Layout {
+        Channels(ColorModel, ChannelDatatype),
+        Pixels(PixelFormat),
+    }
+
+    let (layout, bytes_per_pixel, swap_rb) = match encoding.as_str() {
+        "mono8" | "8UC1" => (
+            Layout::Channels(ColorModel::L, ChannelDatatype::U8),
+            1,
+            false,
+        ),
+        "mono16" | "16UC1" => (
+            Layout::Channels(ColorModel::L, ChannelDatatype::U16),
+            2,
+            false,
+        ),
+        "32FC1" => (
+            Layout::Channels(ColorModel::L, ChannelDatatype::F32),
+            4,
+            false,
+        ),
+        "rgb8" | "8UC3" => (
+            Layout::Channels(ColorModel::RGB, ChannelDatatype::U8),
+            3,
+            false,
+        ),
+        "bgr8" => (
+            Layout::Channels(ColorModel::RGB, ChannelDatatype::U8),
+            3,
+            true,
+        ),
+        "rgba8" | "8UC4" => (
+            Layout::Channels(ColorModel::RGBA, ChannelDatatype::U8),
+            4,
+            false,
+        ),
+        "bgra8" => (
+            Layout::Channels(ColorModel::RGBA, ChannelDatatype::U8),
+            4,
+            true,
+        ),
+        "rgb16" => (
+            Layout::Channels(ColorModel::RGB, ChannelDatatype::U16),
+            6,
+            false,
+        ),
+        "bgr16" => (
+            Layout::Channels(ColorModel::RGB, ChannelDatatype::U16),
+            6,
+            true,
+        ),
+        "rgba16" => (
+            Layout::Channels(ColorModel::RGBA, ChannelDatatype::U16),
+            8,
+            false,
+        ),
+        "bgra16" => (
+            Layout::Channels(ColorModel::RGBA, ChannelDatatype::U16),
+            8,
+            true,
+        ),
+        "yuyv" | "yuv422_yuy2" => (Layout::Pixels(PixelFormat::YUY2), 2, false),
+        "nv12" => {
+            // NV12 rows are one byte per pixel, followed by half as many chroma rows
+            let rows = (height as usize * 3).div_ceil(2);
+            let pixels = pack_rows(data, width as usize, rows, step);
+            let num_bytes = pixels.len();
+            return Ok(Some(DecodedImage {
+                image: Image::from_pixel_format([width, height], PixelFormat::NV12, pixels),
+                num_bytes,
+            }));
+        }
+        _ => return Ok(None),
+    };
+
+    let mut pixels = pack_rows(
+        data,
+        width as usize * bytes_per_pixel,
+        height as usize,
+        step,
+    );
+
+    // Rerun expects little endian channels
+    let channel_size = match &layout {
+        Layout::Channels(_, ChannelDatatype::U16) => 2,
+        Layout::Channels(_, ChannelDatatype::F32) => 4,
+        _ => 1,
+    };
+    if is_bigendian && channel_size > 1 {
+        for channel in pixels.chunks_exact_mut(channel_size) {
+            channel.reverse();
+        }
+    }
+
+    if swap_rb {
+        let channels = match &layout {
+            Layout::Channels(ColorModel::RGBA, _) => 4,
+            _ => 3,
+        };
+        for pixel in pixels.chunks_exact_mut(channels * channel_size) {
+            let (blue, rest) = pixel.split_at_mut(channel_size);
+            blue.swap_with_slice(&mut rest[channel_size..2 * channel_size]);
+        }
+    }
+
+    let num_bytes = pixels.len();
+    let image = match layout {
+        Layout::Channels(color_model, datatype) => {
+            Image::from_color_model_and_bytes(pixels, [width, height], color_model, datatype)
+        }
+        Layout::Pixels(pixel_format) => {
+            Image::from_pixel_format([width, height], pixel_format, pixels)
+        }
+    };
+
+    Ok(Some(DecodedImage { image, num_bytes }))
+}
+
+/// Drop the padding at the end of each row of an image buffer.
+fn pack_rows(data: &[u8], row_len: usize, num_rows: usize, step: usize) -> Vec<u8> {
+    if step <= row_len {
+        return data.to_vec();
+    }
+    data.chunks(step)
+        .take(num_rows)
+        .flat_map(|row| &row[..row_len.min(row.len())])
+        .copied()
+        .collect()
+}
+
+/// Map a `rcl_interfaces/msg/Log` level to a Rerun `TextLogLevel`.
+fn log_level(level: u8) -> TextLogLevel {
+    match level {
+        0..=10 => TextLogLevel::from(TextLogLevel::DEBUG),
+        11..=20 => TextLogLevel::from(TextLogLevel::INFO),
+        21..=30 => TextLogLevel::from(TextLogLevel::WARN),
+        31..=40 => TextLogLevel::from(TextLogLevel::ERROR),
+        _ => TextLogLevel::from(TextLogLevel::CRITICAL),
+    }
+}
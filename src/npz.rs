@@ -0,0 +1,380 @@
+//! Export of RRD data entities to NumPy `.npz` archives.
+//!
+//! An `.npz` file is a zip archive of `.npy` files, one per array, which `numpy.load` opens
+//! without any extra dependency.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Seek, Write},
+    path::Path,
+};
+
+use log::debug;
+use re_arrow2::{
+    array::{Array, PrimitiveArray},
+    datatypes::{PhysicalType, PrimitiveType},
+    types::{f16, NativeType},
+};
+use re_chunk::{Chunk, ComponentName};
+use zip::{result::ZipError, write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{columns, index::load_recording, query_data_chunks, Error, Result};
+
+/// An n-dimensional array in `.npy` layout: C order, little endian.
+pub(crate) struct NpyArray {
+    /// The NumPy type string, e.g. `<f8`.
+    pub descr: &'static str,
+    pub shape: Vec<usize>,
+    pub data: Vec<u8>,
+}
+
+impl NpyArray {
+    /// Serialize the array as a version 1.0 `.npy` file.
+    pub fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
+        let shape = match self.shape.as_slice() {
+            [len] => format!("({},)", len),
+            shape => format!(
+                "({})",
+                shape
+                    .iter()
+                    .map(|size| size.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            self.descr, shape
+        );
+        // Magic, version and header length take 10 bytes, the whole header is padded to 64 bytes
+        let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+        header.push_str(&" ".repeat(padding));
+        header.push('\n');
+
+        w.write_all(b"\x93NUMPY\x01\x00")?;
+        w.write_all(&(header.len() as u16).to_le_bytes())?;
+        w.write_all(header.as_bytes())?;
+        w.write_all(&self.data)
+    }
+}
+
+/// Export the data entities (scalar or tensor) of an RRD file to a NumPy `.npz` archive.
+///
+/// Every entity contributes one array per component and one per timeline, named after the entity
+/// path without its leading slash:
+///
+/// * `<entity>/Scalar` - a `float64` array of shape `(rows,)`.
+/// * `<entity>/TensorData` - an array of shape `(rows, *tensor_shape)` in the native type of the
+///   tensor (`float64`, `float32`, `uint8`, ...).
+/// * `<entity>/<timeline>` - an `int64` array of shape `(rows,)`, nanoseconds for temporal
+///   timelines.
+///
+/// Rows are kept in recording order, and only the timelines shared by every row of the entity are
+/// exported. Missing floating point values are written as NaN.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `out_path` - The path of the `.npz` file to write. Its directory is created if missing.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to export all data.
+/// * `compressed` - Deflate the arrays, like `numpy.savez_compressed`.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The names of the arrays in the archive.
+///
+/// # Example
+///
+/// ```python
+/// requery.export_npz("/path/to/file.rrd", "/path/to/file.npz")
+/// arrays = numpy.load("/path/to/file.npz")
+/// positions = arrays["action/joints/position/TensorData"]
+/// ```
+pub fn export_npz(
+    file_path: &str,
+    out_path: &str,
+    entity_path_filter: &str,
+    compressed: bool,
+) -> Result<Vec<String>> {
+    let recording = load_recording(file_path)?;
+    let rrd = recording.entity_db();
+
+    let mut chunks_per_entity: BTreeMap<String, Vec<Chunk>> = BTreeMap::new();
+    for chunk in query_data_chunks(rrd, "", entity_path_filter) {
+        chunks_per_entity
+            .entry(chunk.entity_path().to_string())
+            .or_default()
+            .push(chunk);
+    }
+
+    if chunks_per_entity.is_empty() {
+        return Err(
+            Error::NoData("No data found for the specified entity".to_string())
+                .with_path(file_path)
+                .with_entity(entity_path_filter),
+        );
+    }
+
+    let mut arrays: Vec<(String, NpyArray)> = Vec::new();
+    for (entity_path, chunks) in &chunks_per_entity {
+        debug!("Converting {} chunks of {}", chunks.len(), entity_path);
+        let prefix = entity_path.trim_start_matches('/');
+        for (name, array) in
+            entity_arrays(chunks).map_err(|e| Error::Invalid(format!("{}: {}", entity_path, e)))?
+        {
+            arrays.push((format!("{}/{}", prefix, name), array));
+        }
+    }
+
+    let out_path = Path::new(out_path);
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent).map_err(Error::Write)?;
+    }
+    let file = fs::File::create(out_path).map_err(Error::Write)?;
+    write_npz(file, &arrays, compressed).map_err(|e| match e {
+        ZipError::Io(e) => Error::Write(e),
+        e => Error::Encode(format!("NPZ export error: {}", e)),
+    })?;
+
+    Ok(arrays.into_iter().map(|(name, _)| name).collect())
+}
+
+fn write_npz<W: Write + Seek>(
+    w: W,
+    arrays: &[(String, NpyArray)],
+    compressed: bool,
+) -> Result<(), ZipError> {
+    let method = if compressed {
+        CompressionMethod::Deflated
+    } else {
+        CompressionMethod::Stored
+    };
+
+    let mut zip = ZipWriter::new(w);
+    for (name, array) in arrays {
+        let options = SimpleFileOptions::default()
+            .compression_method(method)
+            .large_file(array.data.len() >= u32::MAX as usize);
+        zip.start_file(format!("{}.npy", name), options)?;
+        array.write(&mut zip)?;
+    }
+    zip.finish()?.flush()?;
+
+    Ok(())
+}
+
+/// Convert the chunks of one entity into named arrays, timelines first.
+pub(crate) fn entity_arrays(chunks: &[Chunk]) -> Result<Vec<(String, NpyArray)>, String> {
+    let num_rows: usize = chunks.iter().map(|chunk| chunk.num_rows()).sum();
+    let mut arrays = Vec::new();
+
+    // Every array of the entity has one entry per row, so only shared timelines can be exported
+    let first = &chunks[0];
+    for timeline in first.timelines().keys() {
+        let mut data = Vec::with_capacity(num_rows * 8);
+        for chunk in chunks {
+            let Some(time_column) = chunk.timelines().get(timeline) else {
+                data.clear();
+                break;
+            };
+            for time in time_column.times_raw() {
+                data.extend_from_slice(&time.to_le_bytes());
+            }
+        }
+        if data.len() == num_rows * 8 {
+            let array = NpyArray {
+                descr: "<i8",
+                shape: vec![num_rows],
+                data,
+            };
+            arrays.push((timeline.name().to_string(), array));
+        }
+    }
+
+    let scalar = ComponentName::from("rerun.components.Scalar");
+    let tensor = ComponentName::from("rerun.components.TensorData");
+
+    if chunks
+        .iter()
+        .any(|chunk| chunk.components().contains_key(&scalar))
+    {
+        let mut data = Vec::with_capacity(num_rows * 8);
+        for chunk in chunks {
+            match chunk.components().get(&scalar) {
+                Some(list_array) => {
+                    for value in columns::scalar_column(list_array).iter() {
+                        let value = value.copied().unwrap_or(f64::NAN);
+                        data.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+                None => {
+                    for _ in 0..chunk.num_rows() {
+                        data.extend_from_slice(&f64::NAN.to_le_bytes());
+                    }
+                }
+            }
+        }
+        let array = NpyArray {
+            descr: "<f8",
+            shape: vec![num_rows],
+            data,
+        };
+        arrays.push((scalar.short_name().to_string(), array));
+    }
+
+    if chunks
+        .iter()
+        .any(|chunk| chunk.components().contains_key(&tensor))
+    {
+        arrays.push((
+            tensor.short_name().to_string(),
+            tensor_array(chunks, &tensor)?,
+        ));
+    }
+
+    Ok(arrays)
+}
+
+/// Stack the tensors of every row into one array of shape `(rows, *tensor_shape)`.
+fn tensor_array(chunks: &[Chunk], component_name: &ComponentName) -> Result<NpyArray, String> {
+    // The element type and shape, and the size in bytes of a row, once the first tensor is found
+    let mut layout: Option<(&'static str, Vec<usize>, usize)> = None;
+    let mut data = Vec::new();
+    let mut num_rows = 0;
+
+    // Missing rows can only be filled once the element type is known
+    let mut missing_rows_before_first = 0;
+
+    for chunk in chunks {
+        let list_array = chunk.components().get(component_name);
+        let tensors = list_array
+            .map(columns::tensor_column)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        // Chunks without the component or without any tensor only hold missing rows
+        let (Some(list_array), Some(tensors)) = (
+            list_array,
+            tensors.filter(|tensors| tensors.null_count() < tensors.len()),
+        ) else {
+            match &layout {
+                Some((descr, _, row_size)) => {
+                    let fill = missing_row(descr, *row_size)?;
+                    for _ in 0..chunk.num_rows() {
+                        data.extend_from_slice(&fill);
+                    }
+                }
+                None => missing_rows_before_first += chunk.num_rows(),
+            }
+            num_rows += chunk.num_rows();
+            continue;
+        };
+
+        let (chunk_descr, values) = native_bytes(tensors.values().as_ref())?;
+        let (descr, element_shape, row_size) = match &layout {
+            Some((descr, _, _)) if *descr != chunk_descr => {
+                return Err(format!(
+                    "Tensor type changes from {} to {}",
+                    descr, chunk_descr
+                ));
+            }
+            Some(layout) => layout,
+            None => {
+                let element_shape: Vec<usize> = columns::tensor_shape(list_array)
+                    .ok_or_else(|| format!("Cannot determine the shape of {}", component_name))?
+                    .iter()
+                    .map(|dimension| dimension.size as usize)
+                    .collect();
+                let row_size = element_shape.iter().product::<usize>() * item_size(chunk_descr);
+                layout.insert((chunk_descr, element_shape, row_size))
+            }
+        };
+        let item_size = item_size(descr);
+        let num_elements = row_size / item_size;
+
+        if missing_rows_before_first > 0 {
+            let fill = missing_row(descr, *row_size)?;
+            for _ in 0..missing_rows_before_first {
+                data.extend_from_slice(&fill);
+            }
+            missing_rows_before_first = 0;
+        }
+
+        for row in 0..tensors.len() {
+            let (start, end) = tensors.offsets().start_end(row);
+            if !tensors.is_valid(row) {
+                data.extend_from_slice(&missing_row(descr, *row_size)?);
+            } else if end - start != num_elements {
+                return Err(format!(
+                    "Tensor has {} elements at row {}, expected {} for the shape {:?}",
+                    end - start,
+                    num_rows + row,
+                    num_elements,
+                    element_shape
+                ));
+            } else {
+                data.extend_from_slice(&values[start * item_size..end * item_size]);
+            }
+        }
+        num_rows += tensors.len();
+    }
+
+    let Some((descr, element_shape, _)) = layout else {
+        return Err("No tensor found".to_string());
+    };
+
+    let mut shape = vec![num_rows];
+    shape.extend(element_shape);
+    Ok(NpyArray { descr, shape, data })
+}
+
+/// The size in bytes of one element of a NumPy type string like `<f8`.
+pub(crate) fn item_size(descr: &str) -> usize {
+    descr[2..]
+        .parse()
+        .expect("type strings end with the item size")
+}
+
+/// The bytes of a row without a value: NaN for floating point tensors, an error otherwise.
+fn missing_row(descr: &str, row_size: usize) -> Result<Vec<u8>, String> {
+    let nan = match descr {
+        "<f2" => f16::from_f32(f32::NAN).to_le_bytes().to_vec(),
+        "<f4" => f32::NAN.to_le_bytes().to_vec(),
+        "<f8" => f64::NAN.to_le_bytes().to_vec(),
+        _ => return Err(format!("Missing rows in a tensor of type {}", descr)),
+    };
+    Ok(nan.iter().copied().cycle().take(row_size).collect())
+}
+
+/// The NumPy type string and little endian bytes of a primitive array.
+fn native_bytes(values: &dyn Array) -> Result<(&'static str, Vec<u8>), String> {
+    fn le_bytes<T: NativeType>(values: &dyn Array) -> Vec<u8> {
+        let values = values
+            .as_any()
+            .downcast_ref::<PrimitiveArray<T>>()
+            .expect("the physical type matches the array type");
+        values
+            .values()
+            .iter()
+            .flat_map(|value| value.to_le_bytes().as_ref().to_vec())
+            .collect()
+    }
+
+    let PhysicalType::Primitive(primitive) = values.data_type().to_physical_type() else {
+        return Err(format!("Unsupported tensor type {:?}", values.data_type()));
+    };
+    match primitive {
+        PrimitiveType::Int8 => Ok(("|i1", le_bytes::<i8>(values))),
+        PrimitiveType::Int16 => Ok(("<i2", le_bytes::<i16>(values))),
+        PrimitiveType::Int32 => Ok(("<i4", le_bytes::<i32>(values))),
+        PrimitiveType::Int64 => Ok(("<i8", le_bytes::<i64>(values))),
+        PrimitiveType::UInt8 => Ok(("|u1", le_bytes::<u8>(values))),
+        PrimitiveType::UInt16 => Ok(("<u2", le_bytes::<u16>(values))),
+        PrimitiveType::UInt32 => Ok(("<u4", le_bytes::<u32>(values))),
+        PrimitiveType::UInt64 => Ok(("<u8", le_bytes::<u64>(values))),
+        PrimitiveType::Float16 => Ok(("<f2", le_bytes::<f16>(values))),
+        PrimitiveType::Float32 => Ok(("<f4", le_bytes::<f32>(values))),
+        PrimitiveType::Float64 => Ok(("<f8", le_bytes::<f64>(values))),
+        _ => Err(format!("Unsupported tensor type {:?}", values.data_type())),
+    }
+}
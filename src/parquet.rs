@@ -0,0 +1,141 @@
+//! Export of RRD data entities to Parquet files.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use log::debug;
+use re_arrow2::{
+    array::Array,
+    chunk::Chunk as ArrowChunk,
+    datatypes::Schema,
+    error::Error as ArrowError,
+    io::parquet::write::{
+        transverse, CompressionOptions, Encoding, FileWriter, RowGroupIterator, Version,
+        WriteOptions,
+    },
+};
+use re_chunk::Chunk;
+
+use crate::{columns, entity_file_path, index::load_recording, query_data_chunks, Error, Result};
+
+/// Export the data entities (scalar or tensor) of an RRD file to Parquet, one file per entity.
+///
+/// Each file mirrors the entity path inside `out_dir`, e.g. `/action/joints/position` is written
+/// to `<out_dir>/action/joints/position.parquet`. Every timeline becomes a column (timestamps for
+/// temporal timelines, `Int64` for sequences), and every component becomes a typed column named
+/// after the component: `Scalar` as `Float64`, `TensorData` as a list of the tensor's native type.
+/// Each chunk of the recording is written as its own row group.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `out_dir` - The directory to write the Parquet files to. Created if missing.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to export all data.
+/// * `compression` - The compression codec to use: "zstd", "snappy" or "none".
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The paths of the written Parquet files.
+pub fn export_parquet(
+    file_path: &str,
+    out_dir: &str,
+    entity_path_filter: &str,
+    compression: &str,
+) -> Result<Vec<String>> {
+    let compression = parse_compression(compression)?;
+
+    let recording = load_recording(file_path)?;
+    let rrd = recording.entity_db();
+
+    let mut chunks_per_entity: BTreeMap<String, Vec<Chunk>> = BTreeMap::new();
+    for chunk in query_data_chunks(rrd, "", entity_path_filter) {
+        chunks_per_entity
+            .entry(chunk.entity_path().to_string())
+            .or_default()
+            .push(chunk);
+    }
+
+    if chunks_per_entity.is_empty() {
+        return Err(
+            Error::NoData("No data found for the specified entity".to_string())
+                .with_path(file_path)
+                .with_entity(entity_path_filter),
+        );
+    }
+
+    let mut written = Vec::new();
+    for (entity_path, chunks) in &chunks_per_entity {
+        let path = entity_file_path(Path::new(out_dir), entity_path, "parquet")
+            .map_err(|e| e.with_path(file_path))?;
+        debug!(
+            "Writing {} chunks of {} to {:?}",
+            chunks.len(),
+            entity_path,
+            path
+        );
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(Error::Write)?;
+        }
+        write_parquet(&path, chunks, compression).map_err(|e| match e {
+            ArrowError::Io(e) => Error::Write(e),
+            e => Error::Encode(format!("Parquet export error: {}", e)),
+        })?;
+
+        written.push(path.to_string_lossy().into_owned());
+    }
+
+    Ok(written)
+}
+
+fn parse_compression(compression: &str) -> Result<CompressionOptions> {
+    match compression {
+        "zstd" => Ok(CompressionOptions::Zstd(None)),
+        "snappy" => Ok(CompressionOptions::Snappy),
+        "none" | "" => Ok(CompressionOptions::Uncompressed),
+        _ => Err(Error::Invalid(format!(
+            "Unsupported compression: {}. Use \"zstd\", \"snappy\" or \"none\"",
+            compression
+        ))),
+    }
+}
+
+/// Write the chunks of a single entity to one Parquet file, one row group per chunk.
+fn write_parquet(
+    path: &Path,
+    chunks: &[Chunk],
+    compression: CompressionOptions,
+) -> Result<(), ArrowError> {
+    let (schema, row_groups) = columns::entity_table(chunks)?;
+    write_row_groups(path, schema, row_groups.into_iter().map(Ok), compression)
+}
+
+/// Write an Arrow schema and its row groups to one Parquet file.
+pub(crate) fn write_row_groups(
+    path: &Path,
+    schema: Schema,
+    row_groups: impl Iterator<Item = Result<ArrowChunk<Box<dyn Array>>, ArrowError>>,
+    compression: CompressionOptions,
+) -> Result<(), ArrowError> {
+    let options = WriteOptions {
+        write_statistics: true,
+        compression,
+        version: Version::V2,
+        data_pagesize_limit: None,
+    };
+    let encodings: Vec<Vec<Encoding>> = schema
+        .fields
+        .iter()
+        .map(|field| transverse(&field.data_type, |_| Encoding::Plain))
+        .collect();
+
+    let row_groups = RowGroupIterator::try_new(row_groups, &schema, options, encodings)?;
+
+    let file = fs::File::create(path)?;
+    let mut writer = FileWriter::try_new(file, schema, options)?;
+    for group in row_groups {
+        writer.write(group?)?;
+    }
+    writer.end(None)?;
+
+    Ok(())
+}
@@ -0,0 +1,918 @@
+//! The Python bindings of the crate, built with the `python` feature.
+//!
+//! The bindings are a thin layer over the Rust API: they convert the arguments, call the
+//! functions of the crate and turn the crate's [`Error`] into Python exceptions. Only the
+//! queries convert their results to Python objects (NumPy arrays and Python classes).
+//!
+//! Every [`Error`] variant is raised as a subclass of `requery.RequeryError`, with the
+//! [`ErrorContext`](crate::ErrorContext) as attributes. The subclasses also derive from the
+//! `OSError` or `ValueError` the bindings raised before, so existing handlers keep working.
+//!
+//! Reading and converting recordings runs without the GIL, so that other Python threads keep
+//! running meanwhile, and the GIL is only held to create the Python objects.
+
+use std::collections::{BTreeMap, HashMap};
+
+use numpy::{PyArray1, PyArray2};
+use pyo3::{
+    prelude::*,
+    sync::GILOnceCell,
+    types::{IntoPyDict, PyDict, PyList, PyTuple, PyType},
+};
+use rayon::prelude::*;
+use re_arrow2::{
+    array::{Array, PrimitiveArray},
+    compute::cast::{cast, CastOptions},
+    datatypes::DataType,
+    types::NativeType,
+};
+
+use crate::{
+    found_data_chunks, load_data_chunks, to_data_chunk, ChunkData, DataChunk, DataQuery, Error,
+    Result,
+};
+
+/// The exception classes of the [`Error`] variants: their name, the built-in exception they also
+/// derive from, and their documentation.
+const EXCEPTION_CLASSES: [(&str, &str, &str); 8] = [
+    (
+        "RrdOpenError",
+        "OSError",
+        "An input file could not be opened or read.",
+    ),
+    (
+        "RrdWriteError",
+        "OSError",
+        "An output file could not be written.",
+    ),
+    (
+        "RrdDecodeError",
+        "ValueError",
+        "An input file could not be decoded.",
+    ),
+    (
+        "RrdEncodeError",
+        "ValueError",
+        "The data could not be encoded in the output format.",
+    ),
+    (
+        "StoreNotFoundError",
+        "ValueError",
+        "The recording store to query was not found.",
+    ),
+    (
+        "NoMatchingDataError",
+        "ValueError",
+        "No data matches the filters.",
+    ),
+    (
+        "UnsupportedComponentError",
+        "ValueError",
+        "A component has a type that the operation doesn't support.",
+    ),
+    (
+        "InvalidArgumentError",
+        "ValueError",
+        "An argument is invalid, or the data doesn't allow the operation.",
+    ),
+];
+
+/// The attributes of every `RequeryError`, from the [`ErrorContext`](crate::ErrorContext).
+const CONTEXT_ATTRIBUTES: [&str; 4] = ["path", "entity", "component", "message_index"];
+
+static EXCEPTIONS: GILOnceCell<BTreeMap<&'static str, Py<PyType>>> = GILOnceCell::new();
+
+/// The `RequeryError` class and its subclasses by name, created with the module.
+fn exception_classes(py: Python<'_>) -> PyResult<&BTreeMap<&'static str, Py<PyType>>> {
+    EXCEPTIONS.get_or_try_init(py, || {
+        let builtins = py.import_bound("builtins")?;
+        let new_class =
+            |name: &str, bases: Bound<'_, PyTuple>, doc: &str| -> PyResult<Py<PyType>> {
+                let namespace = PyDict::new_bound(py);
+                namespace.set_item("__module__", "requery")?;
+                namespace.set_item("__doc__", doc)?;
+                if name == "RequeryError" {
+                    for attribute in CONTEXT_ATTRIBUTES {
+                        namespace.set_item(attribute, py.None())?;
+                    }
+                }
+                Ok(builtins
+                    .getattr("type")?
+                    .call1((name, bases, namespace))?
+                    .downcast_into::<PyType>()?
+                    .unbind())
+            };
+
+        let base = new_class(
+            "RequeryError",
+            PyTuple::new_bound(py, [builtins.getattr("Exception")?]),
+            "The base class of the errors of requery.\n\n\
+             `path`, `entity`, `component` and `message_index` tell where the error happened, or\n\
+             are `None` if unknown.",
+        )?;
+        let mut classes = BTreeMap::new();
+        for (name, builtin, doc) in EXCEPTION_CLASSES {
+            let bases = PyTuple::new_bound(
+                py,
+                [base.bind(py).clone().into_any(), builtins.getattr(builtin)?],
+            );
+            classes.insert(name, new_class(name, bases, doc)?);
+        }
+        classes.insert("RequeryError", base);
+        Ok(classes)
+    })
+}
+
+fn exception_name(e: &Error) -> &'static str {
+    match e.kind() {
+        Error::Open(_) => "RrdOpenError",
+        Error::Write(_) => "RrdWriteError",
+        Error::Decode(_) => "RrdDecodeError",
+        Error::Encode(_) => "RrdEncodeError",
+        Error::StoreNotFound(_) => "StoreNotFoundError",
+        Error::NoData(_) => "NoMatchingDataError",
+        Error::UnsupportedComponent(_) => "UnsupportedComponentError",
+        Error::Invalid(_) | Error::WithContext(..) => "InvalidArgumentError",
+    }
+}
+
+fn to_exception(py: Python<'_>, e: &Error) -> PyResult<PyErr> {
+    let exception = exception_classes(py)?[exception_name(e)]
+        .bind(py)
+        .call1((e.to_string(),))?;
+    if let Some(context) = e.context() {
+        exception.setattr("path", &context.path)?;
+        exception.setattr("entity", &context.entity)?;
+        exception.setattr("component", &context.component)?;
+        exception.setattr("message_index", context.message_index)?;
+    }
+    Ok(PyErr::from_value_bound(exception))
+}
+
+impl From<Error> for PyErr {
+    fn from(e: Error) -> Self {
+        Python::with_gil(|py| to_exception(py, &e).unwrap_or_else(|err| err))
+    }
+}
+
+#[pyclass(name = "DataChunk")]
+pub struct PyDataChunk {
+    entity_path: String,
+    timelines: HashMap<String, Py<PyArray1<i64>>>,
+    data: PyData,
+}
+
+#[pyclass(name = "Data")]
+pub enum PyData {
+    Tensor { data: Py<PyArray2<Py<PyAny>>> },
+    Scalar { data: Py<PyArray1<Py<PyAny>>> },
+}
+
+#[pymethods]
+impl PyDataChunk {
+    #[new]
+    pub fn new(
+        py: Python,
+        entity_path: String,
+        timelines: HashMap<String, Py<PyArray1<i64>>>,
+        data: PyObject, // Accept PyObject and determine if it's tensor or scalar data
+    ) -> PyResult<Self> {
+        // Determine if the provided data is a Tensor or Scalar
+        let data_enum = if let Ok(tensor_data) = data.extract::<Py<PyArray2<Py<PyAny>>>>(py) {
+            PyData::Tensor { data: tensor_data }
+        } else if let Ok(scalar_data) = data.extract::<Py<PyArray1<Py<PyAny>>>>(py) {
+            PyData::Scalar { data: scalar_data }
+        } else {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Provided data is neither Tensor nor Scalar",
+            ));
+        };
+
+        Ok(PyDataChunk {
+            entity_path,
+            timelines,
+            data: data_enum,
+        })
+    }
+
+    #[getter]
+    pub fn entity_path(&self) -> &str {
+        &self.entity_path
+    }
+
+    #[getter]
+    pub fn timelines(&self, py: Python) -> Py<PyDict> {
+        let dict_items: Vec<(&str, Py<PyArray1<i64>>)> = self
+            .timelines
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.clone()))
+            .collect();
+
+        dict_items.into_py_dict_bound(py).unbind()
+    }
+
+    #[getter]
+    pub fn data(&self, py: Python) -> PyObject {
+        match &self.data {
+            PyData::Tensor { data } => data.clone_ref(py).into(),
+            PyData::Scalar { data } => data.clone_ref(py).into(),
+        }
+    }
+}
+
+impl Default for PyDataChunk {
+    fn default() -> Self {
+        Python::with_gil(|py| PyDataChunk {
+            entity_path: String::new(),
+            timelines: HashMap::new(),
+            data: PyData::Tensor {
+                data: PyArray2::zeros_bound(py, (0, 0), false).into(),
+            },
+        })
+    }
+}
+
+impl PyDataChunk {
+    /// Convert a [`DataChunk`] of the Rust API to Python objects.
+    ///
+    /// The tensor data is a `(rows, 1)` array holding the list of the tensor elements of every
+    /// row, or `None` for rows without a tensor, and the scalar data an array of the scalar of
+    /// every row, or `None`.
+    pub(crate) fn from_data_chunk(py: Python<'_>, chunk: DataChunk) -> PyResult<Py<Self>> {
+        let timelines = chunk
+            .timelines
+            .into_iter()
+            .map(|(name, times)| (name, PyArray1::from_vec_bound(py, times).unbind()))
+            .collect();
+
+        let data = match &chunk.data {
+            ChunkData::Tensor(rows) => {
+                let rows = (0..rows.len())
+                    .map(|i| match rows.is_valid(i) {
+                        true => tensor_elements(py, rows.value(i).as_ref()).map(|row| vec![row]),
+                        false => Ok(vec![py.None()]),
+                    })
+                    .collect::<Result<Vec<_>>>()
+                    .map_err(|e| {
+                        e.with_entity(&chunk.entity_path)
+                            .with_component("rerun.components.TensorData")
+                    })?;
+                PyData::Tensor {
+                    data: PyArray2::from_vec2_bound(py, &rows)?.unbind(),
+                }
+            }
+            ChunkData::Scalar(values) => {
+                let values: Vec<PyObject> = values
+                    .iter()
+                    .map(|value| value.copied().into_py(py))
+                    .collect();
+                PyData::Scalar {
+                    data: PyArray1::from_vec_bound(py, values).unbind(),
+                }
+            }
+        };
+
+        Py::new(
+            py,
+            PyDataChunk {
+                entity_path: chunk.entity_path,
+                timelines,
+                data,
+            },
+        )
+    }
+}
+
+/// The elements of a tensor, in the native type of its buffer, as a Python list.
+fn tensor_elements(py: Python<'_>, elements: &dyn Array) -> Result<PyObject> {
+    fn to_list<T: NativeType + IntoPy<PyObject>>(py: Python<'_>, elements: &dyn Array) -> PyObject {
+        let elements = elements
+            .as_any()
+            .downcast_ref::<PrimitiveArray<T>>()
+            .expect("an array of the type of its data type");
+        PyList::new_bound(
+            py,
+            elements.iter().map(|element| element.copied().into_py(py)),
+        )
+        .into()
+    }
+
+    Ok(match elements.data_type() {
+        DataType::Int8 => to_list::<i8>(py, elements),
+        DataType::Int16 => to_list::<i16>(py, elements),
+        DataType::Int32 => to_list::<i32>(py, elements),
+        DataType::Int64 => to_list::<i64>(py, elements),
+        DataType::UInt8 => to_list::<u8>(py, elements),
+        DataType::UInt16 => to_list::<u16>(py, elements),
+        DataType::UInt32 => to_list::<u32>(py, elements),
+        DataType::UInt64 => to_list::<u64>(py, elements),
+        DataType::Float16 => {
+            let elements = cast(elements, &DataType::Float32, CastOptions::default())
+                .map_err(|e| Error::UnsupportedComponent(e.to_string()))?;
+            to_list::<f32>(py, elements.as_ref())
+        }
+        DataType::Float32 => to_list::<f32>(py, elements),
+        DataType::Float64 => to_list::<f64>(py, elements),
+        data_type => {
+            return Err(Error::UnsupportedComponent(format!(
+                "Unsupported data type: {:?}",
+                data_type
+            )))
+        }
+    })
+}
+
+/// Retrieve specific data (scalar or tensor) for an entity in a specific RRD file.
+/// Set entity_path to "" will return all the data.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to return all data.
+/// * `data_type_filter` - A string slice that holds the data type to filter. Set to "scalar" or "tensor" to filter by data type.
+/// * `time_range` - The `(start, end)` times to keep on `timeline`, see [`crate::query_data_entities_in_range`].
+/// * `timeline` - The timeline of `time_range`.
+///
+/// # Returns
+///
+/// * `PyResult<Py<PyList>>` - A list of ActionChunk objects.
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    data_type_filter = "",
+    entity_path_filter = "",
+    time_range = None,
+    timeline = "log_time"
+))]
+pub fn query_data_entities(
+    py: Python<'_>,
+    file_path: &str,
+    data_type_filter: &str,   // "scalar" or "tensor", or "" for both
+    entity_path_filter: &str, // "" for all entities
+    time_range: Option<(i64, i64)>,
+    timeline: &str,
+) -> PyResult<Py<PyList>> {
+    let data_chunks = py.allow_threads(|| {
+        query_data_chunks_parallel(
+            file_path,
+            data_type_filter,
+            entity_path_filter,
+            time_range,
+            timeline,
+        )
+    })?;
+
+    to_data_chunk_list(py, data_chunks)
+}
+
+/// [`crate::query_data_entities_in_range`], converting the chunks of the file in parallel, which
+/// leaves only the creation of the Python objects to do with the GIL.
+pub(crate) fn query_data_chunks_parallel(
+    file_path: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    time_range: Option<(i64, i64)>,
+    timeline: &str,
+) -> Result<Vec<DataChunk>> {
+    let chunks = load_data_chunks(
+        file_path,
+        data_type_filter,
+        entity_path_filter,
+        time_range,
+        timeline,
+    )?;
+    found_data_chunks(
+        file_path,
+        entity_path_filter,
+        chunks.par_iter().map(to_data_chunk).collect(),
+    )
+}
+
+/// The `DataChunk` objects of the data chunks of a query.
+pub(crate) fn to_data_chunk_list(
+    py: Python<'_>,
+    data_chunks: Vec<DataChunk>,
+) -> PyResult<Py<PyList>> {
+    let data_chunks = data_chunks
+        .into_iter()
+        .map(|data_chunk| PyDataChunk::from_data_chunk(py, data_chunk))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(PyList::new_bound(py, &data_chunks).unbind())
+}
+
+/// Retrieve specific data (scalar or tensor) from many RRD files concurrently.
+///
+/// See [`crate::query_data_entities_batch`].
+///
+/// # Returns
+///
+/// * `PyResult<Py<PyList>>` - For every file, in order, a list of DataChunk objects or the
+///   exception raised by `query_data_entities` for the file.
+#[pyfunction]
+#[pyo3(signature = (
+    file_paths,
+    data_type_filter = "",
+    entity_path_filter = "",
+    time_range = None,
+    timeline = "log_time",
+    num_threads = 0
+))]
+pub fn query_data_entities_batch(
+    py: Python<'_>,
+    file_paths: Vec<String>,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    time_range: Option<(i64, i64)>,
+    timeline: &str,
+    num_threads: usize,
+) -> PyResult<Py<PyList>> {
+    let query = DataQuery {
+        data_type_filter: data_type_filter.to_string(),
+        entity_path_filter: entity_path_filter.to_string(),
+        time_range,
+        timeline: timeline.to_string(),
+    };
+    let results =
+        py.allow_threads(|| crate::query_data_entities_batch(&file_paths, &query, num_threads))?;
+
+    Ok(to_batch_list(py, results))
+}
+
+/// The `DataChunk` lists of the files of a batch, or the exceptions of the files that failed.
+pub(crate) fn to_batch_list(py: Python<'_>, results: Vec<Result<Vec<DataChunk>>>) -> Py<PyList> {
+    let results: Vec<PyObject> = results
+        .into_iter()
+        .map(|data_chunks| {
+            match data_chunks
+                .map_err(PyErr::from)
+                .and_then(|data_chunks| to_data_chunk_list(py, data_chunks))
+            {
+                Ok(data_chunks) => data_chunks.into_py(py),
+                Err(e) => e.into_value(py).into_py(py),
+            }
+        })
+        .collect();
+    PyList::new_bound(py, results).unbind()
+}
+
+#[pyclass(name = "MetaChunk")]
+/// A class representing a text chunk extracted from the RRD file.
+///
+/// # Fields
+///
+/// * `entity_path` - The path of the entity associated with this chunk.
+/// * `media_type` - The media type of the metadata, such as text/plain.
+/// * `text` - The metadata.
+///
+/// This class is subject to change in the future, as data types are being extended.
+pub enum PyMetaChunk {
+    Text {
+        entity_path: String,
+        media_type: String,
+        text: String,
+    },
+}
+
+// Implement the conversion to Py<PyMetaChunk>
+impl IntoPy<Py<PyMetaChunk>> for PyMetaChunk {
+    fn into_py(self, py: Python) -> Py<PyMetaChunk> {
+        Py::new(py, self).unwrap() // Adjust error handling as needed
+    }
+}
+#[pymethods]
+impl PyMetaChunk {
+    #[new]
+    pub fn new(entity_path: String, media_type: String, text: String) -> Self {
+        PyMetaChunk::Text {
+            entity_path,
+            media_type,
+            text,
+        }
+    }
+
+    #[getter]
+    pub fn entity_path(&self) -> &str {
+        let PyMetaChunk::Text { entity_path, .. } = self;
+        entity_path
+    }
+
+    #[getter]
+    pub fn media_type(&self) -> &str {
+        let PyMetaChunk::Text { media_type, .. } = self;
+        media_type
+    }
+
+    #[getter]
+    pub fn text(&self) -> &str {
+        let PyMetaChunk::Text { text, .. } = self;
+        text
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        let PyMetaChunk::Text {
+            entity_path,
+            media_type,
+            text,
+        } = self;
+
+        Ok(format!(
+            "<MetaChunk(entity_path='{}', media_type='{}', text='{}')>",
+            entity_path, media_type, text
+        ))
+    }
+}
+
+/// Retrieve specific metadata for an entity in a specific RRD file.
+/// Set entity_path to "" will return all the data.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `entity_path` - A string slice that holds the specific entity path to filter. Set to empty string to return all data.
+///
+/// # Returns
+///
+/// * `PyResult<Py<PyList>>` - A list of MetaChunk objects.
+#[pyfunction]
+#[pyo3(text_signature = "(file_path: str, entity_path: str) -> List[MetaChunk]")]
+pub fn query_meta_entities(
+    py: Python<'_>,
+    file_path: &str,
+    entity_path: &str,
+) -> PyResult<Py<PyList>> {
+    let meta_chunks = py
+        .allow_threads(|| crate::query_meta_entities(file_path, entity_path))?
+        .into_iter()
+        .map(|chunk| {
+            Py::new(
+                py,
+                PyMetaChunk::Text {
+                    entity_path: chunk.entity_path,
+                    media_type: chunk.media_type,
+                    text: chunk.text,
+                },
+            )
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    Ok(PyList::new_bound(py, &meta_chunks).unbind())
+}
+
+/// Retrieve the list of all entity paths from a specific RRD file.
+///
+/// See [`crate::list_entity_paths`].
+#[pyfunction]
+pub fn list_entity_paths(py: Python<'_>, file_path: &str) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| crate::list_entity_paths(file_path))?)
+}
+
+/// See [`crate::export_parquet`].
+#[pyfunction]
+#[pyo3(signature = (file_path, out_dir, entity_path_filter = "", compression = "zstd"))]
+pub fn export_parquet(
+    py: Python<'_>,
+    file_path: &str,
+    out_dir: &str,
+    entity_path_filter: &str,
+    compression: &str,
+) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| {
+        crate::export_parquet(file_path, out_dir, entity_path_filter, compression)
+    })?)
+}
+
+/// See [`crate::export_csv`].
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    out_dir,
+    data_type_filter = "",
+    entity_path_filter = "",
+    delimiter = ",",
+    time_format = "raw"
+))]
+pub fn export_csv(
+    py: Python<'_>,
+    file_path: &str,
+    out_dir: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    delimiter: &str,
+    time_format: &str,
+) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| {
+        crate::export_csv(
+            file_path,
+            out_dir,
+            data_type_filter,
+            entity_path_filter,
+            delimiter,
+            time_format,
+        )
+    })?)
+}
+
+/// See [`crate::export_lerobot`].
+#[pyfunction]
+pub fn export_lerobot(
+    py: Python<'_>,
+    file_paths: Vec<String>,
+    out_dir: &str,
+    config: &str,
+) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| crate::export_lerobot(file_paths, out_dir, config))?)
+}
+
+/// See [`crate::export_mcap`].
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    out_path,
+    entity_path_filter = "",
+    timeline = "log_time",
+    compression = "zstd"
+))]
+pub fn export_mcap(
+    py: Python<'_>,
+    file_path: &str,
+    out_path: &str,
+    entity_path_filter: &str,
+    timeline: &str,
+    compression: &str,
+) -> PyResult<usize> {
+    Ok(py.allow_threads(|| {
+        crate::export_mcap(
+            file_path,
+            out_path,
+            entity_path_filter,
+            timeline,
+            compression,
+        )
+    })?)
+}
+
+/// See [`crate::import_mcap`].
+#[pyfunction]
+#[pyo3(signature = (file_path, out_path, topic_filter = "", application_id = ""))]
+pub fn import_mcap(
+    py: Python<'_>,
+    file_path: &str,
+    out_path: &str,
+    topic_filter: &str,
+    application_id: &str,
+) -> PyResult<usize> {
+    Ok(py.allow_threads(|| crate::import_mcap(file_path, out_path, topic_filter, application_id))?)
+}
+
+/// See [`crate::export_npz`].
+#[pyfunction]
+#[pyo3(signature = (file_path, out_path, entity_path_filter = "", compressed = false))]
+pub fn export_npz(
+    py: Python<'_>,
+    file_path: &str,
+    out_path: &str,
+    entity_path_filter: &str,
+    compressed: bool,
+) -> PyResult<Vec<String>> {
+    Ok(py
+        .allow_threads(|| crate::export_npz(file_path, out_path, entity_path_filter, compressed))?)
+}
+
+/// See [`crate::export_zarr`].
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    out_dir,
+    entity_path_filter = "",
+    zarr_format = 2,
+    chunk_rows = 1024,
+    compression = "zstd"
+))]
+pub fn export_zarr(
+    py: Python<'_>,
+    file_path: &str,
+    out_dir: &str,
+    entity_path_filter: &str,
+    zarr_format: u8,
+    chunk_rows: usize,
+    compression: &str,
+) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| {
+        crate::export_zarr(
+            file_path,
+            out_dir,
+            entity_path_filter,
+            zarr_format,
+            chunk_rows,
+            compression,
+        )
+    })?)
+}
+
+/// See [`crate::export_tfrecord`].
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    out_dir,
+    data_type_filter = "",
+    entity_path_filter = "",
+    fps = 30,
+    timeline = "log_time",
+    samples_per_shard = 1000
+))]
+#[allow(clippy::too_many_arguments)] // The keyword arguments of the Python function
+pub fn export_tfrecord(
+    py: Python<'_>,
+    file_path: &str,
+    out_dir: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    fps: u32,
+    timeline: &str,
+    samples_per_shard: usize,
+) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| {
+        crate::export_tfrecord(
+            file_path,
+            out_dir,
+            data_type_filter,
+            entity_path_filter,
+            fps,
+            timeline,
+            samples_per_shard,
+        )
+    })?)
+}
+
+/// See [`crate::export_webdataset`].
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    out_dir,
+    data_type_filter = "",
+    entity_path_filter = "",
+    fps = 30,
+    timeline = "log_time",
+    samples_per_shard = 1000
+))]
+#[allow(clippy::too_many_arguments)] // The keyword arguments of the Python function
+pub fn export_webdataset(
+    py: Python<'_>,
+    file_path: &str,
+    out_dir: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    fps: u32,
+    timeline: &str,
+    samples_per_shard: usize,
+) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| {
+        crate::export_webdataset(
+            file_path,
+            out_dir,
+            data_type_filter,
+            entity_path_filter,
+            fps,
+            timeline,
+            samples_per_shard,
+        )
+    })?)
+}
+
+/// See [`crate::build_index`].
+#[pyfunction]
+pub fn build_index(py: Python<'_>, file_path: &str) -> PyResult<String> {
+    Ok(py.allow_threads(|| crate::build_index(file_path))?)
+}
+
+/// See [`crate::clear_cache`].
+#[pyfunction]
+pub fn clear_cache() {
+    crate::clear_cache()
+}
+
+/// See [`crate::set_cache_budget`].
+#[pyfunction]
+pub fn set_cache_budget(max_bytes: u64) {
+    crate::set_cache_budget(max_bytes)
+}
+
+/// See [`crate::write_filtered_rrd`].
+#[pyfunction]
+#[pyo3(signature = (
+    src,
+    dst,
+    entity_filter = "",
+    time_range = None,
+    store_selector = "",
+    timeline = "log_time",
+    include_blueprint = true
+))]
+#[allow(clippy::too_many_arguments)] // The keyword arguments of the Python function
+pub fn write_filtered_rrd(
+    py: Python<'_>,
+    src: &str,
+    dst: &str,
+    entity_filter: &str,
+    time_range: Option<(i64, i64)>,
+    store_selector: &str,
+    timeline: &str,
+    include_blueprint: bool,
+) -> PyResult<usize> {
+    Ok(py.allow_threads(|| {
+        crate::write_filtered_rrd(
+            src,
+            dst,
+            entity_filter,
+            time_range,
+            store_selector,
+            timeline,
+            include_blueprint,
+        )
+    })?)
+}
+
+/// See [`crate::merge_rrds`].
+#[pyfunction]
+#[pyo3(signature = (file_paths, out_path, store_id = "", offset_timelines = Vec::new()))]
+pub fn merge_rrds(
+    py: Python<'_>,
+    file_paths: Vec<String>,
+    out_path: &str,
+    store_id: &str,
+    offset_timelines: Vec<String>,
+) -> PyResult<usize> {
+    Ok(py.allow_threads(|| crate::merge_rrds(file_paths, out_path, store_id, offset_timelines))?)
+}
+
+/// See [`crate::split_rrd`].
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    out_dir,
+    marker_entity = "",
+    gap_threshold = None,
+    timeline = "log_time"
+))]
+pub fn split_rrd(
+    py: Python<'_>,
+    file_path: &str,
+    out_dir: &str,
+    marker_entity: &str,
+    gap_threshold: Option<i64>,
+    timeline: &str,
+) -> PyResult<Vec<String>> {
+    Ok(py.allow_threads(|| {
+        crate::split_rrd(file_path, out_dir, marker_entity, gap_threshold, timeline)
+    })?)
+}
+
+/// See [`crate::sql::sql_ipc`]. `requery.sql` reads the stream into a `pyarrow.Table`.
+#[cfg(feature = "sql")]
+#[pyfunction]
+pub fn sql_ipc<'py>(
+    py: Python<'py>,
+    file_path: &str,
+    query: &str,
+) -> PyResult<Bound<'py, pyo3::types::PyBytes>> {
+    let stream = py.allow_threads(|| crate::sql::sql_ipc(file_path, query))?;
+    Ok(pyo3::types::PyBytes::new_bound(py, &stream))
+}
+
+/// A Python module implemented in Rust.
+/// This module is a plugin for the Python package `rerun-query`.
+#[pymodule]
+fn requery(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    env_logger::init();
+
+    for (name, class) in exception_classes(py)? {
+        m.add(*name, class.bind(py))?;
+    }
+
+    m.add_function(wrap_pyfunction_bound!(query_data_entities, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(query_data_entities_batch, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(query_meta_entities, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(list_entity_paths, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(build_index, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(clear_cache, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(set_cache_budget, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(export_parquet, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(export_csv, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(export_lerobot, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(export_mcap, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(import_mcap, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(export_npz, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(export_zarr, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(export_tfrecord, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(export_webdataset, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(write_filtered_rrd, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(merge_rrds, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(split_rrd, m)?)?;
+    #[cfg(feature = "sql")]
+    m.add_function(wrap_pyfunction_bound!(sql_ipc, m)?)?;
+    m.add_class::<PyDataChunk>()?;
+    m.add_class::<PyMetaChunk>()?;
+    m.add_class::<PyData>()?;
+    crate::python_async::register(m)?;
+    Ok(())
+}
@@ -0,0 +1,477 @@
+//! The asyncio API of the Python bindings, built with the `python` feature.
+//!
+//! The awaitable functions run the query without the GIL on a pool of Rust threads, one per core,
+//! and resolve the asyncio future on its event loop with `call_soon_threadsafe`, so neither the
+//! event loop nor the threads of its default executor are ever blocked. The jobs of the pool never
+//! wait for the consumer: streams convert their chunks on the pool only while there is room for
+//! them, so idle streams hold no thread. Queries beyond the number of threads wait for a free one.
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+};
+
+use log::debug;
+use pyo3::{
+    exceptions::PyStopAsyncIteration,
+    panic::PanicException,
+    prelude::*,
+    types::{PyCFunction, PyList},
+};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use re_chunk::Chunk;
+
+use crate::{
+    found_data_chunks, load_data_chunks,
+    python::{
+        query_data_chunks_parallel, to_batch_list, to_data_chunk_list, PyDataChunk, PyMetaChunk,
+    },
+    to_data_chunk, DataChunk, DataQuery, Error, Result,
+};
+
+/// The threads running the queries of the awaitable functions, one per core.
+fn pool() -> Result<&'static ThreadPool> {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+    if let Some(pool) = POOL.get() {
+        return Ok(pool);
+    }
+    let pool = ThreadPoolBuilder::new()
+        .thread_name(|index| format!("requery-async-{}", index))
+        .build()
+        .map_err(|e| Error::Invalid(format!("Thread pool error: {}", e)))?;
+    Ok(POOL.get_or_init(|| pool))
+}
+
+/// An asyncio future of the running event loop, resolved by the threads of the pool.
+struct PendingFuture {
+    event_loop: PyObject,
+    future: PyObject,
+}
+
+impl PendingFuture {
+    fn new(py: Python<'_>) -> PyResult<Self> {
+        let event_loop = py
+            .import_bound("asyncio")?
+            .call_method0("get_running_loop")?;
+        let future = event_loop.call_method0("create_future")?;
+        Ok(Self {
+            event_loop: event_loop.unbind(),
+            future: future.unbind(),
+        })
+    }
+
+    /// The future, to be awaited by the caller.
+    fn awaitable<'py>(&self, py: Python<'py>) -> Bound<'py, PyAny> {
+        self.future.clone_ref(py).into_bound(py)
+    }
+
+    /// Resolve the future on its event loop with the result of `to_py`, or a `PanicException` if
+    /// it panics. A cancelled future is left as is.
+    fn resolve(self, py: Python<'_>, to_py: impl FnOnce(Python<'_>) -> PyResult<PyObject>) {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| to_py(py)))
+            .unwrap_or_else(|payload| Err(PanicException::new_err(panic_message(payload))));
+        let (value, failed) = match result {
+            Ok(value) => (value, false),
+            Err(e) => (e.into_value(py).into_py(py), true),
+        };
+        let set_result = PyCFunction::new_closure_bound(py, None, None, |args, _| {
+            let (future, value, failed): (Bound<'_, PyAny>, Bound<'_, PyAny>, bool) =
+                args.extract()?;
+            if !future.call_method0("done")?.is_truthy()? {
+                let method = if failed {
+                    "set_exception"
+                } else {
+                    "set_result"
+                };
+                future.call_method1(method, (value,))?;
+            }
+            PyResult::Ok(())
+        });
+        let scheduled = set_result.and_then(|set_result| {
+            self.event_loop.bind(py).call_method1(
+                "call_soon_threadsafe",
+                (set_result, self.future, value, failed),
+            )
+        });
+        if let Err(e) = scheduled {
+            // The event loop is closed, so nothing awaits the future anymore
+            debug!("Dropping the result of an async query: {}", e);
+        }
+    }
+}
+
+/// Run `query` without the GIL on the pool, and return the asyncio future of the result of
+/// `to_py`, called with the GIL.
+fn spawn_query<'py, T, Q, C>(py: Python<'py>, query: Q, to_py: C) -> PyResult<Bound<'py, PyAny>>
+where
+    T: Send + 'static,
+    Q: FnOnce() -> T + Send + 'static,
+    C: FnOnce(Python<'_>, T) -> PyResult<PyObject> + Send + 'static,
+{
+    let future = PendingFuture::new(py)?;
+    let awaitable = future.awaitable(py);
+    pool()?.spawn(move || {
+        let result = panic::catch_unwind(AssertUnwindSafe(query));
+        Python::with_gil(|py| {
+            future.resolve(py, |py| match result {
+                Ok(result) => to_py(py, result),
+                Err(payload) => Err(PanicException::new_err(panic_message(payload))),
+            })
+        });
+    });
+    Ok(awaitable)
+}
+
+/// Awaitable version of `query_data_entities`.
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    data_type_filter = String::new(),
+    entity_path_filter = String::new(),
+    time_range = None,
+    timeline = "log_time".to_string()
+))]
+pub fn query_data_entities_async(
+    py: Python<'_>,
+    file_path: String,
+    data_type_filter: String,
+    entity_path_filter: String,
+    time_range: Option<(i64, i64)>,
+    timeline: String,
+) -> PyResult<Bound<'_, PyAny>> {
+    spawn_query(
+        py,
+        move || {
+            query_data_chunks_parallel(
+                &file_path,
+                &data_type_filter,
+                &entity_path_filter,
+                time_range,
+                &timeline,
+            )
+        },
+        |py, data_chunks| Ok(to_data_chunk_list(py, data_chunks?)?.into_py(py)),
+    )
+}
+
+/// Awaitable version of `query_data_entities_batch`.
+#[pyfunction]
+#[pyo3(signature = (
+    file_paths,
+    data_type_filter = String::new(),
+    entity_path_filter = String::new(),
+    time_range = None,
+    timeline = "log_time".to_string(),
+    num_threads = 0
+))]
+pub fn query_data_entities_batch_async(
+    py: Python<'_>,
+    file_paths: Vec<String>,
+    data_type_filter: String,
+    entity_path_filter: String,
+    time_range: Option<(i64, i64)>,
+    timeline: String,
+    num_threads: usize,
+) -> PyResult<Bound<'_, PyAny>> {
+    let query = DataQuery {
+        data_type_filter,
+        entity_path_filter,
+        time_range,
+        timeline,
+    };
+    spawn_query(
+        py,
+        move || crate::query_data_entities_batch(&file_paths, &query, num_threads),
+        |py, results| Ok(to_batch_list(py, results?).into_py(py)),
+    )
+}
+
+/// Awaitable version of `query_meta_entities`.
+#[pyfunction]
+#[pyo3(signature = (file_path, entity_path = String::new()))]
+pub fn query_meta_entities_async(
+    py: Python<'_>,
+    file_path: String,
+    entity_path: String,
+) -> PyResult<Bound<'_, PyAny>> {
+    spawn_query(
+        py,
+        move || crate::query_meta_entities(&file_path, &entity_path),
+        |py, meta_chunks| {
+            let meta_chunks = meta_chunks?
+                .into_iter()
+                .map(|chunk| {
+                    Py::new(
+                        py,
+                        PyMetaChunk::Text {
+                            entity_path: chunk.entity_path,
+                            media_type: chunk.media_type,
+                            text: chunk.text,
+                        },
+                    )
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(PyList::new_bound(py, &meta_chunks).into())
+        },
+    )
+}
+
+/// Awaitable version of `list_entity_paths`.
+#[pyfunction]
+pub fn list_entity_paths_async(py: Python<'_>, file_path: String) -> PyResult<Bound<'_, PyAny>> {
+    spawn_query(
+        py,
+        move || crate::list_entity_paths(&file_path),
+        |py, entity_paths| Ok(entity_paths?.into_py(py)),
+    )
+}
+
+/// The number of converted chunks a stream queues ahead of the consumer.
+const STREAM_CAPACITY: usize = 4;
+
+/// A converted chunk or the error of the query, or the message of a panic of the pool.
+enum StreamItem {
+    Chunk(Result<DataChunk>),
+    Panic(String),
+}
+
+/// The chunks of a query, converted on the pool as the consumer awaits them.
+#[derive(Default)]
+struct StreamState {
+    /// The chunks of the query not converted yet.
+    chunks: VecDeque<Chunk>,
+    /// The converted chunks not yet awaited, at most `STREAM_CAPACITY`.
+    converted: VecDeque<StreamItem>,
+    /// Whether every item was queued: every chunk was converted, or the query failed.
+    finished: bool,
+    /// Whether the iterator was dropped or an `__anext__` cancelled, which stops the conversion.
+    cancelled: bool,
+    /// Whether a job of the pool is running the query or converting chunks.
+    converting: bool,
+    /// The futures of the `__anext__` calls waiting for an item, in call order.
+    waiters: VecDeque<PendingFuture>,
+}
+
+/// The state of a stream, shared by the iterator and the jobs of the pool.
+///
+/// The GIL is never acquired with the state locked, so the state can be locked with the GIL.
+struct Stream {
+    file_path: String,
+    pool: &'static ThreadPool,
+    state: Mutex<StreamState>,
+}
+
+impl Stream {
+    fn state(&self) -> MutexGuard<'_, StreamState> {
+        // The state is always left consistent, even by a panic
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Run `query` on the pool, then convert the first chunks it returns.
+    fn start(self: &Arc<Self>, query: impl FnOnce() -> Result<Vec<Chunk>> + Send + 'static) {
+        self.state().converting = true;
+        let stream = self.clone();
+        self.pool.spawn(move || {
+            match panic::catch_unwind(AssertUnwindSafe(query)) {
+                Ok(Ok(chunks)) => stream.state().chunks = chunks.into(),
+                Ok(Err(e)) => stream.queue(StreamItem::Chunk(Err(e)), true),
+                Err(payload) => stream.queue(StreamItem::Panic(panic_message(payload)), true),
+            }
+            stream.convert();
+        });
+    }
+
+    /// Convert chunks until `STREAM_CAPACITY` of them are queued, every chunk is converted or the
+    /// stream is cancelled. Called by the job of the pool marked as `converting`.
+    fn convert(self: &Arc<Self>) {
+        loop {
+            let chunk = {
+                let mut state = self.state();
+                if state.cancelled || state.finished || state.converted.len() >= STREAM_CAPACITY {
+                    state.converting = false;
+                    return;
+                }
+                match state.chunks.pop_front() {
+                    Some(chunk) => chunk,
+                    None => {
+                        state.finished = true;
+                        state.converting = false;
+                        drop(state);
+                        self.wake();
+                        return;
+                    }
+                }
+            };
+            let item = match panic::catch_unwind(AssertUnwindSafe(|| to_data_chunk(&chunk))) {
+                Ok(chunk) => StreamItem::Chunk(chunk.map_err(|e| e.with_path(&self.file_path))),
+                Err(payload) => StreamItem::Panic(panic_message(payload)),
+            };
+            // The first chunk that cannot be converted ends the stream
+            let failed = !matches!(item, StreamItem::Chunk(Ok(_)));
+            self.queue(item, failed);
+        }
+    }
+
+    /// Queue an item for the consumer, the last one if `last`.
+    fn queue(self: &Arc<Self>, item: StreamItem, last: bool) {
+        {
+            let mut state = self.state();
+            state.converted.push_back(item);
+            state.finished |= last;
+        }
+        self.wake();
+    }
+
+    /// Resolve the waiting `__anext__` futures with the queued items, or with the end of the
+    /// stream once it is finished or cancelled, and convert more chunks if there is room for them.
+    fn wake(self: &Arc<Self>) {
+        let mut resolved = Vec::new();
+        {
+            let mut state = self.state();
+            while !state.waiters.is_empty() {
+                let item = if state.cancelled {
+                    None
+                } else if let Some(item) = state.converted.pop_front() {
+                    Some(item)
+                } else if state.finished {
+                    None
+                } else {
+                    break;
+                };
+                let waiter = state.waiters.pop_front().expect("a waiter is left");
+                resolved.push((waiter, item));
+            }
+
+            let has_room = state.converted.len() < STREAM_CAPACITY;
+            if has_room && !state.converting && !state.finished && !state.cancelled {
+                state.converting = true;
+                let stream = self.clone();
+                self.pool.spawn(move || stream.convert());
+            }
+        }
+        if resolved.is_empty() {
+            return;
+        }
+        Python::with_gil(|py| {
+            for (waiter, item) in resolved {
+                waiter.resolve(py, |py| match item {
+                    Some(StreamItem::Chunk(chunk)) => {
+                        Ok(PyDataChunk::from_data_chunk(py, chunk?)?.into_py(py))
+                    }
+                    Some(StreamItem::Panic(message)) => Err(PanicException::new_err(message)),
+                    None => Err(PyErr::new::<PyStopAsyncIteration, _>(())),
+                });
+            }
+        });
+    }
+
+    fn cancel(self: &Arc<Self>) {
+        {
+            let mut state = self.state();
+            state.cancelled = true;
+            state.chunks.clear();
+            state.converted.clear();
+        }
+        self.wake();
+    }
+}
+
+/// An async iterator over the `DataChunk`s of a query, returned by `iter_data_entities_async`.
+///
+/// The chunks are converted on the pool, a few chunks ahead of the consumer. The conversion stops
+/// when the iterator is dropped or an awaited `__anext__` is cancelled.
+#[pyclass(name = "DataChunkStream")]
+pub struct PyDataChunkStream {
+    stream: Arc<Stream>,
+}
+
+impl Drop for PyDataChunkStream {
+    fn drop(&mut self) {
+        self.stream.cancel();
+    }
+}
+
+#[pymethods]
+impl PyDataChunkStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let waiter = PendingFuture::new(py)?;
+        let future = waiter.awaitable(py);
+        self.stream.state().waiters.push_back(waiter);
+        self.stream.wake();
+
+        let stream = self.stream.clone();
+        let on_done = PyCFunction::new_closure_bound(py, None, None, move |args, _| {
+            let (future,): (Bound<'_, PyAny>,) = args.extract()?;
+            if future.call_method0("cancelled")?.is_truthy()? {
+                stream.cancel();
+            }
+            PyResult::Ok(())
+        })?;
+        future.call_method1("add_done_callback", (on_done,))?;
+        Ok(future)
+    }
+}
+
+/// The message of a panic, as PyO3 reports it.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "panic from Rust code".to_string(),
+        },
+    }
+}
+
+/// Stream the `DataChunk`s of `query_data_entities` with `async for`, as they are converted.
+///
+/// Errors of the query, and of the first chunk that cannot be converted, end the stream.
+#[pyfunction]
+#[pyo3(signature = (
+    file_path,
+    data_type_filter = String::new(),
+    entity_path_filter = String::new(),
+    time_range = None,
+    timeline = "log_time".to_string()
+))]
+pub fn iter_data_entities_async(
+    file_path: String,
+    data_type_filter: String,
+    entity_path_filter: String,
+    time_range: Option<(i64, i64)>,
+    timeline: String,
+) -> PyResult<PyDataChunkStream> {
+    let stream = Arc::new(Stream {
+        file_path: file_path.clone(),
+        pool: pool()?,
+        state: Mutex::default(),
+    });
+    stream.start(move || {
+        let chunks = load_data_chunks(
+            &file_path,
+            &data_type_filter,
+            &entity_path_filter,
+            time_range,
+            &timeline,
+        );
+        found_data_chunks(&file_path, &entity_path_filter, chunks)
+    });
+    Ok(PyDataChunkStream { stream })
+}
+
+/// Add the asyncio API to the `requery` module.
+pub(crate) fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction_bound!(query_data_entities_async, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(query_data_entities_batch_async, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(query_meta_entities_async, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(list_entity_paths_async, m)?)?;
+    m.add_function(wrap_pyfunction_bound!(iter_data_entities_async, m)?)?;
+    m.add_class::<PyDataChunkStream>()?;
+    Ok(())
+}
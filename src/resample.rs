@@ -0,0 +1,246 @@
+//! Resampling of RRD entities to a fixed frame rate, shared by the dataset exporters.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use re_arrow2::{
+    array::{Array, Float32Array},
+    compute::cast::{cast, CastOptions},
+    datatypes::DataType,
+    error::Error as ArrowError,
+};
+use re_chunk::{Chunk, ComponentName};
+use re_entity_db::EntityDb;
+use re_log_types::TimeType;
+
+use crate::{
+    columns, matches_entity_path, query_chunks, query_data_chunks, query_entity_chunks, Error,
+    Result,
+};
+
+/// The samples of one feature, sorted by time.
+pub(crate) enum FeatureSamples {
+    /// Scalars or tensors, flattened to `float32` vectors of a fixed length.
+    Numeric {
+        times: Vec<i64>,
+        values: Vec<Vec<f32>>,
+    },
+    /// Encoded images (`EncodedImage` blobs), kept as encoded bytes.
+    Image {
+        times: Vec<i64>,
+        frames: Vec<Vec<u8>>,
+    },
+}
+
+impl FeatureSamples {
+    pub(crate) fn times(&self) -> &[i64] {
+        match self {
+            Self::Numeric { times, .. } | Self::Image { times, .. } => times,
+        }
+    }
+}
+
+/// Load and time-sort the samples of one entity on the given temporal timeline.
+pub(crate) fn load_feature(
+    rrd: &EntityDb,
+    entity_path: &str,
+    timeline_name: &str,
+) -> Result<FeatureSamples> {
+    let chunks = query_entity_chunks(rrd, entity_path, |_| true);
+
+    let scalar = ComponentName::from("rerun.components.Scalar");
+    let tensor = ComponentName::from("rerun.components.TensorData");
+    let blob = ComponentName::from("rerun.components.Blob");
+
+    let mut numeric: Vec<(i64, Vec<f32>)> = Vec::new();
+    let mut images: Vec<(i64, Vec<u8>)> = Vec::new();
+
+    for chunk in &chunks {
+        let Some((timeline, time_column)) = chunk
+            .timelines()
+            .iter()
+            .find(|(timeline, _)| timeline.name().as_str() == timeline_name)
+        else {
+            continue;
+        };
+        if timeline.typ() != TimeType::Time {
+            return Err(Error::Invalid(format!(
+                "Timeline {} is not a temporal timeline",
+                timeline_name
+            )));
+        }
+        let times = time_column.times_raw();
+
+        if let Some(list_array) = chunk.components().get(&scalar) {
+            let values = columns::scalar_column(list_array);
+            for (time, value) in times.iter().zip(values.iter()) {
+                if let Some(value) = value {
+                    numeric.push((*time, vec![*value as f32]));
+                }
+            }
+        } else if let Some(list_array) = chunk.components().get(&tensor) {
+            let tensors = columns::tensor_column(list_array).map_err(arrow_error)?;
+            let values = cast(
+                tensors.values().as_ref(),
+                &DataType::Float32,
+                CastOptions::default(),
+            )
+            .map_err(arrow_error)?;
+            let values = values
+                .as_any()
+                .downcast_ref::<Float32Array>()
+                .expect("cast to Float32 returns a Float32Array");
+            for (row, time) in times.iter().enumerate() {
+                if tensors.is_valid(row) {
+                    let (start, end) = tensors.offsets().start_end(row);
+                    numeric.push((*time, values.values()[start..end].to_vec()));
+                }
+            }
+        } else if let Some(list_array) = chunk.components().get(&blob) {
+            for (row, time) in times.iter().enumerate() {
+                if let Some(frame) = columns::blob_bytes(list_array, row) {
+                    images.push((*time, frame));
+                }
+            }
+        }
+    }
+
+    if !numeric.is_empty() {
+        numeric.sort_by_key(|(time, _)| *time);
+        let (times, values) = numeric.into_iter().unzip();
+        Ok(FeatureSamples::Numeric { times, values })
+    } else if !images.is_empty() {
+        images.sort_by_key(|(time, _)| *time);
+        let (times, frames) = images.into_iter().unzip();
+        Ok(FeatureSamples::Image { times, frames })
+    } else {
+        Err(Error::Invalid(format!(
+            "No scalar, tensor or encoded image data found for {} on timeline {}",
+            entity_path, timeline_name
+        )))
+    }
+}
+
+/// Compute the frame times of a recording: `fps` frames per second over the time range covered by
+/// every feature.
+pub(crate) fn frame_times(
+    samples: &BTreeMap<String, FeatureSamples>,
+    fps: u32,
+) -> Option<Vec<i64>> {
+    let start = samples
+        .values()
+        .map(|samples| samples.times().first().copied())
+        .collect::<Option<Vec<i64>>>()?
+        .into_iter()
+        .max()?;
+    let end = samples
+        .values()
+        .map(|samples| samples.times().last().copied())
+        .collect::<Option<Vec<i64>>>()?
+        .into_iter()
+        .min()?;
+    if end < start {
+        return None;
+    }
+
+    let step = 1e9 / fps as f64;
+    let num_frames = ((end - start) as f64 / step).floor() as usize + 1;
+    Some(
+        (0..num_frames)
+            .map(|frame| start + (frame as f64 * step).round() as i64)
+            .collect(),
+    )
+}
+
+/// For every frame time, the index of the latest sample at or before it.
+pub(crate) fn latest_at(times: &[i64], frame_times: &[i64]) -> Vec<usize> {
+    let mut index = 0;
+    frame_times
+        .iter()
+        .map(|frame_time| {
+            while index + 1 < times.len() && times[index + 1] <= *frame_time {
+                index += 1;
+            }
+            index
+        })
+        .collect()
+}
+
+/// The data entities of a recording resampled to a fixed frame rate.
+pub(crate) struct AlignedFeatures {
+    /// The frame times, in nanoseconds on the alignment timeline.
+    pub frame_times: Vec<i64>,
+    /// The feature key and samples of every entity, with the index of its sample at every frame.
+    pub features: Vec<(String, FeatureSamples, Vec<usize>)>,
+}
+
+/// Load the data entities selected like `query_data_entities` and align them to `fps` frames
+/// per second on the given temporal timeline.
+///
+/// `EncodedImage` entities matching `entity_path_filter` are selected too, unless
+/// `data_type_filter` restricts the selection to scalars or tensors. Feature keys are the entity
+/// paths with dots as separators, e.g. `action.joints.position`, and entities with the same key,
+/// e.g. `/arm.left` and `/arm/left`, are rejected.
+pub(crate) fn load_aligned_features(
+    rrd: &EntityDb,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    timeline_name: &str,
+    fps: u32,
+) -> Result<AlignedFeatures> {
+    let mut entity_paths: BTreeSet<String> =
+        query_data_chunks(rrd, data_type_filter, entity_path_filter)
+            .iter()
+            .map(|chunk| chunk.entity_path().to_string())
+            .collect();
+    if data_type_filter.is_empty() {
+        let images = query_chunks(rrd, |chunk| {
+            matches_entity_path(chunk, entity_path_filter) && is_encoded_image_chunk(chunk)
+        });
+        entity_paths.extend(images.iter().map(|chunk| chunk.entity_path().to_string()));
+    }
+    if entity_paths.is_empty() {
+        return Err(Error::NoData(
+            "No data found for the specified entity".to_string(),
+        ));
+    }
+
+    let mut samples = BTreeMap::new();
+    let mut key_entities: BTreeMap<String, String> = BTreeMap::new();
+    for entity_path in entity_paths {
+        let key = entity_path.trim_matches('/').replace('/', ".");
+        if let Some(other) = key_entities.insert(key.clone(), entity_path.clone()) {
+            return Err(Error::Invalid(format!(
+                "Entities {} and {} have the same feature key {}, narrow the entity filter",
+                other, entity_path, key
+            )));
+        }
+        let feature = load_feature(rrd, &entity_path, timeline_name)?;
+        samples.insert(key, feature);
+    }
+
+    let frame_times = frame_times(&samples, fps).ok_or_else(|| {
+        Error::NoData("No overlapping data for the selected entities".to_string())
+    })?;
+    let features = samples
+        .into_iter()
+        .map(|(key, samples)| {
+            let rows = latest_at(samples.times(), &frame_times);
+            (key, samples, rows)
+        })
+        .collect();
+
+    Ok(AlignedFeatures {
+        frame_times,
+        features,
+    })
+}
+
+fn is_encoded_image_chunk(chunk: &Chunk) -> bool {
+    chunk
+        .component_names()
+        .any(|name| name == "rerun.components.Blob")
+}
+
+fn arrow_error(e: ArrowError) -> Error {
+    Error::Invalid(format!("Data conversion error: {}", e))
+}
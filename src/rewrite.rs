@@ -0,0 +1,547 @@
+//! Rewriting of RRD files: the decoded `LogMsg`s are filtered, merged or split and encoded again,
+//! so the result opens in the Rerun viewer like any recording.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use log::debug;
+use re_arrow2::array::PrimitiveArray;
+use re_build_info::CrateVersion;
+use re_chunk::{Chunk, ChunkResult, TimeColumn};
+use re_log_encoding::{
+    decoder::{Decoder, VersionPolicy},
+    encoder::Encoder,
+    EncodingOptions,
+};
+use re_log_types::{ApplicationId, LogMsg, StoreId, StoreKind, Timeline};
+
+use crate::{Error, Result};
+
+/// Write a copy of an RRD file restricted to some stores, entities and time range.
+///
+/// The store infos of the kept stores are copied as is. Recording chunks are kept if their entity
+/// path contains `entity_filter`, and temporal chunks are trimmed to the rows whose time on
+/// `timeline` is within `time_range`, bounds included. Static chunks are always kept since they
+/// apply to every time, and temporal chunks without `timeline` are dropped when a `time_range` is
+/// given. Blueprints of the kept recordings are copied unfiltered if `include_blueprint` is set.
+///
+/// # Arguments
+///
+/// * `src` - A string slice that holds the path to the RRD file to read.
+/// * `dst` - The path of the RRD file to write. Its directory is created if missing.
+/// * `entity_filter` - Only keep the entities whose path contains this string. Set to empty string to keep all entities.
+/// * `time_range` - The `(start, end)` times to keep on `timeline`, in nanoseconds for temporal timelines. `None` keeps all times.
+/// * `store_selector` - The store id or application id of the recordings to keep. Set to empty string to keep all recordings.
+/// * `timeline` - The timeline of `time_range`.
+/// * `include_blueprint` - Also copy the blueprints of the kept recordings.
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of written messages.
+pub fn write_filtered_rrd(
+    src: &str,
+    dst: &str,
+    entity_filter: &str,
+    time_range: Option<(i64, i64)>,
+    store_selector: &str,
+    timeline: &str,
+    include_blueprint: bool,
+) -> Result<usize> {
+    let messages = read_messages(src)?;
+
+    let application_ids: BTreeMap<StoreId, ApplicationId> = messages
+        .iter()
+        .filter_map(|message| match message {
+            LogMsg::SetStoreInfo(set_store_info) => Some((
+                set_store_info.info.store_id.clone(),
+                set_store_info.info.application_id.clone(),
+            )),
+            _ => None,
+        })
+        .collect();
+
+    let recordings: BTreeSet<StoreId> = messages
+        .iter()
+        .map(LogMsg::store_id)
+        .filter(|store_id| store_id.kind == StoreKind::Recording)
+        .filter(|store_id| {
+            store_selector.is_empty()
+                || store_id.id.as_str() == store_selector
+                || application_ids
+                    .get(store_id)
+                    .is_some_and(|application_id| application_id.as_str() == store_selector)
+        })
+        .cloned()
+        .collect();
+    if recordings.is_empty() {
+        return Err(Error::StoreNotFound(format!(
+            "No recording found for the store selector {:?}",
+            store_selector
+        )));
+    }
+    let applications: BTreeSet<&ApplicationId> = recordings
+        .iter()
+        .filter_map(|store_id| application_ids.get(store_id))
+        .collect();
+    let keep_store = |store_id: &StoreId| match store_id.kind {
+        StoreKind::Recording => recordings.contains(store_id),
+        StoreKind::Blueprint => {
+            include_blueprint
+                && application_ids
+                    .get(store_id)
+                    .is_some_and(|application_id| applications.contains(application_id))
+        }
+    };
+
+    let mut encoder = create_encoder(dst)?;
+    let mut num_messages = 0;
+    let mut num_chunks = 0;
+    for message in &messages {
+        if !keep_store(message.store_id()) {
+            continue;
+        }
+
+        let message = match message {
+            LogMsg::ArrowMsg(store_id, arrow_msg) if store_id.kind == StoreKind::Recording => {
+                let chunk = Chunk::from_arrow_msg(arrow_msg).map_err(decode_error)?;
+                if !entity_filter.is_empty()
+                    && !chunk.entity_path().to_string().contains(entity_filter)
+                {
+                    continue;
+                }
+                let Some(trimmed) = trim_chunk(&chunk, timeline, time_range) else {
+                    continue;
+                };
+                num_chunks += 1;
+                if trimmed.num_rows() == chunk.num_rows() {
+                    message.clone()
+                } else {
+                    LogMsg::ArrowMsg(
+                        store_id.clone(),
+                        trimmed.to_arrow_msg().map_err(encode_error)?,
+                    )
+                }
+            }
+            _ => message.clone(),
+        };
+        encoder.append(&message).map_err(encode_error)?;
+        num_messages += 1;
+    }
+    encoder.into_inner().flush().map_err(Error::Write)?;
+
+    if num_chunks == 0 {
+        // Don't leave a recording without data behind
+        fs::remove_file(dst).ok();
+        return Err(Error::NoData(
+            "No data found for the specified entity and time range".to_string(),
+        )
+        .with_path(src)
+        .with_entity(entity_filter));
+    }
+    debug!(
+        "Wrote {} messages, {} data chunks",
+        num_messages, num_chunks
+    );
+
+    Ok(num_messages)
+}
+
+/// Merge RRD files, e.g. the rotated log files of one session, into a single recording.
+///
+/// Every recording of every file is written under one store id: `store_id` if set, or the id of
+/// the first recording of the first file otherwise. The store info of that first recording is
+/// kept, and so are the blueprints of the first file holding any. Chunks keep their row ids, so
+/// queries on the merged recording see the data of all the files as one continuous session.
+///
+/// The timelines listed in `offset_timelines` are shifted so that every file starts right after
+/// the previous one ends, which turns per-file counters such as `log_tick` into one sequence.
+///
+/// # Arguments
+///
+/// * `file_paths` - The RRD files to merge, in session order.
+/// * `out_path` - The path of the RRD file to write. Its directory is created if missing.
+/// * `store_id` - The store id of the merged recording. Set to empty string to keep the id of the first recording.
+/// * `offset_timelines` - The timelines to offset so that the files follow each other.
+///
+/// # Returns
+///
+/// * `Result<usize>` - The number of written messages.
+pub fn merge_rrds(
+    file_paths: Vec<String>,
+    out_path: &str,
+    store_id: &str,
+    offset_timelines: Vec<String>,
+) -> Result<usize> {
+    let mut target: Option<StoreId> = (!store_id.is_empty())
+        .then(|| StoreId::from_string(StoreKind::Recording, store_id.to_string()));
+    let mut has_store_info = false;
+    let mut blueprint_file: Option<usize> = None;
+    // The first time of every offset timeline in the next file
+    let mut next_start: BTreeMap<String, i64> = BTreeMap::new();
+
+    let mut encoder = create_encoder(out_path)?;
+    let mut num_messages = 0;
+    for (file_index, file_path) in file_paths.iter().enumerate() {
+        let messages = read_messages(file_path)?;
+
+        let offsets = timeline_offsets(&messages, &offset_timelines, &mut next_start)?;
+        debug!("Merging {} with timeline offsets {:?}", file_path, offsets);
+
+        for message in messages {
+            let store_id = message.store_id();
+            if store_id.kind == StoreKind::Blueprint {
+                if *blueprint_file.get_or_insert(file_index) != file_index {
+                    continue;
+                }
+                encoder.append(&message).map_err(encode_error)?;
+                num_messages += 1;
+                continue;
+            }
+
+            let target = target.get_or_insert_with(|| store_id.clone()).clone();
+            let message = match message {
+                LogMsg::SetStoreInfo(mut set_store_info) => {
+                    if has_store_info {
+                        continue;
+                    }
+                    has_store_info = true;
+                    set_store_info.info.store_id = target;
+                    LogMsg::SetStoreInfo(set_store_info)
+                }
+                LogMsg::ArrowMsg(_, arrow_msg) if offsets.is_empty() => {
+                    LogMsg::ArrowMsg(target, arrow_msg)
+                }
+                LogMsg::ArrowMsg(_, arrow_msg) => {
+                    let chunk = Chunk::from_arrow_msg(&arrow_msg).map_err(decode_error)?;
+                    let chunk = offset_chunk(chunk, &offsets).map_err(encode_error)?;
+                    LogMsg::ArrowMsg(target, chunk.to_arrow_msg().map_err(encode_error)?)
+                }
+                message => message,
+            };
+            encoder.append(&message).map_err(encode_error)?;
+            num_messages += 1;
+        }
+    }
+    encoder.into_inner().flush().map_err(Error::Write)?;
+
+    if target.is_none() {
+        fs::remove_file(out_path).ok();
+        return Err(Error::StoreNotFound(
+            "No recording found in the RRD files".to_string(),
+        ));
+    }
+
+    Ok(num_messages)
+}
+
+/// Compute the offsets of the timelines of one file so that it starts at `next_start`, and move
+/// `next_start` past its end.
+fn timeline_offsets(
+    messages: &[LogMsg],
+    offset_timelines: &[String],
+    next_start: &mut BTreeMap<String, i64>,
+) -> Result<BTreeMap<Timeline, i64>> {
+    if offset_timelines.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+
+    let mut ranges: BTreeMap<Timeline, (i64, i64)> = BTreeMap::new();
+    for message in messages {
+        let LogMsg::ArrowMsg(store_id, arrow_msg) = message else {
+            continue;
+        };
+        if store_id.kind != StoreKind::Recording {
+            continue;
+        }
+        let chunk = Chunk::from_arrow_msg(arrow_msg).map_err(decode_error)?;
+        for (timeline, time_column) in chunk.timelines() {
+            if !offset_timelines
+                .iter()
+                .any(|name| name == timeline.name().as_str())
+            {
+                continue;
+            }
+            let range = time_column.time_range();
+            let (min, max) = ranges.entry(*timeline).or_insert((i64::MAX, i64::MIN));
+            *min = (*min).min(range.min().as_i64());
+            *max = (*max).max(range.max().as_i64());
+        }
+    }
+
+    let mut offsets = BTreeMap::new();
+    for (timeline, (min, max)) in ranges {
+        let name = timeline.name().to_string();
+        let offset = next_start.get(&name).map_or(0, |start| start - min);
+        next_start.insert(name, max + offset + 1);
+        offsets.insert(timeline, offset);
+    }
+    Ok(offsets)
+}
+
+/// Shift the timelines of a chunk by the given offsets.
+fn offset_chunk(mut chunk: Chunk, offsets: &BTreeMap<Timeline, i64>) -> ChunkResult<Chunk> {
+    let shifted: Vec<TimeColumn> = chunk
+        .timelines()
+        .iter()
+        .filter_map(|(timeline, time_column)| {
+            let offset = offsets.get(timeline)?;
+            let times = time_column
+                .times_raw()
+                .iter()
+                .map(|time| time + offset)
+                .collect();
+            Some(TimeColumn::new(
+                Some(time_column.is_sorted()),
+                *timeline,
+                PrimitiveArray::from_vec(times),
+            ))
+        })
+        .collect();
+    for time_column in shifted {
+        chunk.add_timeline(time_column)?;
+    }
+    Ok(chunk)
+}
+
+/// Split an RRD file into one RRD file per episode.
+///
+/// Episodes are delimited on `timeline` either by the rows of `marker_entity` (e.g. the
+/// `/meta/episode` text rows logged at the start of every episode), or by gaps between
+/// consecutive data times larger than `gap_threshold`. Data before the first marker belongs to
+/// the first episode.
+///
+/// Every episode file holds the temporal rows of its time range, all the static data and the
+/// blueprints, and every recording is given a new store id, with `cloned_from` pointing to the
+/// original one. Episodes without temporal data are skipped.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `out_dir` - The directory of the episode files, named `episode_000000.rrd`, ... Created if missing.
+/// * `marker_entity` - The entity whose rows start the episodes. Set to empty string to split on gaps.
+/// * `gap_threshold` - The minimal gap between two episodes, in nanoseconds for temporal timelines. Used if `marker_entity` is empty.
+/// * `timeline` - The timeline used to delimit the episodes.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The paths of the written episode files.
+pub fn split_rrd(
+    file_path: &str,
+    out_dir: &str,
+    marker_entity: &str,
+    gap_threshold: Option<i64>,
+    timeline: &str,
+) -> Result<Vec<String>> {
+    let messages = read_messages(file_path)?;
+    let chunks = messages
+        .iter()
+        .map(|message| match message {
+            LogMsg::ArrowMsg(store_id, arrow_msg) if store_id.kind == StoreKind::Recording => {
+                Chunk::from_arrow_msg(arrow_msg)
+                    .map(Some)
+                    .map_err(decode_error)
+            }
+            _ => Ok(None),
+        })
+        .collect::<Result<Vec<Option<Chunk>>>>()?;
+
+    let starts = match (marker_entity.is_empty(), gap_threshold) {
+        (false, _) => marker_times(&chunks, marker_entity, timeline),
+        (true, Some(gap_threshold)) => gap_starts(&chunks, timeline, gap_threshold),
+        (true, None) => {
+            return Err(Error::Invalid(
+                "Either marker_entity or gap_threshold must be set".to_string(),
+            ))
+        }
+    };
+    if starts.is_empty() {
+        return Err(Error::NoData(format!(
+            "No episode found on timeline {}",
+            timeline
+        )));
+    }
+    debug!("Found {} episodes", starts.len());
+
+    let mut written = Vec::new();
+    for (episode, start) in starts.iter().enumerate() {
+        // The first episode also holds the data before the first marker
+        let start = if episode == 0 { i64::MIN } else { *start };
+        let end = starts.get(episode + 1).map_or(i64::MAX, |next| next - 1);
+
+        let path = Path::new(out_dir).join(format!("episode_{:06}.rrd", written.len()));
+        let path = path.to_string_lossy().into_owned();
+        if write_episode(&path, &messages, &chunks, timeline, (start, end))? {
+            written.push(path);
+        } else {
+            fs::remove_file(&path).ok();
+        }
+    }
+
+    Ok(written)
+}
+
+/// The sorted times of the rows of the marker entity.
+fn marker_times(chunks: &[Option<Chunk>], marker_entity: &str, timeline_name: &str) -> Vec<i64> {
+    let mut times: Vec<i64> = chunks
+        .iter()
+        .flatten()
+        .filter(|chunk| chunk.entity_path().to_string() == marker_entity)
+        .filter_map(|chunk| {
+            chunk
+                .timelines()
+                .iter()
+                .find(|(timeline, _)| timeline.name().as_str() == timeline_name)
+        })
+        .flat_map(|(_, time_column)| time_column.times_raw().iter().copied())
+        .collect();
+    times.sort_unstable();
+    times.dedup();
+    times
+}
+
+/// The first time of the data and every time following a gap larger than `gap_threshold`.
+fn gap_starts(chunks: &[Option<Chunk>], timeline_name: &str, gap_threshold: i64) -> Vec<i64> {
+    let mut times: Vec<i64> = chunks
+        .iter()
+        .flatten()
+        .filter_map(|chunk| {
+            chunk
+                .timelines()
+                .iter()
+                .find(|(timeline, _)| timeline.name().as_str() == timeline_name)
+        })
+        .flat_map(|(_, time_column)| time_column.times_raw().iter().copied())
+        .collect();
+    times.sort_unstable();
+    times.dedup();
+
+    let mut starts: Vec<i64> = times.first().copied().into_iter().collect();
+    starts.extend(
+        times
+            .windows(2)
+            .filter(|pair| pair[1] - pair[0] > gap_threshold)
+            .map(|pair| pair[1]),
+    );
+    starts
+}
+
+/// Write the messages of one episode under new recording ids.
+///
+/// Returns false if the episode has no temporal data.
+fn write_episode(
+    path: &str,
+    messages: &[LogMsg],
+    chunks: &[Option<Chunk>],
+    timeline: &str,
+    time_range: (i64, i64),
+) -> Result<bool> {
+    let mut store_ids: BTreeMap<StoreId, StoreId> = BTreeMap::new();
+    let mut new_store_id = |store_id: &StoreId| {
+        store_ids
+            .entry(store_id.clone())
+            .or_insert_with(|| StoreId::random(StoreKind::Recording))
+            .clone()
+    };
+
+    let mut encoder = create_encoder(path)?;
+    let mut num_rows = 0;
+    for (message, chunk) in messages.iter().zip(chunks) {
+        let message = match (message, chunk) {
+            (LogMsg::SetStoreInfo(set_store_info), _)
+                if set_store_info.info.store_id.kind == StoreKind::Recording =>
+            {
+                let mut set_store_info = set_store_info.clone();
+                set_store_info.info.cloned_from = Some(set_store_info.info.store_id.clone());
+                set_store_info.info.store_id = new_store_id(&set_store_info.info.store_id);
+                LogMsg::SetStoreInfo(set_store_info)
+            }
+            (LogMsg::ArrowMsg(store_id, _), Some(chunk)) => {
+                let Some(trimmed) = trim_chunk(chunk, timeline, Some(time_range)) else {
+                    continue;
+                };
+                if !trimmed.is_static() {
+                    num_rows += trimmed.num_rows();
+                }
+                LogMsg::ArrowMsg(
+                    new_store_id(store_id),
+                    trimmed.to_arrow_msg().map_err(encode_error)?,
+                )
+            }
+            (message, _) => message.clone(),
+        };
+        encoder.append(&message).map_err(encode_error)?;
+    }
+    encoder.into_inner().flush().map_err(Error::Write)?;
+
+    Ok(num_rows > 0)
+}
+
+/// Decode all the messages of an RRD file, in file order.
+pub(crate) fn read_messages(file_path: &str) -> Result<Vec<LogMsg>> {
+    let decode = || {
+        let file = File::open(file_path).map_err(Error::Open)?;
+        let decoder =
+            Decoder::new(VersionPolicy::Warn, BufReader::new(file)).map_err(decode_error)?;
+        decoder
+            .enumerate()
+            .map(|(message_index, message)| {
+                message.map_err(|e| decode_error(e).with_message_index(message_index))
+            })
+            .collect::<Result<_>>()
+    };
+    decode().map_err(|e| e.with_path(file_path))
+}
+
+/// Create an RRD file, with its directory if missing, and an encoder writing to it.
+pub(crate) fn create_encoder(file_path: &str) -> Result<Encoder<BufWriter<File>>> {
+    let path = Path::new(file_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(Error::Write)?;
+    }
+    let file = File::create(path).map_err(Error::Write)?;
+    Encoder::new(
+        CrateVersion::LOCAL,
+        EncodingOptions::COMPRESSED,
+        BufWriter::new(file),
+    )
+    .map_err(encode_error)
+}
+
+/// Keep the rows of a chunk within `time_range` on the given timeline.
+///
+/// Returns `None` if no row is left.
+pub(crate) fn trim_chunk(
+    chunk: &Chunk,
+    timeline_name: &str,
+    time_range: Option<(i64, i64)>,
+) -> Option<Chunk> {
+    let Some((start, end)) = time_range else {
+        return Some(chunk.clone());
+    };
+    if chunk.is_static() {
+        return Some(chunk.clone());
+    }
+
+    let timeline = *chunk
+        .timelines()
+        .keys()
+        .find(|timeline| timeline.name().as_str() == timeline_name)?;
+    let chunk = chunk.sorted_by_timeline_if_unsorted(&timeline);
+    let times = chunk.timelines()[&timeline].times_raw();
+    let first = times.partition_point(|time| *time < start);
+    let last = times.partition_point(|time| *time <= end);
+
+    (first < last).then(|| chunk.row_sliced(first, last - first))
+}
+
+pub(crate) fn decode_error(e: impl std::fmt::Display) -> Error {
+    Error::Decode(e.to_string())
+}
+
+pub(crate) fn encode_error(e: impl std::fmt::Display) -> Error {
+    Error::Encode(format!("RRD encoding error: {}", e))
+}
@@ -0,0 +1,375 @@
+//! A local HTTP service over a folder of RRD files, built with the `server` feature.
+//!
+//! Every recording is addressed by its file name inside the folder:
+//!
+//! ```text
+//! GET /api/recordings                          the .rrd files of the folder
+//! GET /api/recordings/{name}                   the stores of a recording
+//! GET /api/recordings/{name}/entities          the entities of the recording stores
+//! GET /api/recordings/{name}/meta?entity=      the text entities
+//! GET /api/recordings/{name}/data?entity=&data_type=&timeline=&start=&end=&format=
+//! ```
+//!
+//! Data is returned as JSON, or as an Arrow IPC stream with `format=arrow`. Errors are JSON
+//! objects with an `error` message.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use actix_web::{
+    get,
+    http::StatusCode,
+    middleware::DefaultHeaders,
+    web::{self, Json},
+    App, HttpResponse, HttpServer, ResponseError,
+};
+use re_arrow2::{
+    array::{Array, Float64Array},
+    compute::cast::{cast, CastOptions},
+    datatypes::DataType,
+};
+use re_chunk::Chunk;
+use re_log_types::StoreKind;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    folder_rrd_path, load_bundle, load_data_chunks, query_meta_entities, store_kind_name,
+    summarize_stores, time_type_name, to_data_chunk, type_name, ChunkData, EntitySummary,
+    EntityTable, Error, Result,
+};
+
+/// The state shared by the handlers.
+pub struct AppState {
+    /// The folder holding the `.rrd` files.
+    pub folder_path: PathBuf,
+}
+
+/// Serve the recordings of `folder_path` on `addr`, e.g. `127.0.0.1:8080`, until stopped.
+///
+/// Responses allow every origin, so that a dashboard served elsewhere can query the service.
+pub async fn serve(folder_path: impl Into<PathBuf>, addr: &str) -> std::io::Result<()> {
+    let state = web::Data::new(AppState {
+        folder_path: folder_path.into(),
+    });
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .wrap(DefaultHeaders::new().add(("Access-Control-Allow-Origin", "*")))
+            .configure(configure)
+    })
+    .bind(addr)?
+    .run()
+    .await
+}
+
+/// Register the routes of the service, to embed it in another actix-web application.
+///
+/// The application must hold a `web::Data<AppState>`.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api")
+            .service(get_recordings)
+            .service(get_stores)
+            .service(get_entities)
+            .service(get_meta)
+            .service(get_data),
+    );
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self.kind() {
+            Error::Open(_) | Error::StoreNotFound(_) | Error::NoData(_) => StatusCode::NOT_FOUND,
+            Error::Invalid(_) => StatusCode::BAD_REQUEST,
+            Error::Decode(_) | Error::UnsupportedComponent(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Write(_) | Error::Encode(_) | Error::WithContext(..) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(json!({ "error": self.to_string() }))
+    }
+}
+
+#[get("/recordings")]
+async fn get_recordings(data: web::Data<AppState>) -> actix_web::Result<Json<Vec<Value>>> {
+    let folder_path = data.folder_path.clone();
+    Ok(Json(
+        web::block(move || list_recordings(&folder_path)).await??,
+    ))
+}
+
+#[get("/recordings/{name}")]
+async fn get_stores(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+) -> actix_web::Result<Json<Vec<Value>>> {
+    Ok(Json(get_entity_dbs(&data, &name).await?))
+}
+
+#[get("/recordings/{name}/entities")]
+async fn get_entities(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+) -> actix_web::Result<Json<Vec<Value>>> {
+    let file_path = rrd_path(&data, &name)?;
+    let entities = web::block(move || {
+        Ok::<_, Error>(
+            summarize_stores(&file_path)?
+                .into_iter()
+                .filter(|store| store.kind == StoreKind::Recording)
+                .flat_map(|store| store.entities)
+                .map(|entity| entity_json(&entity))
+                .collect(),
+        )
+    })
+    .await??;
+    Ok(Json(entities))
+}
+
+#[derive(Deserialize)]
+struct MetaQuery {
+    #[serde(default)]
+    entity: String,
+}
+
+#[get("/recordings/{name}/meta")]
+async fn get_meta(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+    query: web::Query<MetaQuery>,
+) -> actix_web::Result<Json<Vec<Value>>> {
+    let file_path = rrd_path(&data, &name)?;
+    let meta_chunks = web::block(move || query_meta_entities(&file_path, &query.entity)).await??;
+    Ok(Json(
+        meta_chunks
+            .into_iter()
+            .map(|meta| {
+                json!({
+                    "entity_path": meta.entity_path,
+                    "media_type": meta.media_type,
+                    "text": meta.text,
+                })
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct DataQuery {
+    /// Only the entities whose path contains this string.
+    #[serde(default)]
+    entity: String,
+    /// "scalar", "tensor" or empty for both.
+    #[serde(default)]
+    data_type: String,
+    /// The timeline of `start` and `end`.
+    #[serde(default = "default_timeline")]
+    timeline: String,
+    /// The first time to return, included.
+    start: Option<i64>,
+    /// The last time to return, included.
+    end: Option<i64>,
+    /// "json" (the default) or "arrow".
+    #[serde(default)]
+    format: String,
+}
+
+fn default_timeline() -> String {
+    "log_time".to_string()
+}
+
+#[get("/recordings/{name}/data")]
+async fn get_data(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+    query: web::Query<DataQuery>,
+) -> actix_web::Result<HttpResponse> {
+    let file_path = rrd_path(&data, &name)?;
+    let query = query.into_inner();
+    // Responses are not `Send`, so the blocking task returns the encoded body
+    let (content_type, body) = web::block(move || -> Result<(&str, Vec<u8>)> {
+        let chunks = query_time_range(&file_path, &query)?;
+        match query.format.as_str() {
+            "json" | "" => {
+                let json = serde_json::to_vec(&data_json(&chunks)?)
+                    .map_err(|e| Error::Encode(format!("JSON error: {}", e)))?;
+                Ok(("application/json", json))
+            }
+            "arrow" => Ok((
+                "application/vnd.apache.arrow.stream",
+                arrow_stream(&chunks)?,
+            )),
+            format => Err(Error::Invalid(format!(
+                "Unsupported format: {}. Use \"json\" or \"arrow\"",
+                format
+            ))),
+        }
+    })
+    .await??;
+    Ok(HttpResponse::Ok().content_type(content_type).body(body))
+}
+
+/// List the stores of a recording of the folder.
+pub async fn get_entity_dbs(
+    data: &web::Data<AppState>,
+    rrd_name: &str,
+) -> actix_web::Result<Vec<Value>> {
+    let file_path = rrd_path(data, rrd_name)?;
+    let entity_dbs = web::block(move || {
+        let bundle = load_bundle(&file_path)?;
+
+        let mut entity_dbs = Vec::new();
+        for rrd in bundle.entity_dbs() {
+            entity_dbs.push(json!({
+                "store_id": rrd.store_id().id.as_str(),
+                "kind": store_kind_name(rrd.store_kind()),
+                "application_id": rrd.app_id().map(|id| id.to_string()),
+                "num_rows": rrd.num_rows(),
+                "num_entities": rrd.entity_paths().len(),
+            }));
+        }
+
+        Ok::<_, Error>(entity_dbs)
+    })
+    .await??;
+    Ok(entity_dbs)
+}
+
+/// Resolve the path of a recording, which must be a `.rrd` file directly inside the folder.
+fn rrd_path(data: &AppState, rrd_name: &str) -> Result<String> {
+    folder_rrd_path(&data.folder_path, rrd_name)
+}
+
+fn list_recordings(folder_path: &Path) -> Result<Vec<Value>> {
+    let mut recordings = BTreeMap::new();
+    for entry in fs::read_dir(folder_path).map_err(Error::Open)? {
+        let entry = entry.map_err(Error::Open)?;
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| extension != "rrd") || !path.is_file() {
+            continue;
+        }
+
+        let metadata = entry.metadata().map_err(Error::Open)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs());
+        let name = entry.file_name().to_string_lossy().into_owned();
+        recordings.insert(
+            name.clone(),
+            json!({ "name": name, "size": metadata.len(), "modified": modified }),
+        );
+    }
+    Ok(recordings.into_values().collect())
+}
+
+/// The data chunks of the query, trimmed to its time range.
+fn query_time_range(file_path: &str, query: &DataQuery) -> Result<Vec<Chunk>> {
+    let time_range = (query.start.is_some() || query.end.is_some()).then(|| {
+        (
+            query.start.unwrap_or(i64::MIN),
+            query.end.unwrap_or(i64::MAX),
+        )
+    });
+    let chunks = load_data_chunks(
+        file_path,
+        &query.data_type,
+        &query.entity,
+        time_range,
+        &query.timeline,
+    )?;
+
+    if chunks.is_empty() {
+        return Err(Error::NoData(
+            "No data found for the specified entity and time range".to_string(),
+        ));
+    }
+    Ok(chunks)
+}
+
+/// One object per chunk, with the times of every timeline and one value per row: a number for
+/// scalars and an array of numbers for tensors.
+fn data_json(chunks: &[Chunk]) -> Result<Vec<Value>> {
+    let mut values = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        let data_chunk = to_data_chunk(chunk)?;
+        let rows: Vec<Value> = match &data_chunk.data {
+            ChunkData::Scalar(scalars) => scalars.iter().map(|value| json!(value)).collect(),
+            ChunkData::Tensor(tensors) => (0..tensors.len())
+                .map(|row| {
+                    if !tensors.is_valid(row) {
+                        return Ok(Value::Null);
+                    }
+                    let elements = cast(
+                        tensors.value(row).as_ref(),
+                        &DataType::Float64,
+                        CastOptions::default(),
+                    )
+                    .map_err(|e| Error::Invalid(format!("Data conversion error: {}", e)))?;
+                    let elements = elements
+                        .as_any()
+                        .downcast_ref::<Float64Array>()
+                        .expect("cast to Float64");
+                    Ok(json!(elements.iter().collect::<Vec<_>>()))
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        values.push(json!({
+            "entity_path": data_chunk.entity_path,
+            "timelines": data_chunk.timelines,
+            "data": rows,
+        }));
+    }
+    Ok(values)
+}
+
+/// An Arrow IPC stream of the chunks of a single entity, one record batch per chunk.
+///
+/// The columns are the timelines and components, as in `export_parquet`, and the schema metadata
+/// holds the `entity_path`.
+fn arrow_stream(chunks: &[Chunk]) -> Result<Vec<u8>> {
+    EntityTable::from_chunks(chunks, &[])?.to_ipc_stream()
+}
+
+fn entity_json(entity: &EntitySummary) -> Value {
+    let components: BTreeMap<&str, String> = entity
+        .components
+        .iter()
+        .map(|(name, data_type)| (name.as_str(), type_name(data_type)))
+        .collect();
+    let timelines: BTreeMap<&str, Value> = entity
+        .timelines
+        .iter()
+        .map(|(name, timeline)| {
+            (
+                name.as_str(),
+                json!({
+                    "type": time_type_name(timeline.typ),
+                    "num_rows": timeline.num_rows,
+                    "min": timeline.min,
+                    "max": timeline.max,
+                }),
+            )
+        })
+        .collect();
+
+    json!({
+        "entity_path": entity.entity_path,
+        "num_chunks": entity.num_chunks,
+        "num_rows": entity.num_rows,
+        "num_static_rows": entity.num_static_rows,
+        "components": components,
+        "timelines": timelines,
+    })
+}
@@ -0,0 +1,306 @@
+//! SQL queries over the entities of a recording, built with the `sql` feature.
+//!
+//! Every entity of the recording is a DataFusion table named by its entity path, with a column
+//! per timeline and component as in `export_parquet`:
+//!
+//! ```sql
+//! SELECT avg("Scalar") FROM "/action/gripper"
+//! WHERE log_time BETWEEN '2024-08-20T02:30:14' AND '2024-08-20T02:30:44'
+//! ```
+//!
+//! Tables are converted when a query scans them, and comparisons of a timeline with a constant
+//! only convert the chunk rows in the time range.
+
+use std::{any::Any, collections::BTreeMap, io::Cursor, sync::Arc};
+
+use async_trait::async_trait;
+use datafusion::{
+    arrow::{
+        array::{new_null_array, ArrayRef},
+        datatypes::SchemaRef,
+        error::ArrowError,
+        ipc::{reader::StreamReader, writer::StreamWriter},
+        record_batch::RecordBatch,
+    },
+    catalog::{Session, TableProvider},
+    common::TableReference,
+    datasource::{MemTable, TableType},
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{Between, BinaryExpr, Expr, Operator, TableProviderFilterPushDown},
+    physical_plan::ExecutionPlan,
+    prelude::SessionContext,
+    scalar::ScalarValue,
+};
+use log::warn;
+use re_chunk::Chunk;
+
+use crate::{index::load_recording, query_chunks, rewrite::trim_chunk, EntityTable, Error, Result};
+
+/// A DataFusion table over the chunks of one entity.
+#[derive(Debug)]
+pub struct EntityTableProvider {
+    chunks: Vec<Chunk>,
+    timelines: Vec<String>,
+    schema: SchemaRef,
+}
+
+impl EntityTableProvider {
+    /// Build the table of chunks that must all belong to the same entity.
+    pub fn try_new(chunks: Vec<Chunk>) -> Result<Self> {
+        let schema = to_record_batches(&EntityTable::empty(&chunks, &[])?)?.0;
+
+        let mut timelines: Vec<String> = chunks
+            .iter()
+            .flat_map(|chunk| chunk.timelines().keys())
+            .map(|timeline| timeline.name().to_string())
+            .collect();
+        timelines.sort();
+        timelines.dedup();
+
+        Ok(Self {
+            chunks,
+            timelines,
+            schema,
+        })
+    }
+
+    /// The time range of a timeline selected by a filter, if the filter compares a timeline with
+    /// a constant.
+    fn time_range(&self, filter: &Expr) -> Option<(String, i64, i64)> {
+        match filter {
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                let (timeline, op, time) = match (self.timeline(left), time_literal(right)) {
+                    (Some(timeline), Some(time)) => (timeline, *op, time),
+                    _ => (self.timeline(right)?, op.swap()?, time_literal(left)?),
+                };
+                let (start, end) = match op {
+                    Operator::Eq => (time, time),
+                    Operator::Gt => (time.checked_add(1)?, i64::MAX),
+                    Operator::GtEq => (time, i64::MAX),
+                    Operator::Lt => (i64::MIN, time.checked_sub(1)?),
+                    Operator::LtEq => (i64::MIN, time),
+                    _ => return None,
+                };
+                Some((timeline, start, end))
+            }
+            Expr::Between(Between {
+                expr,
+                negated: false,
+                low,
+                high,
+            }) => Some((
+                self.timeline(expr)?,
+                time_literal(low)?,
+                time_literal(high)?,
+            )),
+            _ => None,
+        }
+    }
+
+    fn timeline(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Column(column) if self.timelines.contains(&column.name) => {
+                Some(column.name.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// The record batches of the rows selected by `filters`, with the schema of the table.
+    fn record_batches(&self, filters: &[Expr]) -> Result<Vec<RecordBatch>> {
+        let mut chunks = self.chunks.clone();
+        for (timeline, start, end) in filters.iter().filter_map(|filter| self.time_range(filter)) {
+            chunks = chunks
+                .iter()
+                .filter_map(|chunk| trim_chunk(chunk, &timeline, Some((start, end))))
+                .collect();
+        }
+        if chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // The selected chunks may lack some columns of the table, or hold no tensor of a tensor
+        // column, which then has the default element type
+        let (_, batches) = to_record_batches(&EntityTable::from_chunks(&chunks, &[])?)?;
+        batches
+            .iter()
+            .map(|batch| {
+                let columns: Vec<ArrayRef> = self
+                    .schema
+                    .fields()
+                    .iter()
+                    .map(|field| {
+                        batch
+                            .column_by_name(field.name())
+                            .filter(|column| column.data_type() == field.data_type())
+                            .cloned()
+                            .unwrap_or_else(|| new_null_array(field.data_type(), batch.num_rows()))
+                    })
+                    .collect();
+                RecordBatch::try_new(self.schema.clone(), columns)
+                    .map_err(|e| Error::Invalid(format!("Data conversion error: {}", e)))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl TableProvider for EntityTableProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let batches = self
+            .record_batches(filters)
+            .map_err(|e| DataFusionError::External(Box::new(e)))?;
+        MemTable::try_new(self.schema.clone(), vec![batches])?
+            .scan(state, projection, &[], limit)
+            .await
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> DataFusionResult<Vec<TableProviderFilterPushDown>> {
+        // Static rows are kept and chunks are trimmed, not filtered row by row
+        Ok(filters
+            .iter()
+            .map(|filter| match self.time_range(filter) {
+                Some(_) => TableProviderFilterPushDown::Inexact,
+                None => TableProviderFilterPushDown::Unsupported,
+            })
+            .collect())
+    }
+}
+
+/// Register every entity of the recording of an RRD file as a table named by its entity path.
+///
+/// Entities whose columns change type between chunks cannot be represented as a table and are
+/// skipped.
+///
+/// # Arguments
+///
+/// * `ctx` - The DataFusion session to register the tables in.
+/// * `file_path` - A string slice that holds the path to the RRD file.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The names of the registered tables.
+pub fn register_recording(ctx: &SessionContext, file_path: &str) -> Result<Vec<String>> {
+    let recording = load_recording(file_path)?;
+    let rrd = recording.entity_db();
+
+    let mut entities: BTreeMap<String, Vec<Chunk>> = BTreeMap::new();
+    for chunk in query_chunks(rrd, |_| true) {
+        entities
+            .entry(chunk.entity_path().to_string())
+            .or_default()
+            .push(chunk);
+    }
+
+    let mut table_names = Vec::with_capacity(entities.len());
+    for (entity_path, chunks) in entities {
+        let table = match EntityTableProvider::try_new(chunks) {
+            Ok(table) => table,
+            Err(e) => {
+                warn!("Skipping entity {}: {}", entity_path, e);
+                continue;
+            }
+        };
+        ctx.register_table(TableReference::bare(entity_path.as_str()), Arc::new(table))
+            .map_err(sql_error)?;
+        table_names.push(entity_path);
+    }
+    Ok(table_names)
+}
+
+/// Run a SQL query over the entities of the recording of an RRD file.
+///
+/// Every entity is a table named by its entity path, to be quoted in the query, e.g.
+/// `SELECT * FROM "/action/gripper"`.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `query` - The SQL query.
+///
+/// # Returns
+///
+/// * `Result<Vec<RecordBatch>>` - The result of the query, or an error if the file cannot be read
+///   or the query is invalid.
+pub fn sql(file_path: &str, query: &str) -> Result<Vec<RecordBatch>> {
+    Ok(run(file_path, query)?.1)
+}
+
+/// Run a SQL query like [`sql`], and encode the result as an Arrow IPC stream.
+pub fn sql_ipc(file_path: &str, query: &str) -> Result<Vec<u8>> {
+    let (schema, batches) = run(file_path, query)?;
+
+    let encode_error = |e: ArrowError| Error::Encode(format!("Arrow IPC error: {}", e));
+    let mut writer = StreamWriter::try_new(Vec::new(), &schema).map_err(encode_error)?;
+    for batch in &batches {
+        writer.write(batch).map_err(encode_error)?;
+    }
+    writer.into_inner().map_err(encode_error)
+}
+
+fn run(file_path: &str, query: &str) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+    let ctx = SessionContext::new();
+    register_recording(&ctx, file_path)?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .map_err(|e| Error::Invalid(format!("Runtime error: {}", e)))?;
+    runtime
+        .block_on(async {
+            let df = ctx.sql(query).await?;
+            let schema = Arc::new(df.schema().as_arrow().clone());
+            Ok((schema, df.collect().await?))
+        })
+        .map_err(sql_error)
+}
+
+/// Convert a table into DataFusion record batches, through the Arrow IPC format.
+fn to_record_batches(table: &EntityTable) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+    let reader = StreamReader::try_new(Cursor::new(table.to_ipc_stream()?), None)
+        .map_err(|e| Error::Decode(format!("Arrow IPC error: {}", e)))?;
+    let schema = reader.schema();
+    let batches = reader
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::Decode(format!("Arrow IPC error: {}", e)))?;
+    Ok((schema, batches))
+}
+
+/// The nanoseconds or sequence number of a constant compared with a timeline.
+fn time_literal(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Literal(ScalarValue::TimestampNanosecond(Some(time), _))
+        | Expr::Literal(ScalarValue::Int64(Some(time))) => Some(*time),
+        _ => None,
+    }
+}
+
+fn sql_error(e: DataFusionError) -> Error {
+    match e {
+        DataFusionError::External(e) => match e.downcast::<Error>() {
+            Ok(e) => *e,
+            Err(e) => Error::Invalid(e.to_string()),
+        },
+        e => Error::Invalid(e.to_string()),
+    }
+}
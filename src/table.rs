@@ -0,0 +1,254 @@
+//! The rows of one entity as an Arrow table, for the services that stream record batches.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use re_arrow2::{
+    array::Array,
+    chunk::Chunk as ArrowChunk,
+    datatypes::{Field, Schema},
+    io::ipc::write::{StreamWriter, WriteOptions},
+};
+use re_chunk::Chunk;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    columns::{self, Batch},
+    index::load_action_recording,
+    matches_entity_path, query_chunks,
+    rewrite::trim_chunk,
+    Error, Result,
+};
+
+/// The rows of an entity to query, see [`query_entity_table`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableQuery {
+    /// The path of the entity, or a string contained in the path of a single entity.
+    #[serde(default)]
+    pub entity: String,
+    /// The timeline of `start` and `end`.
+    #[serde(default = "default_timeline")]
+    pub timeline: String,
+    /// The first time to return, included.
+    #[serde(default)]
+    pub start: Option<i64>,
+    /// The last time to return, included.
+    #[serde(default)]
+    pub end: Option<i64>,
+    /// The components to return, by column name, e.g. `Scalar`. All components if empty.
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+impl Default for TableQuery {
+    fn default() -> Self {
+        Self {
+            entity: String::new(),
+            timeline: default_timeline(),
+            start: None,
+            end: None,
+            components: Vec::new(),
+        }
+    }
+}
+
+fn default_timeline() -> String {
+    "log_time".to_string()
+}
+
+/// The rows of one entity: a column per timeline and component, a record batch per chunk.
+#[derive(Debug, Clone)]
+pub struct EntityTable {
+    /// The path of the entity, also stored in the `entity_path` schema metadata.
+    pub entity_path: String,
+    /// The schema shared by the batches.
+    pub schema: Schema,
+    /// The record batches, one per chunk.
+    pub batches: Vec<ArrowChunk<Box<dyn Array>>>,
+}
+
+impl EntityTable {
+    /// Build the table of chunks that must all belong to the same entity.
+    ///
+    /// The columns are the ones written by `export_parquet`. Only the timelines and `components`
+    /// are kept, unless `components` is empty.
+    pub fn from_chunks(chunks: &[Chunk], components: &[String]) -> Result<Self> {
+        Self::build(chunks, components, true)
+    }
+
+    /// Build the table of chunks like [`EntityTable::from_chunks`], with its schema but without
+    /// converting any row.
+    pub fn empty(chunks: &[Chunk], components: &[String]) -> Result<Self> {
+        Self::build(chunks, components, false)
+    }
+
+    fn build(chunks: &[Chunk], components: &[String], with_rows: bool) -> Result<Self> {
+        let Some(first) = chunks.first() else {
+            return Err(Error::NoData("No chunks to convert".to_string()));
+        };
+        let entity_path = first.entity_path().to_string();
+        if chunks
+            .iter()
+            .any(|chunk| chunk.entity_path() != first.entity_path())
+        {
+            return Err(Error::Invalid(format!(
+                "A table holds a single entity, narrow the entity filter: {}",
+                entity_paths(chunks)
+                    .into_iter()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        let (schema, batches) = if with_rows {
+            columns::entity_table(chunks)
+        } else {
+            columns::entity_schema(chunks).map(|schema| (schema, Vec::new()))
+        }
+        .map_err(|e| Error::Invalid(format!("Data conversion error: {}", e)))?;
+        let (fields, batches) = if components.is_empty() {
+            (schema.fields, batches)
+        } else {
+            select_components(chunks, schema.fields, batches, components)?
+        };
+
+        let schema = Schema::from(fields).with_metadata(BTreeMap::from([(
+            "entity_path".to_string(),
+            entity_path.clone(),
+        )]));
+        Ok(Self {
+            entity_path,
+            schema,
+            batches,
+        })
+    }
+
+    /// The number of rows of the table.
+    pub fn num_rows(&self) -> usize {
+        self.batches.iter().map(|batch| batch.len()).sum()
+    }
+
+    /// Encode the table as an Arrow IPC stream.
+    pub fn to_ipc_stream(&self) -> Result<Vec<u8>> {
+        let encode_error =
+            |e: re_arrow2::error::Error| Error::Encode(format!("Arrow IPC error: {}", e));
+        let mut writer = StreamWriter::new(Vec::new(), WriteOptions { compression: None });
+        writer.start(&self.schema, None).map_err(encode_error)?;
+        for batch in &self.batches {
+            writer.write(batch, None).map_err(encode_error)?;
+        }
+        writer.finish().map_err(encode_error)?;
+        Ok(writer.into_inner())
+    }
+}
+
+/// Query the rows of one entity of the recording of an RRD file, trimmed to a time range.
+///
+/// The entity filter must select a single entity: either its exact path, or a string contained
+/// in the path of only one entity.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `query` - The entity, time range and components to return.
+///
+/// # Returns
+///
+/// * `Result<EntityTable>` - The table of the entity, or an error if no rows or several entities
+///   match.
+pub fn query_entity_table(file_path: &str, query: &TableQuery) -> Result<EntityTable> {
+    let time_range = (query.start.is_some() || query.end.is_some()).then(|| {
+        (
+            query.start.unwrap_or(i64::MIN),
+            query.end.unwrap_or(i64::MAX),
+        )
+    });
+    let recording = load_action_recording(file_path, |entry| {
+        entry.entity_path.contains(&query.entity) && entry.overlaps(&query.timeline, time_range)
+    })?;
+
+    let mut chunks: Vec<Chunk> = query_chunks(recording.entity_db(), |chunk| {
+        matches_entity_path(chunk, &query.entity)
+    })
+    .iter()
+    .filter_map(|chunk| trim_chunk(chunk, &query.timeline, time_range))
+    .collect();
+
+    // An exact path selects its entity only, not the entities below it
+    if chunks
+        .iter()
+        .any(|chunk| chunk.entity_path().to_string() == query.entity)
+    {
+        chunks.retain(|chunk| chunk.entity_path().to_string() == query.entity);
+    }
+
+    if chunks.is_empty() {
+        return Err(Error::NoData(
+            "No data found for the specified entity and time range".to_string(),
+        )
+        .with_path(file_path)
+        .with_entity(&query.entity));
+    }
+    EntityTable::from_chunks(&chunks, &query.components)
+}
+
+fn entity_paths(chunks: &[Chunk]) -> BTreeSet<String> {
+    chunks
+        .iter()
+        .map(|chunk| chunk.entity_path().to_string())
+        .collect()
+}
+
+/// Keep the timeline columns and the columns of `components`, in the order of the schema.
+fn select_components(
+    chunks: &[Chunk],
+    fields: Vec<Field>,
+    batches: Vec<Batch>,
+    components: &[String],
+) -> Result<(Vec<Field>, Vec<Batch>)> {
+    if let Some(missing) = components
+        .iter()
+        .find(|component| !fields.iter().any(|field| &field.name == *component))
+    {
+        return Err(Error::Invalid(format!(
+            "Unknown component: {}. Available columns: {}",
+            missing,
+            fields
+                .iter()
+                .map(|field| field.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+
+    let timelines: BTreeSet<&str> = chunks
+        .iter()
+        .flat_map(|chunk| chunk.timelines().keys())
+        .map(|timeline| timeline.name().as_str())
+        .collect();
+    let selected: Vec<usize> = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| {
+            timelines.contains(field.name.as_str()) || components.contains(&field.name)
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    let fields = selected
+        .iter()
+        .map(|&index| fields[index].clone())
+        .collect();
+    let batches = batches
+        .into_iter()
+        .map(|batch| {
+            let arrays = batch.arrays();
+            Batch::new(
+                selected
+                    .iter()
+                    .map(|&index| arrays[index].clone())
+                    .collect(),
+            )
+        })
+        .collect();
+    Ok((fields, batches))
+}
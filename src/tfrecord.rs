@@ -0,0 +1,217 @@
+//! Export of RRD recordings to TFRecord files of `tf.train.Example` records.
+//!
+//! Every record is one time-aligned sample of the selected entities, and the records are split
+//! into shards of a fixed number of samples:
+//!
+//! ```text
+//! <out_dir>/<rrd_stem>-00000-of-00003.tfrecord
+//! <out_dir>/<rrd_stem>-00001-of-00003.tfrecord
+//! <out_dir>/<rrd_stem>-00002-of-00003.tfrecord
+//! ```
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use log::debug;
+
+use crate::{
+    index::load_recording,
+    resample::{load_aligned_features, FeatureSamples},
+    Error, Result,
+};
+
+/// Added to the rotated CRC32C of every length and payload, as in TensorFlow's `crc32c::Mask`.
+const CRC_MASK_DELTA: u32 = 0xa282_ead8;
+
+/// Export the data entities of an RRD file to sharded TFRecord files.
+///
+/// Entities are selected like `query_data_entities`, plus the `EncodedImage` entities matching
+/// `entity_path_filter` when `data_type_filter` is empty. They are resampled to `fps` on the
+/// given temporal timeline, using the latest sample at or before each frame, over the time range
+/// covered by every entity. Every sample is a `tf.train.Example` with the features:
+///
+/// * `<feature>` - a `float_list` for scalar and tensor entities, a `bytes_list` holding the
+///   encoded JPEG or PNG for image entities. The feature key is the entity path with dots as
+///   separators, e.g. `action.joints.position`.
+/// * `timestamp` - a `float_list` with the seconds since the first sample.
+/// * `frame_index` - an `int64_list` with the index of the sample.
+/// * `<timeline>` - an `int64_list` with the time of the sample in nanoseconds.
+///
+/// Entities whose feature key is one of the last three keys are rejected.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `out_dir` - The directory of the shards. Created if missing.
+/// * `data_type_filter` - A string slice that specifies the type of data to filter. Can be either "scalar", "tensor", or empty string for all data.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to export all data.
+/// * `fps` - The number of samples per second.
+/// * `timeline` - The temporal timeline used to align the entities.
+/// * `samples_per_shard` - The maximum number of samples in one shard.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The paths of the written shards.
+pub fn export_tfrecord(
+    file_path: &str,
+    out_dir: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    fps: u32,
+    timeline: &str,
+    samples_per_shard: usize,
+) -> Result<Vec<String>> {
+    if fps == 0 || samples_per_shard == 0 {
+        return Err(Error::Invalid(
+            "fps and samples_per_shard must be positive".to_string(),
+        ));
+    }
+
+    let recording = load_recording(file_path)?;
+    let rrd = recording.entity_db();
+    let aligned = load_aligned_features(rrd, data_type_filter, entity_path_filter, timeline, fps)
+        .map_err(|e| e.with_path(file_path).with_entity(entity_path_filter))?;
+    // The entity features share the feature map of the example with the time of the sample
+    if let Some((key, ..)) = aligned
+        .features
+        .iter()
+        .find(|(key, ..)| ["timestamp", "frame_index", timeline].contains(&key.as_str()))
+    {
+        return Err(Error::Invalid(format!(
+            "The feature key {} is reserved for the time of the samples, exclude its entity",
+            key
+        ))
+        .with_path(file_path));
+    }
+
+    let num_samples = aligned.frame_times.len();
+    let num_shards = num_samples.div_ceil(samples_per_shard);
+    debug!("Writing {} samples to {} shards", num_samples, num_shards);
+
+    let out_dir = Path::new(out_dir);
+    fs::create_dir_all(out_dir).map_err(Error::Write)?;
+    let stem = Path::new(file_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let start = aligned.frame_times[0];
+    let mut written = Vec::new();
+    for shard in 0..num_shards {
+        let path = out_dir.join(format!(
+            "{}-{:05}-of-{:05}.tfrecord",
+            stem, shard, num_shards
+        ));
+        let mut writer = BufWriter::new(File::create(&path).map_err(Error::Write)?);
+
+        let first = shard * samples_per_shard;
+        let last = (first + samples_per_shard).min(num_samples);
+        for frame in first..last {
+            let time = aligned.frame_times[frame];
+            let mut features = Vec::with_capacity(aligned.features.len() + 3);
+            for (key, samples, rows) in &aligned.features {
+                let row = rows[frame];
+                let feature = match samples {
+                    FeatureSamples::Numeric { values, .. } => float_feature(&values[row]),
+                    FeatureSamples::Image { frames, .. } => bytes_feature(&frames[row]),
+                };
+                features.push((key.as_str(), feature));
+            }
+            features.push((
+                "timestamp",
+                float_feature(&[((time - start) as f64 * 1e-9) as f32]),
+            ));
+            features.push(("frame_index", int64_feature(&[frame as i64])));
+            features.push((timeline, int64_feature(&[time])));
+
+            write_record(&mut writer, &example(&features)).map_err(Error::Write)?;
+        }
+        writer.flush().map_err(Error::Write)?;
+        written.push(path.to_string_lossy().into_owned());
+    }
+
+    Ok(written)
+}
+
+/// Write one TFRecord: the payload length and the payload, each followed by its masked CRC32C.
+fn write_record(w: &mut impl Write, data: &[u8]) -> std::io::Result<()> {
+    let len = (data.len() as u64).to_le_bytes();
+    w.write_all(&len)?;
+    w.write_all(&masked_crc(&len).to_le_bytes())?;
+    w.write_all(data)?;
+    w.write_all(&masked_crc(data).to_le_bytes())
+}
+
+fn masked_crc(bytes: &[u8]) -> u32 {
+    let crc = crc32c::crc32c(bytes);
+    crc.rotate_right(15).wrapping_add(CRC_MASK_DELTA)
+}
+
+/// Encode a `tf.train.Example` from its encoded `tf.train.Feature`s.
+fn example(features: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    // Features { map<string, Feature> feature = 1; }, every map entry is a key/value message
+    let mut map = Vec::new();
+    for (key, feature) in features {
+        let mut entry = Vec::new();
+        put_bytes_field(&mut entry, 1, key.as_bytes());
+        put_bytes_field(&mut entry, 2, feature);
+        put_bytes_field(&mut map, 1, &entry);
+    }
+    // Example { Features features = 1; }
+    let mut example = Vec::new();
+    put_bytes_field(&mut example, 1, &map);
+    example
+}
+
+/// A `Feature` holding a `BytesList` (field 1) of one value.
+fn bytes_feature(value: &[u8]) -> Vec<u8> {
+    let mut list = Vec::new();
+    put_bytes_field(&mut list, 1, value);
+    let mut feature = Vec::new();
+    put_bytes_field(&mut feature, 1, &list);
+    feature
+}
+
+/// A `Feature` holding a packed `FloatList` (field 2).
+fn float_feature(values: &[f32]) -> Vec<u8> {
+    let packed: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    let mut list = Vec::new();
+    put_bytes_field(&mut list, 1, &packed);
+    let mut feature = Vec::new();
+    put_bytes_field(&mut feature, 2, &list);
+    feature
+}
+
+/// A `Feature` holding a packed `Int64List` (field 3).
+fn int64_feature(values: &[i64]) -> Vec<u8> {
+    let mut packed = Vec::new();
+    for value in values {
+        put_varint(&mut packed, *value as u64);
+    }
+    let mut list = Vec::new();
+    put_bytes_field(&mut list, 1, &packed);
+    let mut feature = Vec::new();
+    put_bytes_field(&mut feature, 3, &list);
+    feature
+}
+
+/// Append a length-delimited protobuf field.
+fn put_bytes_field(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_varint(buf, ((field as u64) << 3) | 2);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
@@ -0,0 +1,143 @@
+//! A JavaScript API over recordings held in memory, built with the `wasm` feature for
+//! `wasm32-unknown-unknown` and bound with `wasm-bindgen`. It never touches the filesystem: the
+//! recording is decoded from memory by [`read_bundle`] and queried on the calling thread, and the
+//! rayon based batch queries are not built for `wasm32`.
+//!
+//! ```js
+//! const recording = new Recording(new Uint8Array(await file.arrayBuffer()));
+//! const entities = recording.entityPaths();
+//! const gripper = recording.scalarSeries("/action/gripper/left/position", "log_time");
+//! ```
+
+use re_entity_db::StoreBundle;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    bundle_entity_paths, get_action_entity_db, is_scalar_chunk, meta_entities, query_entity_chunks,
+    read_bundle, to_data_chunk, ChunkData, Error,
+};
+
+/// A recording decoded from the bytes of an RRD file.
+#[wasm_bindgen]
+pub struct Recording {
+    bundle: StoreBundle,
+}
+
+#[wasm_bindgen]
+impl Recording {
+    /// Decode the bytes of an RRD file.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<Recording, JsError> {
+        Ok(Self {
+            bundle: read_bundle(bytes)?,
+        })
+    }
+
+    /// The entity paths of every store of the recording.
+    #[wasm_bindgen(js_name = entityPaths)]
+    pub fn entity_paths(&self) -> Vec<String> {
+        bundle_entity_paths(&self.bundle)
+    }
+
+    /// The text entities logged exactly at `entityPath`, or of all entities if it is empty.
+    #[wasm_bindgen(js_name = queryMeta)]
+    pub fn query_meta(&self, entity_path: &str) -> Result<Vec<MetaEntity>, JsError> {
+        let rrd = get_action_entity_db(&self.bundle)?;
+        Ok(meta_entities(rrd, entity_path)?
+            .into_iter()
+            .map(|meta| MetaEntity {
+                entity_path: meta.entity_path,
+                media_type: meta.media_type,
+                text: meta.text,
+            })
+            .collect())
+    }
+
+    /// The scalars logged exactly at `entityPath`, sorted by their time on `timeline`.
+    ///
+    /// Rows without a time on the timeline are left out.
+    #[wasm_bindgen(js_name = scalarSeries)]
+    pub fn scalar_series(
+        &self,
+        entity_path: &str,
+        timeline: &str,
+    ) -> Result<ScalarSeries, JsError> {
+        let rrd = get_action_entity_db(&self.bundle)?;
+        let chunks = query_entity_chunks(rrd, entity_path, is_scalar_chunk);
+        if chunks.is_empty() {
+            return Err(
+                Error::NoData(format!("No scalars found for entity {}", entity_path)).into(),
+            );
+        }
+
+        let mut rows = Vec::new();
+        for chunk in &chunks {
+            let data_chunk = to_data_chunk(chunk)?;
+            let (Some(times), ChunkData::Scalar(values)) =
+                (data_chunk.timelines.get(timeline), &data_chunk.data)
+            else {
+                continue;
+            };
+            rows.extend(
+                times
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(time, value)| (*time, value.copied().unwrap_or(f64::NAN))),
+            );
+        }
+        if rows.is_empty() {
+            return Err(Error::Invalid(format!(
+                "The scalars of entity {} have no time on timeline {}",
+                entity_path, timeline
+            ))
+            .into());
+        }
+        rows.sort_by_key(|(time, _)| *time);
+
+        Ok(ScalarSeries {
+            times: rows.iter().map(|(time, _)| *time).collect(),
+            values: rows.iter().map(|(_, value)| *value).collect(),
+        })
+    }
+}
+
+/// A text entity of a recording.
+#[wasm_bindgen(getter_with_clone)]
+pub struct MetaEntity {
+    /// The path of the entity.
+    #[wasm_bindgen(js_name = entityPath)]
+    pub entity_path: String,
+    /// The media type of the text, such as text/plain.
+    #[wasm_bindgen(js_name = mediaType)]
+    pub media_type: String,
+    /// The text.
+    pub text: String,
+}
+
+/// The scalars of an entity over a timeline.
+#[wasm_bindgen]
+pub struct ScalarSeries {
+    times: Vec<i64>,
+    values: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl ScalarSeries {
+    /// The number of rows.
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.values.len()
+    }
+
+    /// The time of every row as a `BigInt64Array`: nanoseconds for temporal timelines.
+    #[wasm_bindgen(getter)]
+    pub fn times(&self) -> Vec<i64> {
+        self.times.clone()
+    }
+
+    /// The value of every row as a `Float64Array`, NaN for rows without a value.
+    #[wasm_bindgen(getter)]
+    pub fn values(&self) -> Vec<f64> {
+        self.values.clone()
+    }
+}
@@ -0,0 +1,165 @@
+//! Export of RRD recordings to WebDataset `.tar` shards.
+//!
+//! Every sample is one time-aligned frame of the selected entities, stored as a group of files
+//! sharing the sample key:
+//!
+//! ```text
+//! 000000042.json
+//! 000000042.action.joints.position.npy
+//! 000000042.observation.images.cam_high.jpg
+//! ```
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use log::debug;
+use serde_json::json;
+use tar::{Builder, Header};
+
+use crate::{
+    index::load_recording,
+    mcap,
+    npz::NpyArray,
+    resample::{load_aligned_features, FeatureSamples},
+    Error, Result,
+};
+
+/// Export the data entities of an RRD file to WebDataset `.tar` shards.
+///
+/// Entities are selected and resampled like [`crate::tfrecord::export_tfrecord`]. Every sample
+/// holds the files:
+///
+/// * `<key>.json` - the `frame_index`, the `timestamp` in seconds since the first sample, and the
+///   time of the sample in nanoseconds under the name of the timeline.
+/// * `<key>.<feature>.npy` - a `float32` vector for scalar and tensor entities.
+/// * `<key>.<feature>.jpg` or `<key>.<feature>.png` - the encoded image for image entities.
+///
+/// The feature name is the entity path with dots as separators, e.g. `action.joints.position`,
+/// and the key is the zero-padded frame index. Shards are named `<rrd_stem>-000000.tar`,
+/// `<rrd_stem>-000001.tar`, ...
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `out_dir` - The directory of the shards. Created if missing.
+/// * `data_type_filter` - A string slice that specifies the type of data to filter. Can be either "scalar", "tensor", or empty string for all data.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to export all data.
+/// * `fps` - The number of samples per second.
+/// * `timeline` - The temporal timeline used to align the entities.
+/// * `samples_per_shard` - The maximum number of samples in one shard.
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The paths of the written shards.
+pub fn export_webdataset(
+    file_path: &str,
+    out_dir: &str,
+    data_type_filter: &str,
+    entity_path_filter: &str,
+    fps: u32,
+    timeline: &str,
+    samples_per_shard: usize,
+) -> Result<Vec<String>> {
+    if fps == 0 || samples_per_shard == 0 {
+        return Err(Error::Invalid(
+            "fps and samples_per_shard must be positive".to_string(),
+        ));
+    }
+
+    let recording = load_recording(file_path)?;
+    let rrd = recording.entity_db();
+    let aligned = load_aligned_features(rrd, data_type_filter, entity_path_filter, timeline, fps)
+        .map_err(|e| e.with_path(file_path).with_entity(entity_path_filter))?;
+
+    let num_samples = aligned.frame_times.len();
+    debug!(
+        "Writing {} samples to {} shards",
+        num_samples,
+        num_samples.div_ceil(samples_per_shard)
+    );
+
+    let out_dir = Path::new(out_dir);
+    fs::create_dir_all(out_dir).map_err(Error::Write)?;
+    let stem = Path::new(file_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let start = aligned.frame_times[0];
+    let mut written = Vec::new();
+    for (shard, first) in (0..num_samples).step_by(samples_per_shard).enumerate() {
+        let path = out_dir.join(format!("{}-{:06}.tar", stem, shard));
+        let mut builder = Builder::new(BufWriter::new(File::create(&path).map_err(Error::Write)?));
+
+        let last = (first + samples_per_shard).min(num_samples);
+        for frame in first..last {
+            let key = format!("{:09}", frame);
+            let time = aligned.frame_times[frame];
+
+            let info = json!({
+                "frame_index": frame,
+                "timestamp": (time - start) as f64 * 1e-9,
+                timeline: time,
+            });
+            append_file(
+                &mut builder,
+                &format!("{}.json", key),
+                info.to_string().as_bytes(),
+            )
+            .map_err(Error::Write)?;
+
+            for (feature, samples, rows) in &aligned.features {
+                let row = rows[frame];
+                match samples {
+                    FeatureSamples::Numeric { values, .. } => {
+                        let array = NpyArray {
+                            descr: "<f4",
+                            shape: vec![values[row].len()],
+                            data: values[row].iter().flat_map(|v| v.to_le_bytes()).collect(),
+                        };
+                        let mut bytes = Vec::new();
+                        array.write(&mut bytes).map_err(Error::Write)?;
+                        append_file(&mut builder, &format!("{}.{}.npy", key, feature), &bytes)
+                    }
+                    FeatureSamples::Image { frames, .. } => {
+                        let extension = match mcap::sniff_media_type(&frames[row]) {
+                            Some("image/png") => "png",
+                            Some("image/jpeg") => "jpg",
+                            _ => "bin",
+                        };
+                        append_file(
+                            &mut builder,
+                            &format!("{}.{}.{}", key, feature, extension),
+                            &frames[row],
+                        )
+                    }
+                }
+                .map_err(Error::Write)?;
+            }
+        }
+
+        builder
+            .into_inner()
+            .and_then(|mut writer| writer.flush())
+            .map_err(Error::Write)?;
+        written.push(path.to_string_lossy().into_owned());
+    }
+
+    Ok(written)
+}
+
+/// Append a regular file to the archive. GNU headers allow names longer than 100 bytes.
+fn append_file<W: Write>(
+    builder: &mut Builder<W>,
+    name: &str,
+    bytes: &[u8],
+) -> std::io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(0);
+    builder.append_data(&mut header, name, bytes)
+}
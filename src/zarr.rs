@@ -0,0 +1,320 @@
+//! Export of RRD data entities to Zarr directory stores.
+//!
+//! The store mirrors the entity hierarchy: every entity path part becomes a group, and the arrays
+//! of an entity (see [`npz::entity_arrays`]) are stored inside the group of the entity, e.g.
+//!
+//! ```text
+//! <out_dir>/action/joints/position/TensorData
+//! <out_dir>/action/joints/position/log_time
+//! ```
+//!
+//! Both the Zarr v2 (`.zgroup` / `.zarray`) and v3 (`zarr.json`) layouts are supported.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression as GzCompression};
+use log::debug;
+use re_chunk::Chunk;
+use serde_json::{json, Value};
+
+use crate::{
+    index::load_recording,
+    npz::{self, NpyArray},
+    query_data_chunks, relative_entity_path, Error, Result,
+};
+
+const ZSTD_LEVEL: i32 = 3;
+const GZIP_LEVEL: u32 = 5;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ZarrFormat {
+    V2,
+    V3,
+}
+
+#[derive(Clone, Copy)]
+enum Codec {
+    Zstd,
+    Gzip,
+    None,
+}
+
+impl Codec {
+    fn parse(compression: &str) -> Result<Self> {
+        match compression {
+            "zstd" => Ok(Self::Zstd),
+            "gzip" => Ok(Self::Gzip),
+            "none" | "" => Ok(Self::None),
+            _ => Err(Error::Invalid(format!(
+                "Unsupported compression: {}. Use \"zstd\", \"gzip\" or \"none\"",
+                compression
+            ))),
+        }
+    }
+
+    fn encode(self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Zstd => zstd::encode_all(bytes, ZSTD_LEVEL),
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), GzCompression::new(GZIP_LEVEL));
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Self::None => Ok(bytes.to_vec()),
+        }
+    }
+
+    /// The v2 `compressor` entry, as understood by numcodecs.
+    fn v2_compressor(self) -> Value {
+        match self {
+            Self::Zstd => json!({ "id": "zstd", "level": ZSTD_LEVEL }),
+            Self::Gzip => json!({ "id": "gzip", "level": GZIP_LEVEL }),
+            Self::None => Value::Null,
+        }
+    }
+
+    /// The v3 `codecs` pipeline.
+    fn v3_codecs(self) -> Value {
+        let bytes = json!({ "name": "bytes", "configuration": { "endian": "little" } });
+        match self {
+            Self::Zstd => json!([
+                bytes,
+                { "name": "zstd", "configuration": { "level": ZSTD_LEVEL, "checksum": false } },
+            ]),
+            Self::Gzip => {
+                json!([bytes, { "name": "gzip", "configuration": { "level": GZIP_LEVEL } }])
+            }
+            Self::None => json!([bytes]),
+        }
+    }
+}
+
+/// Export the data entities (scalar or tensor) of an RRD file to a Zarr directory store.
+///
+/// Groups mirror the entity hierarchy, and every entity group holds one array per component and
+/// one per timeline, like [`npz::export_npz`]: `Scalar` (`float64`), `TensorData` (the native
+/// tensor type, shape `(rows, *tensor_shape)`) and one `int64` array per timeline. Arrays are
+/// chunked along the row axis only, and missing floating point values are NaN.
+///
+/// # Arguments
+///
+/// * `file_path` - A string slice that holds the path to the RRD file.
+/// * `out_dir` - The root directory of the store. Created if missing.
+/// * `entity_path_filter` - A string slice that holds the specific entity path to filter. Set to empty string to export all data.
+/// * `zarr_format` - The Zarr format version, 2 or 3.
+/// * `chunk_rows` - The number of rows per chunk.
+/// * `compression` - The compression codec to use: "zstd", "gzip" or "none".
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The paths of the written arrays, relative to `out_dir`.
+pub fn export_zarr(
+    file_path: &str,
+    out_dir: &str,
+    entity_path_filter: &str,
+    zarr_format: u8,
+    chunk_rows: usize,
+    compression: &str,
+) -> Result<Vec<String>> {
+    let zarr_format = match zarr_format {
+        2 => ZarrFormat::V2,
+        3 => ZarrFormat::V3,
+        _ => {
+            return Err(Error::Invalid(format!(
+                "Unsupported Zarr format: {}. Use 2 or 3",
+                zarr_format
+            )))
+        }
+    };
+    if chunk_rows == 0 {
+        return Err(Error::Invalid("chunk_rows must be positive".to_string()));
+    }
+    let codec = Codec::parse(compression)?;
+
+    let recording = load_recording(file_path)?;
+    let rrd = recording.entity_db();
+
+    let mut chunks_per_entity: BTreeMap<String, Vec<Chunk>> = BTreeMap::new();
+    for chunk in query_data_chunks(rrd, "", entity_path_filter) {
+        chunks_per_entity
+            .entry(chunk.entity_path().to_string())
+            .or_default()
+            .push(chunk);
+    }
+
+    if chunks_per_entity.is_empty() {
+        return Err(
+            Error::NoData("No data found for the specified entity".to_string())
+                .with_path(file_path)
+                .with_entity(entity_path_filter),
+        );
+    }
+
+    let out_dir = Path::new(out_dir);
+
+    // The root and every entity path prefix are groups
+    let mut groups: BTreeSet<String> = BTreeSet::new();
+    groups.insert(String::new());
+    for entity_path in chunks_per_entity.keys() {
+        let relative = relative_entity_path(entity_path).map_err(|e| e.with_path(file_path))?;
+        let mut group = String::new();
+        for part in relative.split('/').filter(|part| !part.is_empty()) {
+            if !group.is_empty() {
+                group.push('/');
+            }
+            group.push_str(part);
+            groups.insert(group.clone());
+        }
+    }
+    for group in &groups {
+        write_group(&out_dir.join(group), zarr_format).map_err(Error::Write)?;
+    }
+
+    let mut written = Vec::new();
+    for (entity_path, chunks) in &chunks_per_entity {
+        debug!("Converting {} chunks of {}", chunks.len(), entity_path);
+        let arrays = npz::entity_arrays(chunks)
+            .map_err(|e| Error::Invalid(format!("{}: {}", entity_path, e)))?;
+
+        for (name, array) in &arrays {
+            let path = format!("{}/{}", entity_path.trim_matches('/'), name);
+            let path = path.trim_start_matches('/').to_string();
+            write_array(&out_dir.join(&path), array, zarr_format, chunk_rows, codec)
+                .map_err(Error::Write)?;
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
+
+fn write_group(dir: &Path, zarr_format: ZarrFormat) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    match zarr_format {
+        ZarrFormat::V2 => fs::write(dir.join(".zgroup"), json!({ "zarr_format": 2 }).to_string()),
+        ZarrFormat::V3 => fs::write(
+            dir.join("zarr.json"),
+            json!({ "zarr_format": 3, "node_type": "group", "attributes": {} }).to_string(),
+        ),
+    }
+}
+
+/// Write the metadata and the chunks of one array.
+fn write_array(
+    dir: &Path,
+    array: &NpyArray,
+    zarr_format: ZarrFormat,
+    chunk_rows: usize,
+    codec: Codec,
+) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let num_rows = array.shape[0];
+    let mut chunk_shape = array.shape.clone();
+    chunk_shape[0] = chunk_rows;
+
+    let is_float = array.descr.starts_with("<f");
+    let fill_value = if is_float { json!("NaN") } else { json!(0) };
+
+    let metadata = match zarr_format {
+        ZarrFormat::V2 => json!({
+            "zarr_format": 2,
+            "shape": array.shape,
+            "chunks": chunk_shape,
+            "dtype": array.descr,
+            "compressor": codec.v2_compressor(),
+            "fill_value": fill_value,
+            "order": "C",
+            "filters": null,
+            "dimension_separator": ".",
+        }),
+        ZarrFormat::V3 => json!({
+            "zarr_format": 3,
+            "node_type": "array",
+            "shape": array.shape,
+            "data_type": v3_data_type(array.descr),
+            "chunk_grid": {
+                "name": "regular",
+                "configuration": { "chunk_shape": chunk_shape },
+            },
+            "chunk_key_encoding": {
+                "name": "default",
+                "configuration": { "separator": "/" },
+            },
+            "fill_value": fill_value,
+            "codecs": codec.v3_codecs(),
+            "attributes": {},
+        }),
+    };
+    let metadata_file = match zarr_format {
+        ZarrFormat::V2 => ".zarray",
+        ZarrFormat::V3 => "zarr.json",
+    };
+    fs::write(dir.join(metadata_file), metadata.to_string())?;
+
+    let row_size = array.shape[1..].iter().product::<usize>() * npz::item_size(array.descr);
+    let fill = fill_bytes(array.descr);
+
+    for (index, start) in (0..num_rows).step_by(chunk_rows).enumerate() {
+        let end = (start + chunk_rows).min(num_rows);
+        let mut bytes = array.data[start * row_size..end * row_size].to_vec();
+        // Zarr chunks always have the full chunk shape, the edge chunk is padded
+        let padding = (chunk_rows - (end - start)) * row_size;
+        bytes.extend(fill.iter().copied().cycle().take(padding));
+
+        // The chunk index along the other axes is always 0
+        let key = chunk_key(zarr_format, index, array.shape.len());
+        let path = dir.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, codec.encode(&bytes)?)?;
+    }
+
+    Ok(())
+}
+
+fn chunk_key(zarr_format: ZarrFormat, index: usize, num_dims: usize) -> PathBuf {
+    let mut indices = vec![index.to_string()];
+    indices.extend(std::iter::repeat_n("0".to_string(), num_dims - 1));
+    match zarr_format {
+        ZarrFormat::V2 => PathBuf::from(indices.join(".")),
+        ZarrFormat::V3 => {
+            let mut path = PathBuf::from("c");
+            path.extend(indices);
+            path
+        }
+    }
+}
+
+/// The little endian bytes of one element holding the fill value.
+fn fill_bytes(descr: &str) -> Vec<u8> {
+    match descr {
+        "<f2" => vec![0x00, 0x7e],
+        "<f4" => f32::NAN.to_le_bytes().to_vec(),
+        "<f8" => f64::NAN.to_le_bytes().to_vec(),
+        _ => vec![0; npz::item_size(descr)],
+    }
+}
+
+/// Map a NumPy type string to a Zarr v3 data type name.
+fn v3_data_type(descr: &str) -> &'static str {
+    match descr {
+        "|i1" => "int8",
+        "<i2" => "int16",
+        "<i4" => "int32",
+        "<i8" => "int64",
+        "|u1" => "uint8",
+        "<u2" => "uint16",
+        "<u4" => "uint32",
+        "<u8" => "uint64",
+        "<f2" => "float16",
+        "<f4" => "float32",
+        _ => "float64",
+    }
+}
@@ -0,0 +1,54 @@
+//! Queries over many RRD files, returned in the order of the files with an error per failing file.
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use rerun_query::{
+    query_data_entities, query_data_entities_batch, scan_files, DataChunk, DataQuery, Error,
+};
+
+use common::{GRIPPER_RRD, SAMPLE_RRD};
+
+/// The entity paths and the number of rows of the chunks of a query.
+fn summary(chunks: &[DataChunk]) -> Vec<(String, usize)> {
+    chunks
+        .iter()
+        .map(|chunk| (chunk.entity_path.clone(), chunk.data.len()))
+        .collect()
+}
+
+#[test]
+fn batch_results_follow_the_file_order() {
+    let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/missing.rrd");
+    let file_paths: Vec<String> = [GRIPPER_RRD, missing, SAMPLE_RRD, GRIPPER_RRD]
+        .iter()
+        .map(|path| path.to_string())
+        .collect();
+    let results = query_data_entities_batch(&file_paths, &DataQuery::default(), 2).unwrap();
+    assert_eq!(results.len(), 4);
+    for (file_path, result) in file_paths.iter().zip(&results) {
+        if file_path == missing {
+            let error = result.as_ref().unwrap_err();
+            assert!(matches!(error.kind(), Error::Open(_)), "{:?}", error);
+            assert_eq!(error.context().unwrap().path.as_deref(), Some(missing));
+        } else {
+            let expected = query_data_entities(file_path, "", "").unwrap();
+            assert_eq!(summary(result.as_ref().unwrap()), summary(&expected));
+        }
+    }
+}
+
+#[test]
+fn scan_results_follow_the_file_order() {
+    // The first files take the longest, so they finish last
+    let file_paths: Vec<String> = (0..8).map(|i| i.to_string()).collect();
+    let results = scan_files(&file_paths, 4, |file_path| {
+        let i: u64 = file_path.parse().unwrap();
+        thread::sleep(Duration::from_millis(10 * (8 - i)));
+        i
+    })
+    .unwrap();
+    assert_eq!(results, (0..8).collect::<Vec<u64>>());
+}
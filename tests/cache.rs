@@ -0,0 +1,136 @@
+//! The cache of decoded recordings: kept while the file is unchanged, dropped when it changes, and
+//! evicted beyond its budget.
+//!
+//! A cached recording is told apart from a decoded one by overwriting the file while keeping its
+//! size and modification time: only the cached recording can still be queried.
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime},
+};
+
+use rerun_query::{
+    clear_cache, load_bundle, query_data_entities, set_cache_budget, ChunkData, Error,
+};
+
+use common::{scalars, set_modified, write_recording};
+
+const LEFT: &str = "/action/gripper/left/position";
+const RIGHT: &str = "/action/gripper/right/position";
+
+/// The cache is shared by the whole process, so the tests take turns.
+static CACHE: Mutex<()> = Mutex::new(());
+
+fn reset_cache() -> MutexGuard<'static, ()> {
+    let guard = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    set_cache_budget(1 << 30);
+    clear_cache();
+    guard
+}
+
+/// Overwrite the first message of an RRD file after its header, keeping the size and the
+/// modification time of the file.
+fn corrupt(path: &Path) {
+    let modified = fs::metadata(path).unwrap().modified().unwrap();
+    let mut encoded = fs::read(path).unwrap();
+    encoded[28..44].fill(0xAB);
+    fs::write(path, encoded).unwrap();
+    set_modified(path, modified);
+}
+
+fn left_values(file_path: &str) -> Result<Vec<f64>, Error> {
+    let chunks = query_data_entities(file_path, "", LEFT)?;
+    Ok(chunks
+        .into_iter()
+        .flat_map(|chunk| match chunk.data {
+            ChunkData::Scalar(values) => values.values().to_vec(),
+            ChunkData::Tensor(_) => panic!("Expected scalars"),
+        })
+        .collect())
+}
+
+fn is_decode_error(result: Result<Vec<f64>, Error>) -> bool {
+    matches!(result, Err(error) if matches!(error.kind(), Error::Decode(_)))
+}
+
+#[test]
+fn changed_files_are_decoded_again() {
+    let _cache = reset_cache();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recording.rrd");
+    let file_path = path.to_str().unwrap();
+
+    write_recording(&path, &[scalars(LEFT, 3, 0.0)]);
+    assert_eq!(left_values(file_path).unwrap(), [0.0, 1.0, 2.0]);
+    corrupt(&path);
+    assert_eq!(left_values(file_path).unwrap(), [0.0, 1.0, 2.0]);
+
+    // A different modification time
+    let modified = SystemTime::now() - Duration::from_secs(3600);
+    set_modified(&path, modified);
+    assert!(is_decode_error(left_values(file_path)));
+
+    // A different size, with the same modification time
+    write_recording(&path, &[scalars(LEFT, 4, 10.0)]);
+    set_modified(&path, modified);
+    assert_eq!(left_values(file_path).unwrap(), [10.0, 11.0, 12.0, 13.0]);
+}
+
+#[test]
+fn clear_cache_drops_every_recording() {
+    let _cache = reset_cache();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recording.rrd");
+    let file_path = path.to_str().unwrap();
+
+    write_recording(&path, &[scalars(LEFT, 3, 0.0)]);
+    left_values(file_path).unwrap();
+    corrupt(&path);
+    clear_cache();
+    assert!(is_decode_error(left_values(file_path)));
+}
+
+#[test]
+fn least_recently_used_recordings_are_evicted() {
+    let _cache = reset_cache();
+    let dir = tempfile::tempdir().unwrap();
+    let paths: Vec<String> = ["a", "b", "c"]
+        .iter()
+        .map(|name| {
+            let path = dir.path().join(format!("{}.rrd", name));
+            write_recording(&path, &[scalars(LEFT, 3, 0.0), scalars(RIGHT, 3, 0.0)]);
+            path.to_string_lossy().into_owned()
+        })
+        .collect();
+    let size_bytes: u64 = load_bundle(&paths[0])
+        .unwrap()
+        .entity_dbs()
+        .map(|rrd| rrd.store().stats().total().total_size_bytes)
+        .sum();
+    clear_cache();
+
+    // Room for two of the three recordings
+    set_cache_budget(size_bytes * 5 / 2);
+    left_values(&paths[0]).unwrap();
+    left_values(&paths[1]).unwrap();
+    left_values(&paths[0]).unwrap();
+    left_values(&paths[2]).unwrap();
+    for path in &paths {
+        corrupt(Path::new(path));
+    }
+    assert!(left_values(&paths[0]).is_ok());
+    assert!(is_decode_error(left_values(&paths[1])));
+    assert!(left_values(&paths[2]).is_ok());
+
+    // Shrinking the budget evicts the recordings beyond it
+    set_cache_budget(size_bytes * 3 / 2);
+    assert!(is_decode_error(left_values(&paths[0])));
+    assert!(left_values(&paths[2]).is_ok());
+    set_cache_budget(0);
+    assert!(is_decode_error(left_values(&paths[2])));
+}
@@ -0,0 +1,88 @@
+//! The C API, through the committed header and a C program linked against the shared library.
+#![cfg(all(feature = "capi", unix))]
+
+mod common;
+
+use std::{env, fs, path::Path, process::Command};
+
+use common::{log_times, tensor_rows, GRIPPER_RRD, JOINTS};
+
+const HEADER: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/include/rerun_query.h");
+const GENERATED_HEADER: &str = concat!(env!("OUT_DIR"), "/rerun_query.h");
+
+#[test]
+fn committed_header_is_current() {
+    let committed = fs::read_to_string(HEADER).unwrap();
+    let generated = fs::read_to_string(GENERATED_HEADER).unwrap();
+    assert!(
+        committed == generated,
+        "include/rerun_query.h is out of date, copy {} over it or run \
+         `cbindgen --output include/rerun_query.h`",
+        GENERATED_HEADER
+    );
+}
+
+#[test]
+fn c_programs_read_columns() {
+    // The shared library is built next to the test binary
+    let exe = env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("read_column");
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/capi/read_column.c");
+    let status = Command::new("cc")
+        .args(["-std=c99", "-Wall", "-Werror", "-I"])
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("include"))
+        .arg(&source)
+        .arg("-o")
+        .arg(&program)
+        .arg("-L")
+        .arg(lib_dir)
+        .arg("-lrerun_query")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .status()
+        .unwrap();
+    assert!(status.success(), "Unable to compile {}", source.display());
+
+    // Cargo's library path would take precedence over the rpath, and may hold a build of the
+    // library without the `capi` feature
+    let output = Command::new(&program)
+        .args([GRIPPER_RRD, JOINTS])
+        .env("LD_LIBRARY_PATH", lib_dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        output.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let mut lines = stdout.lines();
+    let missing = lines.next().unwrap();
+    assert!(missing.starts_with("missing: "), "{}", missing);
+    assert!(missing.len() > "missing: ".len());
+
+    let rows = tensor_rows(GRIPPER_RRD, JOINTS);
+    let times = log_times(GRIPPER_RRD, JOINTS);
+    // RQ_DATA_TYPE_TENSOR
+    assert_eq!(
+        lines.next().unwrap(),
+        format!("entity: 1 {} {}", rows.len(), rows[0].len())
+    );
+    let too_small = lines.next().unwrap();
+    // RQ_STATUS_BUFFER_TOO_SMALL
+    assert!(too_small.starts_with("too small: 6 "), "{}", too_small);
+
+    let read: Vec<(i64, Vec<f64>)> = lines
+        .map(|line| {
+            let mut fields = line.split(' ');
+            let time = fields.next().unwrap().parse().unwrap();
+            (time, fields.map(|value| value.parse().unwrap()).collect())
+        })
+        .collect();
+    let expected: Vec<(i64, Vec<f64>)> = times.into_iter().zip(rows).collect();
+    assert_eq!(read, expected);
+}
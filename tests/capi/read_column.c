@@ -0,0 +1,60 @@
+/* Reads an entity of a recording through the C API, run by tests/capi.rs.
+ *
+ * Prints the error of a missing file, the shape of the entity, then one line per row with the
+ * log_time and the values of the row. */
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "rerun_query.h"
+
+int main(int argc, char **argv) {
+    if (argc != 3) {
+        fprintf(stderr, "usage: %s <file.rrd> <entity>\n", argv[0]);
+        return 2;
+    }
+    const char *file_path = argv[1];
+    const char *entity_path = argv[2];
+
+    RqRecording *recording = NULL;
+    if (rq_open("missing.rrd", &recording) != RQ_STATUS_OPEN || recording != NULL) {
+        fprintf(stderr, "Opening a missing file must fail\n");
+        return 1;
+    }
+    printf("missing: %s\n", rq_last_error());
+
+    if (rq_open(file_path, &recording) != RQ_STATUS_OK) {
+        fprintf(stderr, "%s\n", rq_last_error());
+        return 1;
+    }
+    RqEntityInfo info;
+    if (rq_entity_info(recording, entity_path, &info) != RQ_STATUS_OK) {
+        fprintf(stderr, "%s\n", rq_last_error());
+        rq_close(recording);
+        return 1;
+    }
+    printf("entity: %d %zu %zu\n", (int)info.data_type, info.num_rows, info.width);
+
+    int64_t *times = malloc(info.num_rows * sizeof(int64_t));
+    double *values = malloc(info.num_rows * info.width * sizeof(double));
+    RqStatus status =
+        rq_read_column(recording, entity_path, "log_time", times, values, info.num_rows - 1);
+    printf("too small: %d %s\n", (int)status, rq_last_error());
+    status = rq_read_column(recording, entity_path, "log_time", times, values, info.num_rows);
+    if (status != RQ_STATUS_OK) {
+        fprintf(stderr, "%s\n", rq_last_error());
+        return 1;
+    }
+    for (size_t row = 0; row < info.num_rows; row++) {
+        printf("%lld", (long long)times[row]);
+        for (size_t i = 0; i < info.width; i++) {
+            printf(" %.17g", values[row * info.width + i]);
+        }
+        printf("\n");
+    }
+
+    free(times);
+    free(values);
+    rq_close(recording);
+    return 0;
+}
@@ -0,0 +1,156 @@
+//! The `requery` command line tool on the recordings of `examples/data`.
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::process::{Command, Output};
+
+use rerun_query::query_data_entities;
+
+use common::{log_times, tensor_rows, GRIPPER_RRD, JOINTS, SAMPLE_RRD};
+
+fn requery(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_requery"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// The standard output of a successful command.
+fn stdout(args: &[&str]) -> String {
+    let output = requery(args);
+    assert!(
+        output.status.success(),
+        "requery {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn ls_lists_stores_and_entities() {
+    let output = stdout(&["ls", GRIPPER_RRD]);
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[0].starts_with("recording "), "{}", lines[0]);
+    assert!(lines[0].ends_with("(application fftai_action, 3611 rows)"));
+    assert_eq!(
+        lines[1..6],
+        [
+            "  /action/gripper/left/position",
+            "  /action/gripper/right/position",
+            "  /action/joints/position",
+            "  /meta",
+            "  /observations/joints/names",
+        ]
+    );
+    assert!(lines[6].starts_with("blueprint "), "{}", lines[6]);
+    assert!(lines.contains(&"  /viewport"));
+}
+
+#[test]
+fn cat_prints_sorted_rows() {
+    let output = stdout(&[
+        "cat",
+        GRIPPER_RRD,
+        "/action/gripper/left/position",
+        "-n",
+        "3",
+    ]);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "log_tick\tlog_time\tstable_time\tScalar");
+    assert!(lines[1].starts_with("13\t2024-08-22 03:50:25.944923\t"));
+    let ticks: Vec<i64> = lines[1..]
+        .iter()
+        .map(|line| line.split('\t').next().unwrap().parse().unwrap())
+        .collect();
+    assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let times = log_times(GRIPPER_RRD, "/action/gripper/left/position");
+    let start = times[10].to_string();
+    let end = times[14].to_string();
+    let output = stdout(&[
+        "cat",
+        GRIPPER_RRD,
+        "/action/gripper/left/position",
+        "--start",
+        &start,
+        "--end",
+        &end,
+    ]);
+    assert_eq!(output.lines().count(), 1 + 5);
+}
+
+#[test]
+fn cat_fails_on_unknown_entity() {
+    let output = requery(&["cat", GRIPPER_RRD, "/missing"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "error: No rows found for /missing on timeline log_time"
+    );
+}
+
+#[test]
+fn filter_merge_and_split_write_recordings() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+    let times = log_times(GRIPPER_RRD, JOINTS);
+    let (start, end) = (times[100].to_string(), times[199].to_string());
+    let filtered = path("filtered.rrd");
+    stdout(&[
+        "filter",
+        GRIPPER_RRD,
+        "-o",
+        &filtered,
+        "-e",
+        "/action/joints",
+        "--start",
+        &start,
+        "--end",
+        &end,
+    ]);
+    assert_eq!(tensor_rows(&filtered, JOINTS).len(), 100);
+    let chunks = query_data_entities(&filtered, "", "").unwrap();
+    assert!(chunks.iter().all(|chunk| chunk.entity_path == JOINTS));
+
+    let merged = path("merged.rrd");
+    let output = stdout(&[
+        "merge",
+        SAMPLE_RRD,
+        GRIPPER_RRD,
+        "-o",
+        &merged,
+        "--offset-timeline",
+        "log_tick",
+    ]);
+    assert!(output.starts_with("Wrote "), "{}", output);
+    assert_eq!(
+        tensor_rows(&merged, JOINTS).len(),
+        tensor_rows(SAMPLE_RRD, JOINTS).len() + tensor_rows(GRIPPER_RRD, JOINTS).len()
+    );
+
+    // An episode delimiter is required
+    assert!(!requery(&["split", &merged, "-o", &path("episodes")])
+        .status
+        .success());
+    let output = stdout(&[
+        "split",
+        &merged,
+        "-o",
+        &path("episodes"),
+        "--gap-threshold",
+        "3600000000000",
+    ]);
+    let episodes: Vec<&str> = output
+        .lines()
+        .map(|line| line.strip_prefix("Wrote ").unwrap())
+        .collect();
+    assert_eq!(episodes.len(), 2);
+    assert_eq!(
+        tensor_rows(episodes[1], JOINTS),
+        tensor_rows(GRIPPER_RRD, JOINTS)
+    );
+}
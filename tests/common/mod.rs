@@ -0,0 +1,147 @@
+//! Recordings written by the tests, with exactly the chunks they need.
+#![allow(dead_code)]
+
+use std::{fs::File, path::Path, time::SystemTime};
+
+use re_arrow2::{
+    array::{Array, Float64Array, ListArray},
+    compute::cast::{cast, CastOptions},
+    datatypes::DataType,
+};
+use re_build_info::CrateVersion;
+use re_chunk::{Chunk, RowId};
+use re_log_encoding::{encoder::Encoder, EncodingOptions};
+use re_log_types::{
+    ApplicationId, LogMsg, SetStoreInfo, StoreId, StoreInfo, StoreKind, StoreSource, Time,
+    TimePoint, Timeline,
+};
+use re_types::{
+    archetypes::{Scalar, Tensor},
+    datatypes::{TensorBuffer, TensorData, TensorDimension},
+};
+use rerun_query::{query_data_entities, ChunkData};
+
+pub const GRIPPER_RRD: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/examples/data/robot_action_gripper.rrd"
+);
+pub const SAMPLE_RRD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data/sample-0.18.rrd");
+pub const JOINTS: &str = "/action/joints/position";
+
+/// The `log_time` of the first row, in nanoseconds.
+pub const START_TIME: i64 = 1_724_298_625_000_000_000;
+
+/// The time of row `i`: `log_time` 1 ms after the previous row, and `log_tick` `i`.
+pub fn timepoint(i: i64) -> TimePoint {
+    TimePoint::default()
+        .with(Timeline::log_time(), START_TIME + i * 1_000_000)
+        .with(Timeline::log_tick(), i)
+}
+
+/// A one-dimensional `float32` tensor.
+pub fn f32_tensor(values: Vec<f32>) -> Tensor {
+    Tensor::new(TensorData::new(
+        vec![TensorDimension::unnamed(values.len() as u64)],
+        TensorBuffer::F32(values.into()),
+    ))
+}
+
+/// A chunk of `num_rows` scalars at [`timepoint`] `i`, `offset + i` for row `i`.
+pub fn scalars(entity_path: &str, num_rows: i64, offset: f64) -> Chunk {
+    let mut builder = Chunk::builder(entity_path.into());
+    for i in 0..num_rows {
+        let scalar = Scalar::new(offset + i as f64);
+        builder = builder.with_archetype(RowId::new(), timepoint(i), &scalar);
+    }
+    builder.build().unwrap()
+}
+
+/// Write the chunks as the only recording of an RRD file.
+pub fn write_recording(path: &Path, chunks: &[Chunk]) {
+    let file = File::create(path).unwrap();
+    let mut encoder = Encoder::new(CrateVersion::LOCAL, EncodingOptions::COMPRESSED, file).unwrap();
+
+    let store_id = StoreId::random(StoreKind::Recording);
+    encoder
+        .append(&LogMsg::SetStoreInfo(SetStoreInfo {
+            row_id: *RowId::new(),
+            info: StoreInfo {
+                application_id: ApplicationId("rerun_query_tests".to_string()),
+                store_id: store_id.clone(),
+                cloned_from: None,
+                is_official_example: false,
+                started: Time::now(),
+                store_source: StoreSource::Other("rerun_query tests".to_string()),
+                store_version: Some(CrateVersion::LOCAL),
+            },
+        }))
+        .unwrap();
+    for chunk in chunks {
+        encoder
+            .append(&LogMsg::ArrowMsg(
+                store_id.clone(),
+                chunk.to_arrow_msg().unwrap(),
+            ))
+            .unwrap();
+    }
+    encoder.into_inner();
+}
+
+pub fn to_f64(array: &dyn Array) -> Vec<f64> {
+    let array = cast(array, &DataType::Float64, CastOptions::default()).unwrap();
+    let array = array.as_any().downcast_ref::<Float64Array>().unwrap();
+    array.values().to_vec()
+}
+
+pub fn list_rows(tensors: &ListArray<i32>) -> Vec<Vec<f64>> {
+    tensors
+        .iter()
+        .map(|row| to_f64(row.expect("tensor rows are valid").as_ref()))
+        .collect()
+}
+
+/// The tensors of an entity, one `float64` vector per row.
+pub fn tensor_rows(file_path: &str, entity_path: &str) -> Vec<Vec<f64>> {
+    let mut rows = Vec::new();
+    for chunk in query_data_entities(file_path, "tensor", entity_path).unwrap() {
+        assert_eq!(chunk.entity_path, entity_path);
+        let ChunkData::Tensor(tensors) = &chunk.data else {
+            panic!("{} is not a tensor entity", entity_path);
+        };
+        rows.extend(list_rows(tensors));
+    }
+    rows
+}
+
+/// The `log_time` of every row of an entity.
+pub fn log_times(file_path: &str, entity_path: &str) -> Vec<i64> {
+    query_data_entities(file_path, "", entity_path)
+        .unwrap()
+        .iter()
+        .flat_map(|chunk| chunk.timelines["log_time"].clone())
+        .collect()
+}
+
+pub fn decode_le(descr: &str, bytes: &[u8]) -> Vec<f64> {
+    match descr {
+        "<f4" => bytes
+            .chunks_exact(4)
+            .map(|value| f32::from_le_bytes(value.try_into().unwrap()) as f64)
+            .collect(),
+        "<f8" => bytes
+            .chunks_exact(8)
+            .map(|value| f64::from_le_bytes(value.try_into().unwrap()))
+            .collect(),
+        _ => panic!("Unexpected element type {}", descr),
+    }
+}
+
+/// Set the modification time of a file, e.g. to restore it after rewriting the file.
+pub fn set_modified(path: &Path, modified: SystemTime) {
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+}
@@ -0,0 +1,137 @@
+//! The CSV export, read back and checked against the queries on the original files.
+#![cfg(feature = "export")]
+
+mod common;
+
+use std::{fs, ops::Range};
+
+use re_chunk::{Chunk, RowId};
+use re_log_types::{TimePoint, Timeline};
+use re_types::components;
+use rerun_query::{export_csv, Error};
+
+use common::{
+    f32_tensor, log_times, tensor_rows, timepoint, write_recording, GRIPPER_RRD, JOINTS, START_TIME,
+};
+
+/// The header and the rows of a CSV file.
+fn read_csv(path: &str) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut reader = csv::Reader::from_path(path).unwrap();
+    let header = reader.headers().unwrap().iter().map(String::from).collect();
+    let rows = reader
+        .records()
+        .map(|record| record.unwrap().iter().map(String::from).collect())
+        .collect();
+    (header, rows)
+}
+
+#[test]
+fn csv_export_reads_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().to_str().unwrap();
+    let written = export_csv(GRIPPER_RRD, out_dir, "", "/action", ",", "raw").unwrap();
+    assert_eq!(written.len(), 3);
+
+    let (header, rows) = read_csv(&format!("{}/action/gripper/left/position.csv", out_dir));
+    assert_eq!(header, ["log_tick", "log_time", "stable_time", "Scalar"]);
+    assert_eq!(rows.len(), 1203);
+
+    let expected = tensor_rows(GRIPPER_RRD, JOINTS);
+    let (header, rows) = read_csv(&format!("{}/action/joints/position.csv", out_dir));
+    let num_elements = expected[0].len();
+    assert_eq!(header.len(), 3 + num_elements);
+    assert_eq!(header[3], "TensorData_0");
+    assert_eq!(
+        header.last().unwrap(),
+        &format!("TensorData_{}", num_elements - 1)
+    );
+    let times: Vec<i64> = rows.iter().map(|row| row[1].parse().unwrap()).collect();
+    assert_eq!(times, log_times(GRIPPER_RRD, JOINTS));
+    let values: Vec<Vec<f64>> = rows
+        .iter()
+        .map(|row| {
+            row[3..]
+                .iter()
+                .map(|value| value.parse().unwrap())
+                .collect()
+        })
+        .collect();
+    assert_eq!(values, expected);
+
+    export_csv(
+        GRIPPER_RRD,
+        out_dir,
+        "scalar",
+        "/action/gripper/left",
+        ";",
+        "iso8601",
+    )
+    .unwrap();
+    let contents =
+        fs::read_to_string(format!("{}/action/gripper/left/position.csv", out_dir)).unwrap();
+    let first = contents.lines().nth(1).unwrap();
+    assert!(
+        first.starts_with("13;2024-08-22T03:50:25.944923000Z;"),
+        "{}",
+        first
+    );
+}
+
+/// Chunks of tensor rows, and of rows without tensors on another set of timelines, so that the
+/// store keeps them apart.
+fn tensors(rows: Range<i64>) -> Chunk {
+    let mut builder = Chunk::builder(JOINTS.into());
+    for i in rows {
+        let tensor = f32_tensor(vec![i as f32, i as f32 + 0.5]);
+        builder = builder.with_archetype(RowId::new(), timepoint(i), &tensor);
+    }
+    builder.build().unwrap()
+}
+
+fn cleared(rows: Range<i64>) -> Chunk {
+    let mut builder = Chunk::builder(JOINTS.into());
+    for i in rows {
+        let timepoint = TimePoint::default().with(Timeline::log_time(), START_TIME + i * 1_000_000);
+        let empty = Vec::<components::TensorData>::new();
+        builder = builder.with_component_batch(RowId::new(), timepoint, &empty);
+    }
+    builder.build().unwrap()
+}
+
+#[test]
+fn tensor_headers_come_from_the_first_tensor() {
+    let dir = tempfile::tempdir().unwrap();
+    let rrd_path = dir.path().join("cleared.rrd");
+    write_recording(&rrd_path, &[cleared(0..2), tensors(2..4)]);
+
+    let out_dir = dir.path().join("out");
+    let out_dir = out_dir.to_str().unwrap();
+    export_csv(rrd_path.to_str().unwrap(), out_dir, "", JOINTS, ",", "raw").unwrap();
+    let (header, rows) = read_csv(&format!("{}/action/joints/position.csv", out_dir));
+    assert_eq!(
+        header,
+        ["log_tick", "log_time", "TensorData_0", "TensorData_1"]
+    );
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows.iter().filter(|row| row[2..] == ["", ""]).count(), 2);
+}
+
+#[test]
+fn tensors_of_unknown_shape_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let rrd_path = dir.path().join("cleared.rrd");
+    let rrd_path = rrd_path.to_str().unwrap();
+    write_recording(std::path::Path::new(rrd_path), &[cleared(0..2)]);
+
+    let out_dir = dir.path().join("out");
+    let error =
+        export_csv(rrd_path, out_dir.to_str().unwrap(), "", JOINTS, ",", "raw").unwrap_err();
+    assert!(
+        matches!(error.kind(), Error::Invalid(message) if message.contains("Cannot determine the shape")),
+        "{:?}",
+        error
+    );
+    let context = error.context().unwrap();
+    assert_eq!(context.path.as_deref(), Some(rrd_path));
+    assert_eq!(context.entity.as_deref(), Some(JOINTS));
+}
@@ -0,0 +1,108 @@
+//! The context of the errors of queries and exports that match no data: the path of the file and
+//! the entity filter. Also the exports of entities whose path would leave the output directory.
+#![cfg(feature = "export")]
+
+mod common;
+
+use rerun_query::{
+    export_csv, export_mcap, export_npz, export_parquet, export_tfrecord, export_webdataset,
+    export_zarr, query_data_entities, query_entity_table, write_filtered_rrd, Error, Result,
+    TableQuery,
+};
+
+use common::{scalars, write_recording, GRIPPER_RRD};
+
+const MISSING: &str = "/missing/entity";
+
+#[track_caller]
+fn assert_no_data<T>(result: Result<T>) {
+    let Err(error) = result else {
+        panic!("Expected an error");
+    };
+    assert!(matches!(error.kind(), Error::NoData(_)), "{:?}", error);
+    let context = error.context().unwrap();
+    assert_eq!(context.path.as_deref(), Some(GRIPPER_RRD));
+    assert_eq!(context.entity.as_deref(), Some(MISSING));
+}
+
+#[test]
+fn no_data_errors_name_the_file_and_the_entity() {
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().to_str().unwrap();
+    let out_path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+
+    assert_no_data(query_data_entities(GRIPPER_RRD, "", MISSING));
+    let query = TableQuery {
+        entity: MISSING.to_string(),
+        ..TableQuery::default()
+    };
+    assert_no_data(query_entity_table(GRIPPER_RRD, &query));
+    assert_no_data(write_filtered_rrd(
+        GRIPPER_RRD,
+        &out_path("filtered.rrd"),
+        MISSING,
+        None,
+        "",
+        "log_time",
+        false,
+    ));
+
+    assert_no_data(export_parquet(GRIPPER_RRD, out_dir, MISSING, "zstd"));
+    assert_no_data(export_csv(GRIPPER_RRD, out_dir, "", MISSING, ",", "raw"));
+    assert_no_data(export_npz(
+        GRIPPER_RRD,
+        &out_path("data.npz"),
+        MISSING,
+        false,
+    ));
+    assert_no_data(export_zarr(GRIPPER_RRD, out_dir, MISSING, 3, 1000, "zstd"));
+    assert_no_data(export_mcap(
+        GRIPPER_RRD,
+        &out_path("data.mcap"),
+        MISSING,
+        "log_time",
+        "zstd",
+    ));
+    assert_no_data(export_tfrecord(
+        GRIPPER_RRD,
+        out_dir,
+        "",
+        MISSING,
+        30,
+        "log_time",
+        100,
+    ));
+    assert_no_data(export_webdataset(
+        GRIPPER_RRD,
+        out_dir,
+        "",
+        MISSING,
+        30,
+        "log_time",
+        100,
+    ));
+}
+
+#[test]
+fn entity_paths_leaving_the_output_directory_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recording.rrd");
+    let file_path = path.to_str().unwrap();
+    write_recording(&path, &[scalars("/topic/../../escape", 3, 0.0)]);
+    let out_dir = dir.path().join("out");
+    let out_dir = out_dir.to_str().unwrap();
+
+    for result in [
+        export_parquet(file_path, out_dir, "", "zstd"),
+        export_csv(file_path, out_dir, "", "", ",", "raw"),
+        export_zarr(file_path, out_dir, "", 3, 1000, "zstd"),
+    ] {
+        let error = result.unwrap_err();
+        assert!(matches!(error.kind(), Error::Invalid(_)), "{:?}", error);
+        assert!(error.to_string().contains("\"..\""), "{}", error);
+        assert_eq!(error.context().unwrap().path.as_deref(), Some(file_path));
+    }
+    assert!(!dir.path().join("escape.parquet").exists());
+    assert!(!dir.path().join("escape.csv").exists());
+    assert!(!dir.path().join("escape").exists());
+}
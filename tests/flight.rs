@@ -0,0 +1,193 @@
+//! The Arrow Flight service over `examples/data`, queried by a Flight client on a local port.
+#![cfg(feature = "flight")]
+
+use std::{fs, path::Path};
+
+use arrow_format::flight::{
+    data::{flight_descriptor::DescriptorType, Criteria, FlightDescriptor, FlightInfo, Ticket},
+    service::{
+        flight_service_client::FlightServiceClient, flight_service_server::FlightServiceServer,
+    },
+};
+use futures::{stream, StreamExt};
+use re_arrow2::io::{
+    flight::{deserialize_batch, deserialize_schemas},
+    ipc::read::Dictionaries,
+};
+use re_build_info::CrateVersion;
+use re_log_encoding::{encoder::Encoder, EncodingOptions};
+use rerun_query::{
+    flight::{FlightTicket, RecordingService},
+    query_data_entities, TableQuery,
+};
+use tokio::{net::TcpListener, runtime::Runtime};
+use tonic::transport::{Channel, Server};
+
+const DATA_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data");
+const JOINTS: &str = "/action/joints/position";
+
+/// Serve `folder_path` on a free port of 127.0.0.1 and connect a client to it.
+async fn connect(folder_path: &Path) -> FlightServiceClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = stream::unfold(listener, |listener| async {
+        let connection = listener.accept().await.map(|(socket, _)| socket);
+        Some((connection, listener))
+    });
+    let service = FlightServiceServer::new(RecordingService::new(folder_path));
+    tokio::spawn(
+        Server::builder()
+            .add_service(service)
+            .serve_with_incoming(incoming),
+    );
+    FlightServiceClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+async fn list_flights(client: &mut FlightServiceClient<Channel>) -> Vec<FlightInfo> {
+    let request = Criteria {
+        expression: Vec::new(),
+    };
+    let mut flights = client.list_flights(request).await.unwrap().into_inner();
+    let mut infos = Vec::new();
+    while let Some(info) = flights.next().await {
+        infos.push(info.unwrap());
+    }
+    infos
+}
+
+fn flight_ticket(info: &FlightInfo) -> FlightTicket {
+    FlightTicket::decode(&info.endpoint[0].ticket.as_ref().unwrap().ticket).unwrap()
+}
+
+#[test]
+fn flights_of_example_recordings() {
+    let times: Vec<i64> = query_data_entities(&format!("{}/sample-0.18.rrd", DATA_DIR), "", JOINTS)
+        .unwrap()
+        .iter()
+        .flat_map(|chunk| chunk.timelines["log_time"].clone())
+        .collect();
+    let ticket = FlightTicket {
+        file: "sample-0.18.rrd".to_string(),
+        query: TableQuery {
+            entity: JOINTS.to_string(),
+            start: Some(times[100]),
+            end: Some(times[199]),
+            ..Default::default()
+        },
+    };
+
+    Runtime::new().unwrap().block_on(async {
+        let mut client = connect(Path::new(DATA_DIR)).await;
+
+        let flights = list_flights(&mut client).await;
+        let joints: Vec<(String, i64)> = flights
+            .iter()
+            .filter(|info| flight_ticket(info).query.entity == JOINTS)
+            .map(|info| (flight_ticket(info).file, info.total_records))
+            .collect();
+        assert_eq!(
+            joints,
+            [
+                ("robot_action_gripper.rrd".to_string(), 1203),
+                ("sample-0.18.rrd".to_string(), 1844),
+            ]
+        );
+
+        let descriptor = FlightDescriptor {
+            r#type: DescriptorType::Cmd as i32,
+            cmd: ticket.encode(),
+            path: Vec::new(),
+        };
+        let info = client
+            .get_flight_info(descriptor)
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(info.total_records, 100);
+        // An encapsulated IPC message: continuation marker, length and schema message
+        let length = u32::from_le_bytes(info.schema[4..8].try_into().unwrap()) as usize;
+        let (info_schema, _) = deserialize_schemas(&info.schema[8..8 + length]).unwrap();
+        let listed = flights
+            .iter()
+            .find(|info| {
+                let ticket = flight_ticket(info);
+                ticket.file == "sample-0.18.rrd" && ticket.query.entity == JOINTS
+            })
+            .unwrap();
+        let length = u32::from_le_bytes(listed.schema[4..8].try_into().unwrap()) as usize;
+        let (listed_schema, _) = deserialize_schemas(&listed.schema[8..8 + length]).unwrap();
+        assert_eq!(listed_schema, info_schema);
+
+        let request = Ticket {
+            ticket: ticket.encode(),
+        };
+        let mut data = client.do_get(request).await.unwrap().into_inner();
+        let first = data.next().await.unwrap().unwrap();
+        let (schema, ipc_schema) = deserialize_schemas(&first.data_header).unwrap();
+        assert_eq!(schema, info_schema);
+        assert_eq!(schema.metadata["entity_path"], JOINTS);
+        let names: Vec<&str> = schema
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect();
+        for name in ["log_time", "log_tick", "TensorData"] {
+            assert!(names.contains(&name), "{} missing from {:?}", name, names);
+        }
+
+        let mut num_rows = 0;
+        while let Some(batch) = data.next().await {
+            let batch = deserialize_batch(
+                &batch.unwrap(),
+                &schema.fields,
+                &ipc_schema,
+                &Dictionaries::default(),
+            )
+            .unwrap();
+            num_rows += batch.len();
+        }
+        assert_eq!(num_rows, 100);
+    });
+}
+
+#[test]
+fn files_without_recording_are_not_listed() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::copy(
+        format!("{}/sample-0.18.rrd", DATA_DIR),
+        dir.path().join("sample.rrd"),
+    )
+    .unwrap();
+    // A valid RRD file without any message
+    let empty = fs::File::create(dir.path().join("empty.rrd")).unwrap();
+    Encoder::new(CrateVersion::LOCAL, EncodingOptions::COMPRESSED, empty)
+        .unwrap()
+        .into_inner();
+    // A recording whose first message is corrupt
+    let mut corrupt = fs::read(format!("{}/sample-0.18.rrd", DATA_DIR)).unwrap();
+    corrupt[28..44].fill(0xAB);
+    fs::write(dir.path().join("corrupt.rrd"), corrupt).unwrap();
+
+    Runtime::new().unwrap().block_on(async {
+        let mut client = connect(dir.path()).await;
+
+        let flights = list_flights(&mut client).await;
+        assert!(!flights.is_empty());
+        assert!(flights
+            .iter()
+            .all(|info| flight_ticket(info).file == "sample.rrd"));
+
+        let request = Criteria {
+            expression: b"empty.rrd".to_vec(),
+        };
+        let status = client.list_flights(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        let request = Criteria {
+            expression: b"corrupt.rrd".to_vec(),
+        };
+        let status = client.list_flights(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DataLoss);
+    });
+}
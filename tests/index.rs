@@ -0,0 +1,139 @@
+//! The sidecar index of RRD files: rebuilt when the file changes, and used to decode only the
+//! messages a query needs.
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{Seek, SeekFrom, Write},
+    time::{Duration, SystemTime},
+};
+
+use rerun_query::{build_index, query_data_entities, set_cache_budget, ChunkData, Error, RrdIndex};
+
+use common::{scalars, set_modified, write_recording};
+
+const LEFT: &str = "/action/gripper/left/position";
+const RIGHT: &str = "/action/gripper/right/position";
+
+fn read_index(file_path: &str) -> RrdIndex {
+    serde_json::from_slice(&fs::read(format!("{}.idx", file_path)).unwrap()).unwrap()
+}
+
+fn scalar_values(file_path: &str, entity_path_filter: &str) -> Vec<(String, Vec<f64>)> {
+    query_data_entities(file_path, "", entity_path_filter)
+        .unwrap()
+        .into_iter()
+        .map(|chunk| {
+            let ChunkData::Scalar(values) = chunk.data else {
+                panic!("Expected scalars for {}", chunk.entity_path);
+            };
+            (chunk.entity_path, values.values().to_vec())
+        })
+        .collect()
+}
+
+#[test]
+fn index_is_rebuilt_when_the_file_changes() {
+    // Cached recordings would be used without looking at the index
+    set_cache_budget(0);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recording.rrd");
+    let file_path = path.to_str().unwrap();
+
+    write_recording(&path, &[scalars(LEFT, 3, 0.0)]);
+    build_index(file_path).unwrap();
+    let index = read_index(file_path);
+    assert_eq!(index.file_size, fs::metadata(&path).unwrap().len());
+    let num_messages = index.messages.len();
+
+    // A different size
+    write_recording(&path, &[scalars(LEFT, 3, 0.0), scalars(RIGHT, 2, 10.0)]);
+    assert_eq!(
+        scalar_values(file_path, RIGHT),
+        [(RIGHT.to_string(), vec![10.0, 11.0])]
+    );
+    let index = read_index(file_path);
+    assert_eq!(index.file_size, fs::metadata(&path).unwrap().len());
+    assert_eq!(index.messages.len(), num_messages + 1);
+
+    // The same size, a different modification time
+    let modified = SystemTime::now() - Duration::from_secs(3600);
+    set_modified(&path, modified);
+    scalar_values(file_path, RIGHT);
+    let index = read_index(file_path);
+    let modified = modified.duration_since(SystemTime::UNIX_EPOCH).unwrap();
+    assert_eq!(index.modified, modified.as_nanos() as u64);
+}
+
+#[test]
+fn indexed_queries_read_only_the_matching_messages() {
+    set_cache_budget(0);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recording.rrd");
+    let file_path = path.to_str().unwrap();
+    write_recording(&path, &[scalars(LEFT, 3, 0.0), scalars(RIGHT, 2, 10.0)]);
+    build_index(file_path).unwrap();
+
+    // Overwrite the message of the right gripper, keeping the size and the modification time so
+    // that the index stays valid
+    let index = read_index(file_path);
+    let (right_index, right) = index
+        .messages
+        .iter()
+        .enumerate()
+        .find(|(_, message)| {
+            message
+                .chunk
+                .as_ref()
+                .is_some_and(|chunk| chunk.entity_path == RIGHT)
+        })
+        .unwrap();
+    let modified = fs::metadata(&path).unwrap().modified().unwrap();
+    let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+    file.seek(SeekFrom::Start(right.offset + 16)).unwrap();
+    file.write_all(&vec![0xAB; right.len as usize - 16])
+        .unwrap();
+    drop(file);
+    set_modified(&path, modified);
+
+    assert_eq!(
+        scalar_values(file_path, LEFT),
+        [(LEFT.to_string(), vec![0.0, 1.0, 2.0])]
+    );
+    let error = query_data_entities(file_path, "", RIGHT).unwrap_err();
+    assert!(matches!(error.kind(), Error::Decode(_)), "{:?}", error);
+    let context = error.context().unwrap();
+    assert_eq!(context.path.as_deref(), Some(file_path));
+    assert_eq!(context.message_index, Some(right_index));
+}
+
+#[test]
+fn recordings_without_action_entities_are_decoded_entirely() {
+    set_cache_budget(0);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recording.rrd");
+    let file_path = path.to_str().unwrap();
+    let entities = ["/observation/left", "/observation/right"];
+    write_recording(
+        &path,
+        &[scalars(entities[0], 3, 0.0), scalars(entities[1], 2, 10.0)],
+    );
+
+    let unindexed = scalar_values(file_path, "");
+    build_index(file_path).unwrap();
+    let indexed = scalar_values(file_path, "");
+    assert_eq!(indexed, unindexed);
+    assert_eq!(
+        indexed,
+        [
+            (entities[0].to_string(), vec![0.0, 1.0, 2.0]),
+            (entities[1].to_string(), vec![10.0, 11.0]),
+        ]
+    );
+    assert_eq!(
+        scalar_values(file_path, entities[1]),
+        [(entities[1].to_string(), vec![10.0, 11.0])]
+    );
+}
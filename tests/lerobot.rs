@@ -0,0 +1,258 @@
+//! The LeRobot export of recordings with tensors and PNG and JPEG images, checked through the
+//! metadata files and the episode parquet files.
+#![cfg(feature = "export")]
+
+mod common;
+
+use std::{fs, io::Cursor, path::Path};
+
+use image::{DynamicImage, GrayAlphaImage, GrayImage, ImageFormat, RgbImage, RgbaImage};
+use re_arrow2::io::parquet::read;
+use re_chunk::{Chunk, RowId};
+use re_log_types::TimePoint;
+use re_types::archetypes::{EncodedImage, TextDocument};
+use rerun_query::export_lerobot;
+use serde_json::{json, Value};
+
+use common::{f32_tensor, timepoint, write_recording, JOINTS};
+
+const RGBA: &str = "/observation/images/rgba";
+const GRAY_ALPHA: &str = "/observation/images/gray_alpha";
+const RGB: &str = "/observation/images/rgb";
+const GRAY: &str = "/observation/images/gray";
+
+fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, format).unwrap();
+    bytes.into_inner()
+}
+
+/// An episode of `num_rows` rows, 1 ms apart, with the joints `[i, -i]` and the same images in
+/// every row, and a task entity if `task` is set.
+fn write_episode(path: &Path, num_rows: i64, task: Option<&str>) {
+    let images = [
+        (
+            RGBA,
+            encode(
+                RgbaImage::from_pixel(4, 3, [255, 0, 51, 255].into()).into(),
+                ImageFormat::Png,
+            ),
+        ),
+        (
+            GRAY_ALPHA,
+            encode(
+                GrayAlphaImage::from_pixel(3, 2, [102, 255].into()).into(),
+                ImageFormat::Png,
+            ),
+        ),
+        (
+            RGB,
+            encode(
+                RgbImage::from_pixel(16, 8, [200, 100, 50].into()).into(),
+                ImageFormat::Jpeg,
+            ),
+        ),
+        (
+            GRAY,
+            encode(
+                GrayImage::from_pixel(8, 16, [128].into()).into(),
+                ImageFormat::Jpeg,
+            ),
+        ),
+    ];
+
+    let mut joints = Chunk::builder(JOINTS.into());
+    for i in 0..num_rows {
+        let tensor = f32_tensor(vec![i as f32, -i as f32]);
+        joints = joints.with_archetype(RowId::new(), timepoint(i), &tensor);
+    }
+    let mut chunks = vec![joints.build().unwrap()];
+    for (entity_path, bytes) in images {
+        let mut builder = Chunk::builder(entity_path.into());
+        for i in 0..num_rows {
+            let image = EncodedImage::from_file_contents(bytes.clone());
+            builder = builder.with_archetype(RowId::new(), timepoint(i), &image);
+        }
+        chunks.push(builder.build().unwrap());
+    }
+    if let Some(task) = task {
+        let text = TextDocument::new(task);
+        let chunk = Chunk::builder("/meta/task".into())
+            .with_archetype(RowId::new(), TimePoint::default(), &text)
+            .build()
+            .unwrap();
+        chunks.push(chunk);
+    }
+    write_recording(path, &chunks);
+}
+
+fn read_json(path: &Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn read_jsonl(path: &Path) -> Vec<Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+/// The values of per-channel stats, shaped `(channel, 1, 1)`.
+fn channels(stat: &Value) -> Vec<f64> {
+    stat.as_array()
+        .unwrap()
+        .iter()
+        .map(|channel| channel[0][0].as_f64().unwrap())
+        .collect()
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn lerobot_export_writes_the_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("first.rrd");
+    let second = dir.path().join("second.rrd");
+    write_episode(&first, 5, Some("Stack the cubes"));
+    write_episode(&second, 3, None);
+
+    let out_dir = dir.path().join("dataset");
+    let config = json!({
+        "fps": 1000,
+        "robot_type": "r1",
+        "task": "Pick up the cube",
+        "task_entity": "/meta/task",
+        "features": {
+            "action": JOINTS,
+            "observation.images.rgba": RGBA,
+            "observation.images.gray_alpha": GRAY_ALPHA,
+            "observation.images.rgb": RGB,
+            "observation.images.gray": GRAY,
+        },
+    });
+    let file_paths = vec![
+        first.to_string_lossy().into_owned(),
+        second.to_string_lossy().into_owned(),
+    ];
+    let written =
+        export_lerobot(file_paths, out_dir.to_str().unwrap(), &config.to_string()).unwrap();
+    let episode_paths = [
+        out_dir.join("data/chunk-000/episode_000000.parquet"),
+        out_dir.join("data/chunk-000/episode_000001.parquet"),
+    ];
+    assert_eq!(
+        written,
+        episode_paths
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>()
+    );
+    for (path, num_frames) in episode_paths.iter().zip([5, 3]) {
+        let metadata = read::read_metadata(&mut fs::File::open(path).unwrap()).unwrap();
+        assert_eq!(metadata.num_rows, num_frames);
+    }
+
+    let info = read_json(&out_dir.join("meta/info.json"));
+    assert_eq!(info["codebase_version"], "v2.1");
+    assert_eq!(info["robot_type"], "r1");
+    assert_eq!(info["total_episodes"], 2);
+    assert_eq!(info["total_frames"], 8);
+    assert_eq!(info["total_tasks"], 2);
+    assert_eq!(info["total_chunks"], 1);
+    assert_eq!(info["fps"], 1000);
+    assert_eq!(info["splits"], json!({ "train": "0:2" }));
+    let features = &info["features"];
+    assert_eq!(
+        features["action"],
+        json!({ "dtype": "float32", "shape": [2], "names": null })
+    );
+    // The shapes are height, width and channels, read from the PNG and JPEG headers
+    for (key, shape) in [
+        ("observation.images.rgba", [3, 4, 4]),
+        ("observation.images.gray_alpha", [2, 3, 2]),
+        ("observation.images.rgb", [8, 16, 3]),
+        ("observation.images.gray", [16, 8, 1]),
+    ] {
+        assert_eq!(
+            features[key],
+            json!({ "dtype": "image", "shape": shape, "names": ["height", "width", "channel"] }),
+            "{}",
+            key
+        );
+    }
+    for key in ["frame_index", "episode_index", "index", "task_index"] {
+        assert_eq!(features[key]["dtype"], "int64");
+    }
+
+    let episodes = read_jsonl(&out_dir.join("meta/episodes.jsonl"));
+    assert_eq!(
+        episodes,
+        [
+            json!({ "episode_index": 0, "tasks": ["Stack the cubes"], "length": 5 }),
+            json!({ "episode_index": 1, "tasks": ["Pick up the cube"], "length": 3 }),
+        ]
+    );
+    let tasks = read_jsonl(&out_dir.join("meta/tasks.jsonl"));
+    assert_eq!(
+        tasks,
+        [
+            json!({ "task_index": 0, "task": "Stack the cubes" }),
+            json!({ "task_index": 1, "task": "Pick up the cube" }),
+        ]
+    );
+
+    let episodes_stats = read_jsonl(&out_dir.join("meta/episodes_stats.jsonl"));
+    assert_eq!(episodes_stats.len(), 2);
+    let stats = &episodes_stats[0]["stats"];
+    assert_eq!(episodes_stats[0]["episode_index"], 0);
+    assert_eq!(
+        stats["action"],
+        json!({
+            "min": [0.0, -4.0],
+            "max": [4.0, 0.0],
+            "mean": [2.0, -2.0],
+            "std": [2.0f64.sqrt(), 2.0f64.sqrt()],
+            "count": [5],
+        })
+    );
+    // Uniform images have the pixel value everywhere, up to rounding in the sums
+    for (key, values) in [
+        ("observation.images.rgba", &[1.0, 0.0, 0.2, 1.0][..]),
+        ("observation.images.gray_alpha", &[0.4, 1.0][..]),
+    ] {
+        assert_eq!(channels(&stats[key]["min"]), values, "{}", key);
+        assert_eq!(channels(&stats[key]["max"]), values, "{}", key);
+        assert_close(&channels(&stats[key]["mean"]), values);
+        assert_close(&channels(&stats[key]["std"]), &vec![0.0; values.len()]);
+        assert_eq!(stats[key]["count"], json!([5]));
+    }
+    // JPEG is lossy, so only the channels and the sample count are exact
+    for (key, num_channels) in [
+        ("observation.images.rgb", 3),
+        ("observation.images.gray", 1),
+    ] {
+        for stat in ["min", "max", "mean", "std"] {
+            assert_eq!(channels(&stats[key][stat]).len(), num_channels);
+        }
+        assert_eq!(stats[key]["count"], json!([5]));
+    }
+    assert_eq!(stats["frame_index"]["max"], json!([4.0]));
+    assert_eq!(stats["timestamp"]["max"], json!([0.004f32 as f64]));
+
+    let stats = &episodes_stats[1]["stats"];
+    assert_eq!(episodes_stats[1]["episode_index"], 1);
+    assert_eq!(stats["index"]["min"], json!([5.0]));
+    assert_eq!(stats["index"]["max"], json!([7.0]));
+    assert_eq!(stats["task_index"]["mean"], json!([1.0]));
+}
@@ -0,0 +1,144 @@
+//! The MCAP export of the recordings of `examples/data`, read back with the `mcap` reader.
+#![cfg(feature = "export")]
+
+mod common;
+
+use std::{collections::BTreeMap, fs};
+
+use mcap::MessageStream;
+use rerun_query::{export_mcap, query_data_entities, ChunkData};
+use serde_json::Value;
+
+use common::{log_times, tensor_rows, GRIPPER_RRD, JOINTS};
+
+const LEFT: &str = "/action/gripper/left/position";
+
+/// JSON numbers are parsed back without the last bit of precision.
+fn assert_close(value: f64, expected: f64) {
+    assert!(
+        (value - expected).abs() <= 1e-12 * expected.abs().max(1.0),
+        "{} != {}",
+        value,
+        expected
+    );
+}
+
+#[test]
+fn mcap_export_reads_back() {
+    let dir = tempfile::tempdir().unwrap();
+
+    for compression in ["zstd", "lz4", "none"] {
+        let out_path = dir.path().join(format!("gripper-{}.mcap", compression));
+        let out_path = out_path.to_str().unwrap();
+        let count = export_mcap(GRIPPER_RRD, out_path, "", "log_time", compression).unwrap();
+        // Three entities of 1203 rows, and two static text entities
+        assert_eq!(count, 3 * 1203 + 2);
+
+        let bytes = fs::read(out_path).unwrap();
+        let mut schemas: BTreeMap<String, (String, String, String)> = BTreeMap::new();
+        let mut messages: BTreeMap<String, Vec<(u64, Value)>> = BTreeMap::new();
+        let mut last_time = 0;
+        for message in MessageStream::new(&bytes).unwrap() {
+            let message = message.unwrap();
+            let channel = &message.channel;
+            let schema = channel.schema.as_ref().unwrap();
+            schemas.insert(
+                channel.topic.clone(),
+                (
+                    schema.name.clone(),
+                    schema.encoding.clone(),
+                    channel.message_encoding.clone(),
+                ),
+            );
+            assert!(message.log_time >= last_time, "messages are in time order");
+            assert_eq!(message.publish_time, message.log_time);
+            last_time = message.log_time;
+
+            let value: Value = serde_json::from_slice(&message.data).unwrap();
+            messages
+                .entry(channel.topic.clone())
+                .or_default()
+                .push((message.log_time, value));
+        }
+        assert_eq!(messages.values().map(Vec::len).sum::<usize>(), count);
+
+        let schema = |name: &str| {
+            (
+                name.to_string(),
+                "jsonschema".to_string(),
+                "json".to_string(),
+            )
+        };
+        assert_eq!(
+            schemas,
+            BTreeMap::from([
+                (LEFT.to_string(), schema("requery.Scalar")),
+                (
+                    "/action/gripper/right/position".to_string(),
+                    schema("requery.Scalar")
+                ),
+                (JOINTS.to_string(), schema("requery.Tensor")),
+                ("/meta".to_string(), schema("foxglove.Log")),
+                (
+                    "/observations/joints/names".to_string(),
+                    schema("foxglove.Log")
+                ),
+            ])
+        );
+
+        // Static text is written at time zero
+        assert_eq!(messages["/meta"].len(), 1);
+        assert_eq!(messages["/meta"][0].0, 0);
+
+        let mut scalars: Vec<(i64, f64)> = Vec::new();
+        for chunk in query_data_entities(GRIPPER_RRD, "scalar", LEFT).unwrap() {
+            let ChunkData::Scalar(values) = &chunk.data else {
+                panic!("{} is not a scalar entity", LEFT);
+            };
+            let times = &chunk.timelines["log_time"];
+            scalars.extend(times.iter().copied().zip(values.values().iter().copied()));
+        }
+        scalars.sort_by_key(|(time, _)| *time);
+        let left: Vec<(i64, f64)> = messages[LEFT]
+            .iter()
+            .map(|(log_time, value)| {
+                let timestamp = &value["timestamp"];
+                let time = timestamp["sec"].as_i64().unwrap() * 1_000_000_000
+                    + timestamp["nsec"].as_i64().unwrap();
+                assert_eq!(time, *log_time as i64);
+                (time, value["value"].as_f64().unwrap())
+            })
+            .collect();
+        assert_eq!(left.len(), scalars.len());
+        for ((time, value), (expected_time, expected)) in left.iter().zip(&scalars) {
+            assert_eq!(time, expected_time);
+            assert_close(*value, *expected);
+        }
+
+        let mut expected: Vec<(u64, Vec<f64>)> = log_times(GRIPPER_RRD, JOINTS)
+            .into_iter()
+            .map(|time| time as u64)
+            .zip(tensor_rows(GRIPPER_RRD, JOINTS))
+            .collect();
+        expected.sort_by_key(|(time, _)| *time);
+        let joints: Vec<(u64, Vec<f64>)> = messages[JOINTS]
+            .iter()
+            .map(|(log_time, value)| {
+                assert_eq!(value["shape"], serde_json::json!([expected[0].1.len()]));
+                let data = value["data"].as_array().unwrap();
+                (
+                    *log_time,
+                    data.iter().map(|value| value.as_f64().unwrap()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(joints.len(), expected.len());
+        for ((time, values), (expected_time, expected)) in joints.iter().zip(&expected) {
+            assert_eq!(time, expected_time);
+            assert_eq!(values.len(), expected.len());
+            for (value, expected) in values.iter().zip(expected) {
+                assert_close(*value, *expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! Round trip of ROS 2 messages through `import_mcap` and the query functions.
+#![cfg(feature = "export")]
+
+use std::{collections::BTreeMap, fs::File, io::BufWriter, path::Path};
+
+use mcap::{records::MessageHeader, Writer};
+use re_arrow2::array::Float64Array;
+use re_types::{
+    components::{Blob, ImageBuffer, ImageFormat, MediaType, Text, TextLogLevel},
+    datatypes::{self, ChannelDatatype, ColorModel},
+    Component,
+};
+use rerun_query::{import_mcap, load_bundle, query_data_entities, ChunkData};
+
+/// A CDR message in little endian, aligned relative to the end of its encapsulation header.
+struct CdrWriter(Vec<u8>);
+
+impl CdrWriter {
+    fn new() -> Self {
+        Self(vec![0x00, 0x01, 0x00, 0x00])
+    }
+
+    fn align(&mut self, alignment: usize) {
+        while !(self.0.len() - 4).is_multiple_of(alignment) {
+            self.0.push(0);
+        }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.align(8);
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32 + 1);
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(0);
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value);
+    }
+
+    fn header(&mut self, stamp_sec: u32) {
+        self.u32(stamp_sec);
+        self.u32(0);
+        self.string("base");
+    }
+
+    fn f64_sequence(&mut self, values: &[f64]) {
+        self.u32(values.len() as u32);
+        for value in values {
+            self.f64(*value);
+        }
+    }
+}
+
+fn joint_state(stamp_sec: u32, position: &[f64]) -> Vec<u8> {
+    let mut cdr = CdrWriter::new();
+    cdr.header(stamp_sec);
+    cdr.u32(2);
+    cdr.string("shoulder");
+    cdr.string("elbow");
+    cdr.f64_sequence(position);
+    cdr.f64_sequence(&[]);
+    cdr.f64_sequence(&[]);
+    cdr.0
+}
+
+fn float64(value: f64) -> Vec<u8> {
+    let mut cdr = CdrWriter::new();
+    cdr.f64(value);
+    cdr.0
+}
+
+/// A `sensor_msgs/msg/Image` of `height` rows of `step` bytes.
+fn image(
+    height: u32,
+    width: u32,
+    encoding: &str,
+    is_bigendian: bool,
+    step: u32,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut cdr = CdrWriter::new();
+    cdr.header(0);
+    cdr.u32(height);
+    cdr.u32(width);
+    cdr.string(encoding);
+    cdr.u8(is_bigendian as u8);
+    cdr.u32(step);
+    cdr.bytes(data);
+    cdr.0
+}
+
+fn compressed_image(format: &str, data: &[u8]) -> Vec<u8> {
+    let mut cdr = CdrWriter::new();
+    cdr.header(0);
+    cdr.string(format);
+    cdr.bytes(data);
+    cdr.0
+}
+
+fn log(level: u8, name: &str, msg: &str) -> Vec<u8> {
+    let mut cdr = CdrWriter::new();
+    cdr.u32(0);
+    cdr.u32(0);
+    cdr.u8(level);
+    cdr.string(name);
+    cdr.string(msg);
+    cdr.string("node.cpp");
+    cdr.string("spin");
+    cdr.u32(42);
+    cdr.0
+}
+
+/// Write the messages of every topic, the message `i` of a topic at 100 ms after 1 s times `i`.
+fn write_bag(path: &Path, topics: &[(&str, &str, Vec<Vec<u8>>)]) {
+    let mut writer = Writer::new(BufWriter::new(File::create(path).unwrap())).unwrap();
+    for (topic, schema_name, messages) in topics {
+        let schema_id = writer.add_schema(schema_name, "ros2msg", &[]).unwrap();
+        let channel_id = writer
+            .add_channel(schema_id, topic, "cdr", &BTreeMap::new())
+            .unwrap();
+        for (step, message) in messages.iter().enumerate() {
+            let log_time = 1_000_000_000 + step as u64 * 100_000_000;
+            let header = MessageHeader {
+                channel_id,
+                sequence: step as u32,
+                log_time,
+                publish_time: log_time,
+            };
+            writer.write_to_known_channel(&header, message).unwrap();
+        }
+    }
+    writer.finish().unwrap();
+}
+
+/// Import a bag into an RRD file of the same directory, returning the number of imported messages.
+fn import(bag: &Path) -> usize {
+    let rrd = bag.with_extension("rrd");
+    import_mcap(
+        bag.to_str().unwrap(),
+        rrd.to_str().unwrap(),
+        "",
+        "round_trip",
+    )
+    .unwrap()
+}
+
+/// The first instance of a component in every row of an entity, in the order of the messages.
+fn components<C: Component + Clone>(rrd: &Path, entity_path: &str) -> Vec<C> {
+    let bundle = load_bundle(rrd.to_str().unwrap()).unwrap();
+    let mut values = Vec::new();
+    for rrd in bundle.entity_dbs() {
+        for chunk in rrd.store().iter_chunks() {
+            if chunk.entity_path().to_string() == entity_path {
+                values.extend(chunk.iter_component::<C>().map(|row| row[0].clone()));
+            }
+        }
+    }
+    values
+}
+
+fn blob_bytes(blob: &datatypes::Blob) -> Vec<u8> {
+    blob.0.as_slice().to_vec()
+}
+
+#[test]
+fn imported_bag_can_be_queried() {
+    let dir = tempfile::tempdir().unwrap();
+    let bag = dir.path().join("bag.mcap");
+    let steps = 0..3u32;
+    write_bag(
+        &bag,
+        &[
+            (
+                "/joint_states",
+                "sensor_msgs/msg/JointState",
+                steps
+                    .clone()
+                    .map(|step| joint_state(step, &[step as f64, -(step as f64)]))
+                    .collect(),
+            ),
+            (
+                "/gripper",
+                "std_msgs/msg/Float64",
+                steps.map(|step| float64(0.5 * step as f64)).collect(),
+            ),
+        ],
+    );
+
+    assert_eq!(import(&bag), 6);
+    let rrd = bag.with_extension("rrd");
+    let rrd = rrd.to_str().unwrap();
+
+    let chunks = query_data_entities(rrd, "scalar", "/gripper").unwrap();
+    let mut values = Vec::new();
+    for chunk in &chunks {
+        assert_eq!(chunk.entity_path, "/gripper");
+        let ChunkData::Scalar(scalars) = &chunk.data else {
+            panic!("/gripper is not a scalar entity");
+        };
+        values.extend(scalars.values_iter().copied());
+    }
+    assert_eq!(values, [0.0, 0.5, 1.0]);
+
+    let chunks = query_data_entities(rrd, "tensor", "/joint_states/position").unwrap();
+    let mut positions = Vec::new();
+    for chunk in &chunks {
+        assert_eq!(chunk.entity_path, "/joint_states/position");
+        assert_eq!(chunk.timelines["log_time"].len(), chunk.data.len());
+        let ChunkData::Tensor(tensors) = &chunk.data else {
+            panic!("/joint_states/position is not a tensor entity");
+        };
+        for row in tensors.iter() {
+            let row = row.unwrap();
+            let row = row.as_any().downcast_ref::<Float64Array>().unwrap();
+            positions.push(row.values().to_vec());
+        }
+    }
+    assert_eq!(
+        positions,
+        [vec![0.0, -0.0], vec![1.0, -1.0], vec![2.0, -2.0]]
+    );
+}
+
+#[test]
+fn images_are_packed_and_converted_to_rgb_and_little_endian() {
+    let dir = tempfile::tempdir().unwrap();
+    let bag = dir.path().join("bag.mcap");
+    // Two rows of two BGR pixels, padded to 8 bytes
+    let bgr = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0];
+    let mono16 = [0x01, 0x02, 0x03, 0x04];
+    write_bag(
+        &bag,
+        &[(
+            "/camera/image_raw",
+            "sensor_msgs/msg/Image",
+            vec![
+                image(2, 2, "bgr8", false, 8, &bgr),
+                image(1, 2, "mono16", true, 4, &mono16),
+                // No Rerun equivalent, skipped
+                image(2, 2, "bayer_rggb8", false, 2, &[0; 4]),
+            ],
+        )],
+    );
+
+    assert_eq!(import(&bag), 2);
+    let rrd = bag.with_extension("rrd");
+    let buffers: Vec<Vec<u8>> = components::<ImageBuffer>(&rrd, "/camera/image_raw")
+        .iter()
+        .map(|buffer| blob_bytes(&buffer.0))
+        .collect();
+    assert_eq!(
+        buffers,
+        [
+            vec![3, 2, 1, 6, 5, 4, 9, 8, 7, 12, 11, 10],
+            vec![0x02, 0x01, 0x04, 0x03],
+        ]
+    );
+    let formats: Vec<_> = components::<ImageFormat>(&rrd, "/camera/image_raw")
+        .into_iter()
+        .map(|format| {
+            let format = format.0;
+            (
+                format.width,
+                format.height,
+                format.color_model,
+                format.channel_datatype,
+            )
+        })
+        .collect();
+    assert_eq!(
+        formats,
+        [
+            (2, 2, Some(ColorModel::RGB), Some(ChannelDatatype::U8)),
+            (2, 1, Some(ColorModel::L), Some(ChannelDatatype::U16)),
+        ]
+    );
+}
+
+#[test]
+fn compressed_images_keep_their_bytes_and_media_type() {
+    let dir = tempfile::tempdir().unwrap();
+    let bag = dir.path().join("bag.mcap");
+    let (png, jpeg) = (vec![0x89, b'P', b'N', b'G'], vec![0xFF, 0xD8, 0xFF]);
+    write_bag(
+        &bag,
+        &[(
+            "/camera/compressed",
+            "sensor_msgs/msg/CompressedImage",
+            vec![
+                compressed_image("png", &png),
+                compressed_image("rgb8; jpeg compressed bgr8", &jpeg),
+            ],
+        )],
+    );
+
+    assert_eq!(import(&bag), 2);
+    let rrd = bag.with_extension("rrd");
+    let blobs: Vec<Vec<u8>> = components::<Blob>(&rrd, "/camera/compressed")
+        .iter()
+        .map(|blob| blob_bytes(&blob.0))
+        .collect();
+    assert_eq!(blobs, [png, jpeg]);
+    let media_types: Vec<String> = components::<MediaType>(&rrd, "/camera/compressed")
+        .iter()
+        .map(|media_type| media_type.0.as_str().to_string())
+        .collect();
+    assert_eq!(media_types, [MediaType::PNG, MediaType::JPEG]);
+}
+
+#[test]
+fn logs_are_text_logs_with_their_level() {
+    let dir = tempfile::tempdir().unwrap();
+    let bag = dir.path().join("bag.mcap");
+    write_bag(
+        &bag,
+        &[(
+            "/rosout",
+            "rcl_interfaces/msg/Log",
+            vec![
+                log(20, "planner", "Plan found"),
+                log(30, "driver", "Joint limit near"),
+                log(50, "driver", "E-stop"),
+            ],
+        )],
+    );
+
+    assert_eq!(import(&bag), 3);
+    let rrd = bag.with_extension("rrd");
+    let texts: Vec<String> = components::<Text>(&rrd, "/rosout")
+        .iter()
+        .map(|text| text.0.as_str().to_string())
+        .collect();
+    assert_eq!(
+        texts,
+        [
+            "[planner] Plan found",
+            "[driver] Joint limit near",
+            "[driver] E-stop"
+        ]
+    );
+    let levels: Vec<String> = components::<TextLogLevel>(&rrd, "/rosout")
+        .iter()
+        .map(|level| level.0.as_str().to_string())
+        .collect();
+    assert_eq!(
+        levels,
+        [
+            TextLogLevel::INFO,
+            TextLogLevel::WARN,
+            TextLogLevel::CRITICAL
+        ]
+    );
+}
@@ -0,0 +1,140 @@
+//! The npz export, read back and checked against the queries on the original files.
+#![cfg(feature = "export")]
+
+mod common;
+
+use std::{fs::File, io::Read, path::Path};
+
+use re_chunk::{Chunk, RowId};
+use re_log_types::{TimePoint, Timeline};
+use re_types::components;
+use rerun_query::export_npz;
+use zip::ZipArchive;
+
+use common::{
+    decode_le, f32_tensor, log_times, tensor_rows, timepoint, write_recording, GRIPPER_RRD, JOINTS,
+    START_TIME,
+};
+
+/// The shape, element type and bytes of an array, from its `.npy` header.
+fn parse_npy(bytes: &[u8]) -> (Vec<usize>, String, &[u8]) {
+    assert!(bytes.starts_with(b"\x93NUMPY\x01\x00"));
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+    let field = |name: &str| {
+        let start = header.find(&format!("'{}': ", name)).unwrap() + name.len() + 4;
+        &header[start..]
+    };
+    let descr = field("descr")[1..].split('\'').next().unwrap().to_string();
+    let shape = field("shape")[1..]
+        .split(')')
+        .next()
+        .unwrap()
+        .split(',')
+        .filter(|size| !size.trim().is_empty())
+        .map(|size| size.trim().parse().unwrap())
+        .collect();
+    (shape, descr, &bytes[10 + header_len..])
+}
+
+/// The bytes of one `.npy` file of an npz archive.
+fn read_npy(npz_path: &Path, name: &str) -> Vec<u8> {
+    let mut archive = ZipArchive::new(File::open(npz_path).unwrap()).unwrap();
+    let mut bytes = Vec::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    bytes
+}
+
+#[test]
+fn npz_export_reads_back() {
+    let expected = tensor_rows(GRIPPER_RRD, JOINTS);
+    let dir = tempfile::tempdir().unwrap();
+    let out_path = dir.path().join("gripper.npz");
+
+    let names = export_npz(GRIPPER_RRD, out_path.to_str().unwrap(), "", true).unwrap();
+    for name in [
+        "action/joints/position/TensorData",
+        "action/joints/position/log_time",
+        "action/gripper/left/position/Scalar",
+    ] {
+        assert!(
+            names.iter().any(|existing| existing == name),
+            "{} missing",
+            name
+        );
+    }
+
+    let bytes = read_npy(&out_path, "action/joints/position/TensorData.npy");
+    let (shape, descr, data) = parse_npy(&bytes);
+    assert_eq!(shape, [expected.len(), expected[0].len()]);
+    assert_eq!(decode_le(&descr, data), expected.concat());
+
+    let bytes = read_npy(&out_path, "action/joints/position/log_time.npy");
+    let (shape, descr, data) = parse_npy(&bytes);
+    assert_eq!(descr, "<i8");
+    assert_eq!(shape, [expected.len()]);
+    let times: Vec<i64> = data
+        .chunks_exact(8)
+        .map(|time| i64::from_le_bytes(time.try_into().unwrap()))
+        .collect();
+    assert_eq!(times, log_times(GRIPPER_RRD, JOINTS));
+}
+
+#[test]
+fn chunks_without_tensors_are_missing_rows() {
+    let tensors = |rows: std::ops::Range<i64>| {
+        let mut builder = Chunk::builder(JOINTS.into());
+        for i in rows {
+            let tensor = f32_tensor(vec![i as f32, i as f32 + 0.5]);
+            builder = builder.with_archetype(RowId::new(), timepoint(i), &tensor);
+        }
+        builder.build().unwrap()
+    };
+    let cleared = |rows: std::ops::Range<i64>| {
+        let mut builder = Chunk::builder(JOINTS.into());
+        for i in rows {
+            // Without `log_tick`, so that the store does not merge them with the tensors
+            let timepoint =
+                TimePoint::default().with(Timeline::log_time(), START_TIME + i * 1_000_000);
+            let empty = Vec::<components::TensorData>::new();
+            builder = builder.with_component_batch(RowId::new(), timepoint, &empty);
+        }
+        builder.build().unwrap()
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let rrd_path = dir.path().join("cleared.rrd");
+    // Without tensors before and after the element type is known
+    write_recording(
+        &rrd_path,
+        &[cleared(0..1), tensors(1..3), cleared(3..5), tensors(5..6)],
+    );
+
+    let out_path = dir.path().join("cleared.npz");
+    export_npz(
+        rrd_path.to_str().unwrap(),
+        out_path.to_str().unwrap(),
+        JOINTS,
+        false,
+    )
+    .unwrap();
+
+    let bytes = read_npy(&out_path, "action/joints/position/TensorData.npy");
+    let (shape, descr, data) = parse_npy(&bytes);
+    assert_eq!(shape, [6, 2]);
+    assert_eq!(descr, "<f4");
+    let values = decode_le(&descr, data);
+    // The store may merge chunks, so the rows are checked by time
+    let bytes = read_npy(&out_path, "action/joints/position/log_time.npy");
+    let (_, _, times) = parse_npy(&bytes);
+    for (time, values) in times.chunks_exact(8).zip(values.chunks(2)) {
+        let i = (i64::from_le_bytes(time.try_into().unwrap()) - START_TIME) / 1_000_000;
+        match i {
+            0 | 3 | 4 => assert!(values.iter().all(|value| value.is_nan()), "row {}", i),
+            _ => assert_eq!(values, [i as f64, i as f64 + 0.5]),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! The Parquet export, read back and checked against the queries on the original files.
+#![cfg(feature = "export")]
+
+mod common;
+
+use std::fs::File;
+
+use re_arrow2::{array::ListArray, io::parquet::read};
+use rerun_query::export_parquet;
+
+use common::{list_rows, tensor_rows, GRIPPER_RRD, JOINTS};
+
+#[test]
+fn parquet_export_reads_back() {
+    let expected = tensor_rows(GRIPPER_RRD, JOINTS);
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().to_str().unwrap();
+
+    let written = export_parquet(GRIPPER_RRD, out_dir, JOINTS, "zstd").unwrap();
+    let path = dir.path().join("action/joints/position.parquet");
+    assert_eq!(written, [path.to_string_lossy()]);
+
+    let mut file = File::open(&path).unwrap();
+    let metadata = read::read_metadata(&mut file).unwrap();
+    let schema = read::infer_schema(&metadata).unwrap();
+    let names: Vec<&str> = schema
+        .fields
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect();
+    for name in ["log_time", "log_tick", "TensorData"] {
+        assert!(names.contains(&name), "{} missing from {:?}", name, names);
+    }
+    let column = names.iter().position(|name| *name == "TensorData").unwrap();
+
+    let mut rows = Vec::new();
+    let reader = read::FileReader::new(file, metadata.row_groups, schema, None, None, None);
+    for chunk in reader {
+        let chunk = chunk.unwrap();
+        let tensors = chunk.arrays()[column]
+            .as_any()
+            .downcast_ref::<ListArray<i32>>()
+            .unwrap();
+        rows.extend(list_rows(tensors));
+    }
+    assert_eq!(rows, expected);
+}
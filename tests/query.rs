@@ -0,0 +1,77 @@
+//! The chunks selected by the entity filter of data queries, looked up per entity when the filter
+//! matches a single entity and scanned otherwise.
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use re_chunk::{Chunk, ChunkId};
+use rerun_query::{is_data_chunk, load_bundle, query_chunks, query_data_chunks};
+
+use common::{scalars, write_recording, GRIPPER_RRD};
+
+/// The entity path and the id of every chunk.
+fn ids(chunks: &[Chunk]) -> Vec<(String, ChunkId)> {
+    chunks
+        .iter()
+        .map(|chunk| (chunk.entity_path().to_string(), chunk.id()))
+        .collect()
+}
+
+/// The data chunks whose entity path contains the filter, found by scanning every chunk.
+fn scanned(file_path: &str, entity_path_filter: &str) -> Vec<(String, ChunkId)> {
+    let bundle = load_bundle(file_path).unwrap();
+    let rrd = bundle.entity_dbs().next().unwrap();
+    ids(&query_chunks(rrd, |chunk| {
+        chunk.entity_path().to_string().contains(entity_path_filter) && is_data_chunk(chunk)
+    }))
+}
+
+fn queried(file_path: &str, entity_path_filter: &str) -> Vec<(String, ChunkId)> {
+    let bundle = load_bundle(file_path).unwrap();
+    let rrd = bundle.entity_dbs().next().unwrap();
+    ids(&query_data_chunks(rrd, "", entity_path_filter))
+}
+
+#[test]
+fn entity_lookups_return_the_scanned_chunks() {
+    let bundle = load_bundle(GRIPPER_RRD).unwrap();
+    let rrd = bundle.entity_dbs().next().unwrap();
+    for entity_path in rrd.entity_paths() {
+        let entity_path = entity_path.to_string();
+        assert_eq!(
+            queried(GRIPPER_RRD, &entity_path),
+            scanned(GRIPPER_RRD, &entity_path),
+            "{}",
+            entity_path
+        );
+    }
+}
+
+#[test]
+fn filters_still_match_the_children_of_an_entity() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recording.rrd");
+    let file_path = path.to_str().unwrap();
+    write_recording(
+        &path,
+        &[
+            scalars("/arm", 3, 0.0),
+            scalars("/arm/left", 2, 10.0),
+            scalars("/arm", 2, 20.0),
+            scalars("/leg", 1, 30.0),
+        ],
+    );
+
+    for filter in ["/arm", "/arm/left", "left", "/leg", "/"] {
+        let queried = queried(file_path, filter);
+        assert!(!queried.is_empty(), "{}", filter);
+        assert_eq!(queried, scanned(file_path, filter), "{}", filter);
+    }
+    let mut entity_paths: Vec<String> = queried(file_path, "/arm")
+        .into_iter()
+        .map(|(entity_path, _)| entity_path)
+        .collect();
+    entity_paths.dedup();
+    assert_eq!(entity_paths, ["/arm", "/arm/left"]);
+    assert!(queried(file_path, "/missing").is_empty());
+}
@@ -0,0 +1,99 @@
+//! The RRD rewrites, read back and checked against the queries on the original files.
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use std::path::Path;
+
+use rerun_query::{merge_rrds, query_data_entities, split_rrd, write_filtered_rrd};
+
+use common::{log_times, tensor_rows, GRIPPER_RRD, JOINTS, SAMPLE_RRD};
+
+#[test]
+fn filtered_rrd_can_be_queried() {
+    let times = log_times(GRIPPER_RRD, JOINTS);
+    let time_range = (times[100], times[199]);
+    let expected: Vec<Vec<f64>> = tensor_rows(GRIPPER_RRD, JOINTS)
+        .into_iter()
+        .zip(&times)
+        .filter(|(_, time)| (time_range.0..=time_range.1).contains(*time))
+        .map(|(row, _)| row)
+        .collect();
+    assert_eq!(expected.len(), 100);
+
+    let dir = tempfile::tempdir().unwrap();
+    let dst = dir.path().join("filtered.rrd");
+    let dst = dst.to_str().unwrap();
+    write_filtered_rrd(
+        GRIPPER_RRD,
+        dst,
+        "/action/joints",
+        Some(time_range),
+        "",
+        "log_time",
+        true,
+    )
+    .unwrap();
+
+    let chunks = query_data_entities(dst, "", "").unwrap();
+    assert!(chunks.iter().all(|chunk| chunk.entity_path == JOINTS));
+    assert_eq!(tensor_rows(dst, JOINTS), expected);
+}
+
+#[test]
+fn merged_rrds_can_be_queried() {
+    let dir = tempfile::tempdir().unwrap();
+    let merged = dir.path().join("merged.rrd");
+    let merged = merged.to_str().unwrap();
+    merge_rrds(
+        vec![SAMPLE_RRD.to_string(), GRIPPER_RRD.to_string()],
+        merged,
+        "",
+        vec!["log_tick".to_string()],
+    )
+    .unwrap();
+
+    let sample_rows = tensor_rows(SAMPLE_RRD, JOINTS);
+    let gripper_rows = tensor_rows(GRIPPER_RRD, JOINTS);
+    let mut merged_rows = tensor_rows(merged, JOINTS);
+    merged_rows.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mut expected = [sample_rows.clone(), gripper_rows.clone()].concat();
+    expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(merged_rows, expected);
+    let gripper = query_data_entities(merged, "scalar", "/action/gripper/left/position").unwrap();
+    assert_eq!(
+        gripper.iter().map(|chunk| chunk.data.len()).sum::<usize>(),
+        1203
+    );
+}
+
+#[test]
+fn split_rrds_can_be_queried() {
+    let dir = tempfile::tempdir().unwrap();
+    let merged = dir.path().join("merged.rrd");
+    let merged = merged.to_str().unwrap();
+    merge_rrds(
+        vec![SAMPLE_RRD.to_string(), GRIPPER_RRD.to_string()],
+        merged,
+        "",
+        vec!["log_tick".to_string()],
+    )
+    .unwrap();
+    let sample_rows = tensor_rows(SAMPLE_RRD, JOINTS);
+    let gripper_rows = tensor_rows(GRIPPER_RRD, JOINTS);
+
+    // The two recordings are days apart, so an hour long gap splits them again
+    let episodes_dir = dir.path().join("episodes");
+    let episodes = split_rrd(
+        merged,
+        episodes_dir.to_str().unwrap(),
+        "",
+        Some(3_600_000_000_000),
+        "log_time",
+    )
+    .unwrap();
+    assert_eq!(episodes.len(), 2);
+    assert!(Path::new(&episodes[0]).ends_with("episodes/episode_000000.rrd"));
+    assert_eq!(tensor_rows(&episodes[0], JOINTS), sample_rows);
+    assert_eq!(tensor_rows(&episodes[1], JOINTS), gripper_rows);
+}
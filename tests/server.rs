@@ -0,0 +1,220 @@
+//! The HTTP service over `examples/data`, called through the actix-web test service.
+#![cfg(feature = "server")]
+
+mod common;
+
+use std::{fs, io::Cursor, path::PathBuf};
+
+use actix_web::{
+    http::{header, StatusCode},
+    test,
+    web::{self, Bytes},
+    App,
+};
+use re_arrow2::io::ipc::read::read_stream_metadata;
+use rerun_query::{
+    query_data_entities, query_entity_table, query_meta_entities,
+    server::{configure, AppState},
+    ChunkData, TableQuery,
+};
+use serde_json::{json, Value};
+
+use common::{list_rows, log_times, GRIPPER_RRD, JOINTS};
+
+const DATA_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/data");
+
+/// Call the service on a GET request, returning the status, the content type and the body.
+async fn get(uri: &str) -> (StatusCode, String, Bytes) {
+    let service = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState {
+                folder_path: PathBuf::from(DATA_DIR),
+            }))
+            .configure(configure),
+    )
+    .await;
+    let response =
+        test::call_service(&service, test::TestRequest::get().uri(uri).to_request()).await;
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    (
+        response.status(),
+        content_type,
+        test::read_body(response).await,
+    )
+}
+
+async fn get_json(uri: &str) -> (StatusCode, Value) {
+    let (status, content_type, body) = get(uri).await;
+    assert_eq!(content_type, "application/json", "{:?}", body);
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[actix_web::test]
+async fn recordings_are_listed_by_name() {
+    let (status, recordings) = get_json("/api/recordings").await;
+    assert_eq!(status, StatusCode::OK);
+    let names: Vec<&str> = recordings
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|recording| recording["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["robot_action_gripper.rrd", "sample-0.18.rrd"]);
+    assert_eq!(
+        recordings[0]["size"],
+        fs::metadata(GRIPPER_RRD).unwrap().len()
+    );
+}
+
+#[actix_web::test]
+async fn stores_and_entities_are_summarized() {
+    let (status, stores) = get_json("/api/recordings/robot_action_gripper.rrd").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(stores
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|store| store["kind"] == "recording"));
+
+    let (status, entities) = get_json("/api/recordings/robot_action_gripper.rrd/entities").await;
+    assert_eq!(status, StatusCode::OK);
+    let joints = entities
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entity| entity["entity_path"] == JOINTS)
+        .unwrap();
+    let times = log_times(GRIPPER_RRD, JOINTS);
+    assert_eq!(joints["timelines"]["log_time"]["type"], "time");
+    assert_eq!(joints["timelines"]["log_time"]["min"], times[0]);
+    assert_eq!(
+        joints["timelines"]["log_time"]["max"],
+        *times.last().unwrap()
+    );
+}
+
+#[actix_web::test]
+async fn meta_returns_the_text_entities() {
+    let (status, meta) = get_json("/api/recordings/robot_action_gripper.rrd/meta").await;
+    assert_eq!(status, StatusCode::OK);
+    let expected: Vec<Value> = query_meta_entities(GRIPPER_RRD, "")
+        .unwrap()
+        .into_iter()
+        .map(|meta| {
+            json!({
+                "entity_path": meta.entity_path,
+                "media_type": meta.media_type,
+                "text": meta.text,
+            })
+        })
+        .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(meta, json!(expected));
+
+    let (status, error) =
+        get_json("/api/recordings/robot_action_gripper.rrd/meta?entity=/missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(error["error"].is_string());
+}
+
+#[actix_web::test]
+async fn data_is_returned_as_json() {
+    let times = log_times(GRIPPER_RRD, JOINTS);
+    let uri = format!(
+        "/api/recordings/robot_action_gripper.rrd/data?entity={}&start={}&end={}",
+        JOINTS, times[1], times[2]
+    );
+    let (status, data) = get_json(&uri).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let mut rows = Vec::new();
+    let mut row_times = Vec::new();
+    for chunk in data.as_array().unwrap() {
+        assert_eq!(chunk["entity_path"], JOINTS);
+        for (time, row) in chunk["timelines"]["log_time"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .zip(chunk["data"].as_array().unwrap())
+        {
+            row_times.push(time.as_i64().unwrap());
+            rows.push(serde_json::from_value::<Vec<f64>>(row.clone()).unwrap());
+        }
+    }
+    assert_eq!(row_times, &times[1..3]);
+
+    // JSON numbers are parsed back to the nearest float or a neighbour of it
+    let expected: Vec<Vec<f64>> = query_data_entities(GRIPPER_RRD, "", JOINTS)
+        .unwrap()
+        .into_iter()
+        .flat_map(|chunk| match chunk.data {
+            ChunkData::Tensor(tensors) => list_rows(&tensors),
+            ChunkData::Scalar(_) => panic!("Expected tensors"),
+        })
+        .collect();
+    assert_eq!(rows.len(), 2);
+    for (row, expected) in rows.iter().zip(&expected[1..3]) {
+        assert_eq!(row.len(), expected.len());
+        for (value, expected) in row.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() <= 1e-12 * expected.abs(),
+                "{} != {}",
+                value,
+                expected
+            );
+        }
+    }
+}
+
+#[actix_web::test]
+async fn data_is_returned_as_an_arrow_stream() {
+    let uri = format!(
+        "/api/recordings/robot_action_gripper.rrd/data?entity={}&format=arrow",
+        JOINTS
+    );
+    let (status, content_type, body) = get(&uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type, "application/vnd.apache.arrow.stream");
+
+    let query = TableQuery {
+        entity: JOINTS.to_string(),
+        ..TableQuery::default()
+    };
+    let expected = query_entity_table(GRIPPER_RRD, &query)
+        .unwrap()
+        .to_ipc_stream()
+        .unwrap();
+    assert_eq!(body.as_ref(), expected.as_slice());
+    let metadata = read_stream_metadata(&mut Cursor::new(body.as_ref())).unwrap();
+    assert_eq!(metadata.schema.metadata["entity_path"], JOINTS);
+
+    let (status, error) = get_json(&uri.replace("format=arrow", "format=csv")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error["error"].as_str().unwrap().contains("csv"));
+}
+
+#[actix_web::test]
+async fn invalid_recording_names_are_rejected() {
+    for name in [
+        "..%2Fx.rrd",
+        "..",
+        "robot_action_gripper.txt",
+        "%2Fetc%2Fx.rrd",
+    ] {
+        let (status, error) = get_json(&format!("/api/recordings/{}/entities", name)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", name);
+        assert!(error["error"]
+            .as_str()
+            .unwrap()
+            .contains("Invalid recording name"));
+    }
+
+    let (status, _) = get_json("/api/recordings/missing.rrd/entities").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
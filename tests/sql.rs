@@ -0,0 +1,114 @@
+//! SQL queries over the tensor and scalar entities of a recording.
+#![cfg(feature = "sql")]
+
+mod common;
+
+use datafusion::arrow::{
+    array::{Array, Float64Array, Int64Array, ListArray},
+    datatypes::DataType,
+    record_batch::RecordBatch,
+};
+use re_chunk::{Chunk, RowId};
+use re_types::{archetypes::Scalar, components};
+use rerun_query::sql::sql;
+
+use common::{f32_tensor, timepoint, write_recording, JOINTS};
+
+const GRIPPER: &str = "/action/gripper";
+
+/// A recording with `float32` joint tensors, whose second chunk holds no tensor, and scalars.
+fn write_joints_and_gripper(path: &std::path::Path) {
+    let mut joints = Chunk::builder(JOINTS.into());
+    for i in 0..3 {
+        let tensor = f32_tensor(vec![i as f32, i as f32 + 0.5]);
+        joints = joints.with_archetype(RowId::new(), timepoint(i), &tensor);
+    }
+    let mut cleared = Chunk::builder(JOINTS.into());
+    for i in 3..5 {
+        cleared = cleared.with_component_batch(
+            RowId::new(),
+            timepoint(i),
+            &Vec::<components::TensorData>::new(),
+        );
+    }
+    let mut gripper = Chunk::builder(GRIPPER.into());
+    for i in 0..4 {
+        gripper = gripper.with_archetype(RowId::new(), timepoint(i), &Scalar::new(i as f64));
+    }
+    write_recording(
+        path,
+        &[
+            joints.build().unwrap(),
+            cleared.build().unwrap(),
+            gripper.build().unwrap(),
+        ],
+    );
+}
+
+fn num_rows(batches: &[RecordBatch]) -> usize {
+    batches.iter().map(|batch| batch.num_rows()).sum()
+}
+
+#[test]
+fn tensor_tables_keep_their_element_type() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("joints.rrd");
+    write_joints_and_gripper(&path);
+    let path = path.to_str().unwrap();
+
+    let query = format!(r#"SELECT "TensorData" FROM "{}" ORDER BY log_tick"#, JOINTS);
+    let batches = sql(path, &query).unwrap();
+    assert_eq!(num_rows(&batches), 5);
+    let tensors = batches[0]
+        .column(0)
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    assert_eq!(
+        tensors.data_type(),
+        &DataType::new_list(DataType::Float32, true)
+    );
+    let valid: usize = batches
+        .iter()
+        .map(|batch| batch.num_rows() - batch.column(0).null_count())
+        .sum();
+    assert_eq!(valid, 3);
+
+    // Only the chunk without tensors is in the time range
+    let query = format!(
+        r#"SELECT "TensorData" FROM "{}" WHERE log_tick >= 3"#,
+        JOINTS
+    );
+    let batches = sql(path, &query).unwrap();
+    assert_eq!(num_rows(&batches), 2);
+    assert!(batches
+        .iter()
+        .all(|batch| batch.column(0).null_count() == batch.num_rows()));
+}
+
+#[test]
+fn scalar_tables_can_be_aggregated() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("gripper.rrd");
+    write_joints_and_gripper(&path);
+
+    let query = format!(
+        r#"SELECT count(*) AS rows, avg("Scalar") AS mean FROM "{}" WHERE log_tick > 0"#,
+        GRIPPER
+    );
+    let batches = sql(path.to_str().unwrap(), &query).unwrap();
+    let rows = batches[0]
+        .column_by_name("rows")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .unwrap();
+    let mean = batches[0]
+        .column_by_name("mean")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap();
+    assert_eq!(rows.value(0), 3);
+    assert_eq!(mean.value(0), 2.0);
+}
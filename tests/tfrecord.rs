@@ -0,0 +1,214 @@
+//! The TFRecord export of the recordings of `examples/data`, read back record by record.
+#![cfg(feature = "export")]
+
+mod common;
+
+use std::{collections::BTreeMap, fs};
+
+use re_chunk::{Chunk, RowId};
+use re_types::archetypes::Scalar;
+use rerun_query::{export_tfrecord, query_data_entities, ChunkData, Error};
+
+use common::{timepoint, write_recording, GRIPPER_RRD};
+
+const LEFT: &str = "/action/gripper/left/position";
+
+/// A decoded `tf.train.Feature`.
+#[derive(Debug, PartialEq)]
+enum Feature {
+    Bytes(Vec<Vec<u8>>),
+    Float(Vec<f32>),
+    Int64(Vec<i64>),
+}
+
+fn varint(bytes: &mut &[u8]) -> u64 {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = bytes[0];
+        *bytes = &bytes[1..];
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            break;
+        }
+    }
+    value
+}
+
+/// The fields of a protobuf message whose fields are all length-delimited.
+fn fields(mut bytes: &[u8]) -> Vec<(u64, &[u8])> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let tag = varint(&mut bytes);
+        assert_eq!(tag & 7, 2, "field {} is not length-delimited", tag >> 3);
+        let len = varint(&mut bytes) as usize;
+        fields.push((tag >> 3, &bytes[..len]));
+        bytes = &bytes[len..];
+    }
+    fields
+}
+
+/// The features of a `tf.train.Example`.
+fn example_features(example: &[u8]) -> BTreeMap<String, Feature> {
+    let [(1, map)] = fields(example)[..] else {
+        panic!("An example holds a single Features message");
+    };
+    fields(map)
+        .into_iter()
+        .map(|(number, entry)| {
+            assert_eq!(number, 1);
+            let [(1, key), (2, feature)] = fields(entry)[..] else {
+                panic!("A feature map entry holds a key and a value");
+            };
+            let [(kind, list)] = fields(feature)[..] else {
+                panic!("A feature holds a single list");
+            };
+            let values = fields(list);
+            let feature = match kind {
+                1 => Feature::Bytes(values.iter().map(|(_, value)| value.to_vec()).collect()),
+                2 => Feature::Float(
+                    values[0]
+                        .1
+                        .chunks_exact(4)
+                        .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                        .collect(),
+                ),
+                3 => {
+                    let mut packed = values[0].1;
+                    let mut values = Vec::new();
+                    while !packed.is_empty() {
+                        values.push(varint(&mut packed) as i64);
+                    }
+                    Feature::Int64(values)
+                }
+                _ => panic!("Unknown feature kind {}", kind),
+            };
+            (String::from_utf8(key.to_vec()).unwrap(), feature)
+        })
+        .collect()
+}
+
+fn masked_crc(bytes: &[u8]) -> u32 {
+    crc32c::crc32c(bytes)
+        .rotate_right(15)
+        .wrapping_add(0xa282_ead8)
+}
+
+/// The payloads of the records of a TFRecord file, after checking their CRCs.
+fn read_records(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut records = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (len, tail) = rest.split_at(8);
+        let (len_crc, tail) = tail.split_at(4);
+        assert_eq!(
+            u32::from_le_bytes(len_crc.try_into().unwrap()),
+            masked_crc(len)
+        );
+        let len = u64::from_le_bytes(len.try_into().unwrap()) as usize;
+        let (data, tail) = tail.split_at(len);
+        let (data_crc, tail) = tail.split_at(4);
+        assert_eq!(
+            u32::from_le_bytes(data_crc.try_into().unwrap()),
+            masked_crc(data)
+        );
+        records.push(data);
+        rest = tail;
+    }
+    records
+}
+
+#[test]
+fn tfrecord_export_reads_back() {
+    let mut scalars: Vec<(i64, f64)> = Vec::new();
+    for chunk in query_data_entities(GRIPPER_RRD, "scalar", LEFT).unwrap() {
+        let ChunkData::Scalar(values) = &chunk.data else {
+            panic!("{} is not a scalar entity", LEFT);
+        };
+        let times = &chunk.timelines["log_time"];
+        scalars.extend(times.iter().copied().zip(values.values().iter().copied()));
+    }
+    scalars.sort_by_key(|(time, _)| *time);
+
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().to_str().unwrap();
+    let shards = export_tfrecord(GRIPPER_RRD, out_dir, "", LEFT, 30, "log_time", 40).unwrap();
+    // 3.005 s of data at 30 fps
+    let names: Vec<String> = shards
+        .iter()
+        .map(|shard| shard.rsplit('/').next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "robot_action_gripper-00000-of-00003.tfrecord",
+            "robot_action_gripper-00001-of-00003.tfrecord",
+            "robot_action_gripper-00002-of-00003.tfrecord",
+        ]
+    );
+
+    let mut frame = 0;
+    let mut start = None;
+    for shard in &shards {
+        let bytes = fs::read(shard).unwrap();
+        for record in read_records(&bytes) {
+            let features = example_features(record);
+            assert_eq!(
+                features.keys().collect::<Vec<_>>(),
+                [
+                    "action.gripper.left.position",
+                    "frame_index",
+                    "log_time",
+                    "timestamp"
+                ]
+            );
+            assert_eq!(features["frame_index"], Feature::Int64(vec![frame]));
+            let Feature::Int64(time) = &features["log_time"] else {
+                panic!("log_time is not an int64 feature");
+            };
+            let time = time[0];
+            let start = *start.get_or_insert(time);
+            assert_eq!(time - start, (frame as f64 * 1e9 / 30.0).round() as i64);
+            assert_eq!(
+                features["timestamp"],
+                Feature::Float(vec![((time - start) as f64 * 1e-9) as f32])
+            );
+            let latest = scalars.iter().rev().find(|(t, _)| *t <= time).unwrap().1;
+            assert_eq!(
+                features["action.gripper.left.position"],
+                Feature::Float(vec![latest as f32])
+            );
+            frame += 1;
+        }
+    }
+    assert_eq!(frame, 91);
+}
+
+#[test]
+fn feature_keys_must_be_unique() {
+    let scalars = |entity_path: &str| {
+        let mut builder = Chunk::builder(entity_path.into());
+        for i in 0..3 {
+            builder = builder.with_archetype(RowId::new(), timepoint(i), &Scalar::new(i as f64));
+        }
+        builder.build().unwrap()
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().join("out");
+    let out_dir = out_dir.to_str().unwrap();
+
+    // The time of the samples has the key of its timeline
+    let path = dir.path().join("timeline.rrd");
+    write_recording(&path, &[scalars("/gripper"), scalars("/log_time")]);
+    let path = path.to_str().unwrap();
+    let error = export_tfrecord(path, out_dir, "", "", 30, "log_time", 10).unwrap_err();
+    assert!(matches!(error.kind(), Error::Invalid(_)), "{}", error);
+    assert!(error.to_string().contains("log_time"), "{}", error);
+    assert!(export_tfrecord(path, out_dir, "", "/gripper", 30, "log_time", 10).is_ok());
+
+    let path = dir.path().join("dots.rrd");
+    write_recording(&path, &[scalars("/arm.left"), scalars("/arm/left")]);
+    let error =
+        export_tfrecord(path.to_str().unwrap(), out_dir, "", "", 30, "log_time", 10).unwrap_err();
+    assert!(matches!(error.kind(), Error::Invalid(_)), "{}", error);
+    assert!(error.to_string().contains("arm.left"), "{}", error);
+}
@@ -0,0 +1,79 @@
+//! The JavaScript API of src/wasm.rs on the bytes of an example recording, run in Node.js with
+//!
+//! ```sh
+//! CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner \
+//!     cargo test --target wasm32-unknown-unknown --no-default-features --features wasm --test wasm
+//! ```
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use rerun_query::wasm::Recording;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const GRIPPER_RRD: &[u8] = include_bytes!("../examples/data/robot_action_gripper.rrd");
+
+fn recording() -> Recording {
+    Recording::new(GRIPPER_RRD).map_err(JsValue::from).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn entity_paths() {
+    let entity_paths = recording().entity_paths();
+    for entity_path in [
+        "/action/gripper/left/position",
+        "/action/gripper/right/position",
+        "/action/joints/position",
+        "/meta",
+        "/viewport",
+    ] {
+        assert!(
+            entity_paths.iter().any(|existing| existing == entity_path),
+            "{} missing from {:?}",
+            entity_path,
+            entity_paths
+        );
+    }
+}
+
+#[wasm_bindgen_test]
+fn query_meta() {
+    let recording = recording();
+    let meta = recording
+        .query_meta("/meta")
+        .map_err(JsValue::from)
+        .unwrap();
+    assert_eq!(meta.len(), 1);
+    assert_eq!(meta[0].entity_path, "/meta");
+    assert!(!meta[0].text.is_empty());
+
+    let all = recording.query_meta("").map_err(JsValue::from).unwrap();
+    let mut entity_paths: Vec<&str> = all.iter().map(|meta| meta.entity_path.as_str()).collect();
+    entity_paths.sort();
+    assert_eq!(entity_paths, ["/meta", "/observations/joints/names"]);
+
+    assert!(recording.query_meta("/missing").is_err());
+}
+
+#[wasm_bindgen_test]
+fn scalar_series() {
+    let recording = recording();
+    let series = recording
+        .scalar_series("/action/gripper/left/position", "log_time")
+        .map_err(JsValue::from)
+        .unwrap();
+    assert_eq!(series.length(), 1203);
+    let times = series.times();
+    assert_eq!(times.len(), 1203);
+    assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(times[0], 1724298625944923000);
+    assert_eq!(times[1202], 1724298628949561000);
+    assert_eq!(series.values().len(), 1203);
+
+    // Tensors and parent paths are not scalar series
+    assert!(recording
+        .scalar_series("/action/joints/position", "log_time")
+        .is_err());
+    assert!(recording
+        .scalar_series("/action/gripper", "log_time")
+        .is_err());
+}
@@ -0,0 +1,88 @@
+//! The WebDataset export of the recordings of `examples/data`, read back member by member.
+#![cfg(feature = "export")]
+
+mod common;
+
+use std::{fs::File, io::Read};
+
+use rerun_query::export_webdataset;
+use serde_json::Value;
+use tar::Archive;
+
+use common::{log_times, GRIPPER_RRD};
+
+#[test]
+fn webdataset_export_lists_samples() {
+    let dir = tempfile::tempdir().unwrap();
+    let out_dir = dir.path().to_str().unwrap();
+    let shards = export_webdataset(
+        GRIPPER_RRD,
+        out_dir,
+        "",
+        "/action/gripper",
+        30,
+        "log_time",
+        40,
+    )
+    .unwrap();
+    let names: Vec<&str> = shards
+        .iter()
+        .map(|shard| shard.rsplit('/').next().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "robot_action_gripper-000000.tar",
+            "robot_action_gripper-000001.tar",
+            "robot_action_gripper-000002.tar",
+        ]
+    );
+
+    let start = log_times(GRIPPER_RRD, "/action/gripper/left/position")[0]
+        .max(log_times(GRIPPER_RRD, "/action/gripper/right/position")[0]);
+    let mut num_samples = 0;
+    for shard in &shards {
+        let mut archive = Archive::new(File::open(shard).unwrap());
+        let mut members = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes).unwrap();
+            members.push((name, bytes));
+        }
+
+        // The files of a sample follow each other, the JSON first
+        for sample in members.chunks(3) {
+            let key = format!("{:09}", num_samples);
+            let names: Vec<&str> = sample.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(
+                names,
+                [
+                    format!("{}.json", key),
+                    format!("{}.action.gripper.left.position.npy", key),
+                    format!("{}.action.gripper.right.position.npy", key),
+                ]
+            );
+
+            let info: Value = serde_json::from_slice(&sample[0].1).unwrap();
+            assert_eq!(info["frame_index"], num_samples);
+            let time = info["log_time"].as_i64().unwrap();
+            assert_eq!(
+                time - start,
+                (num_samples as f64 * 1e9 / 30.0).round() as i64
+            );
+
+            for (_, npy) in &sample[1..] {
+                assert!(npy.starts_with(b"\x93NUMPY"));
+                let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+                let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+                assert!(header.contains("'descr': '<f4'"), "{}", header);
+                assert!(header.contains("'shape': (1,)"), "{}", header);
+                assert_eq!(npy.len(), 10 + header_len + 4);
+            }
+            num_samples += 1;
+        }
+    }
+    assert_eq!(num_samples, 91);
+}
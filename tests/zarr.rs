@@ -0,0 +1,76 @@
+//! The Zarr export, read back and checked against the queries on the original files.
+#![cfg(feature = "export")]
+
+mod common;
+
+use std::{fs, io::Read};
+
+use rerun_query::export_zarr;
+use serde_json::Value;
+
+use common::{decode_le, tensor_rows, JOINTS, SAMPLE_RRD};
+
+#[test]
+fn zarr_export_reads_back() {
+    let expected = tensor_rows(SAMPLE_RRD, JOINTS);
+    let num_rows = expected.len();
+    let chunk_rows = 1000;
+
+    for (zarr_format, compression) in [(2, "zstd"), (3, "gzip")] {
+        let dir = tempfile::tempdir().unwrap();
+        let out_dir = dir.path().to_str().unwrap();
+        let written = export_zarr(
+            SAMPLE_RRD,
+            out_dir,
+            JOINTS,
+            zarr_format,
+            chunk_rows,
+            compression,
+        )
+        .unwrap();
+        assert!(written
+            .iter()
+            .any(|path| path == "action/joints/position/TensorData"));
+
+        let array_dir = dir.path().join("action/joints/position/TensorData");
+        let (metadata_file, chunk_key): (&str, fn(usize) -> String) = match zarr_format {
+            2 => (".zarray", |index| format!("{}.0", index)),
+            _ => ("zarr.json", |index| format!("c/{}/0", index)),
+        };
+        let metadata: Value =
+            serde_json::from_slice(&fs::read(array_dir.join(metadata_file)).unwrap()).unwrap();
+        assert_eq!(
+            metadata["shape"],
+            serde_json::json!([num_rows, expected[0].len()])
+        );
+
+        let mut values = Vec::new();
+        for index in 0..num_rows.div_ceil(chunk_rows) {
+            let encoded = fs::read(array_dir.join(chunk_key(index))).unwrap();
+            let decoded = match compression {
+                "zstd" => zstd::decode_all(encoded.as_slice()).unwrap(),
+                _ => {
+                    let mut decoded = Vec::new();
+                    flate2::read::GzDecoder::new(encoded.as_slice())
+                        .read_to_end(&mut decoded)
+                        .unwrap();
+                    decoded
+                }
+            };
+            values.extend(decode_le(&element_type(&metadata), &decoded));
+        }
+        // The last chunk is padded to `chunk_rows` rows
+        values.truncate(expected.concat().len());
+        assert_eq!(values, expected.concat());
+    }
+}
+
+/// The NumPy type string of a v2 or v3 array.
+fn element_type(metadata: &Value) -> String {
+    match (&metadata["dtype"], &metadata["data_type"]) {
+        (Value::String(dtype), _) => dtype.clone(),
+        (_, Value::String(data_type)) if data_type == "float32" => "<f4".to_string(),
+        (_, Value::String(data_type)) if data_type == "float64" => "<f8".to_string(),
+        other => panic!("Unexpected element type {:?}", other),
+    }
+}